@@ -0,0 +1,41 @@
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) color: vec4<f32>,
+}
+
+struct ColorGradeUniform
+{
+    tint: vec4<f32>,
+    saturation: f32,
+    contrast: f32,
+    brightness: f32
+}
+
+@group(1) @binding(0)
+var t: texture_2d<f32>;
+
+@group(1) @binding(1)
+var s: sampler;
+
+@group(3) @binding(0)
+var<uniform> grade: ColorGradeUniform;
+
+/*
+    Tint, then saturation, contrast and brightness.
+    tint = 1, saturation = 1, contrast = 1, brightness = 0 is the identity
+*/
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32>
+{
+    let screen = textureSample(t, s, in.uv);
+
+    var rgb = screen.rgb * grade.tint.rgb;
+
+    let luma = dot(rgb, vec3f(0.2126, 0.7152, 0.0722));
+    rgb = mix(vec3f(luma), rgb, grade.saturation);
+    rgb = (rgb - 0.5) * grade.contrast + 0.5;
+    rgb += grade.brightness;
+
+    return vec4f(clamp(rgb, vec3f(0.0), vec3f(1.0)), screen.a);
+}
@@ -10,14 +10,20 @@ struct PeriscopeUniform
     width: f32
 }
 
+@group(1) @binding(0)
+var t: texture_2d<f32>;
+
+@group(1) @binding(1)
+var s: sampler;
+
 @group(3) @binding(0)
 var<uniform> ps: PeriscopeUniform;
 
 /*
     Acts as a spotlight around given position
-    In reality, it darkest everything around it
+    In reality, it darkens everything around it
 
-    Blend mode should be on multiply
+    Runs as a post-processing pass over the rendered screen in `t`
 */
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32>
@@ -40,6 +46,6 @@ fn fs_main(in: VertexOutput) -> @location(0) vec4<f32>
         }
     }
 
-    // let out = vec4<f32>(col, 1.0);
-    return vec4f(col, 0.0);
-}
\ No newline at end of file
+    let screen = textureSample(t, s, in.uv);
+    return vec4f(screen.rgb * col, screen.a);
+}
@@ -0,0 +1,36 @@
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) color: vec4<f32>,
+}
+
+struct VignetteUniform
+{
+    strength: f32,
+    radius: f32
+}
+
+@group(1) @binding(0)
+var t: texture_2d<f32>;
+
+@group(1) @binding(1)
+var s: sampler;
+
+@group(3) @binding(0)
+var<uniform> vignette: VignetteUniform;
+
+/*
+    Darkens the screen toward its edges, starting at `radius`
+    (a fraction of the center-to-corner distance)
+*/
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32>
+{
+    let screen = textureSample(t, s, in.uv);
+
+    // 0.0 at the center, 1.0 at a corner
+    let d = length(in.uv - vec2f(0.5)) / length(vec2f(0.5));
+    let falloff = smoothstep(vignette.radius, 1.0, d) * vignette.strength;
+
+    return vec4f(screen.rgb * (1.0 - falloff), screen.a);
+}
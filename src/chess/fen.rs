@@ -0,0 +1,268 @@
+//! Forsyth–Edwards Notation, which writes a position on one line, like
+//! `rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1`
+
+// local imports
+use super::{Board, Castling, Cell, ChessError, Piece, PieceColor, PieceType};
+
+impl Board
+{
+    /// The position written in `fen`, with no moves to take back
+    pub fn from_fen(fen: &str) -> Result<Board, ChessError>
+    {
+        let error = |message: String| ChessError(format!("bad FEN: {message}"));
+
+        let fields: Vec<&str> = fen.split_whitespace().collect();
+        let &[placement, to_move, castling, en_passant, halfmove_clock, fullmove_number] = &fields[..]
+        else
+        {
+            return Err(error(format!("expected 6 fields separated by spaces, found {}", fields.len())));
+        };
+
+        let mut board = Board::empty();
+
+        let ranks: Vec<&str> = placement.split('/').collect();
+        if ranks.len() != 8
+        {
+            return Err(error(format!("the placement `{placement}` has {} ranks, not 8", ranks.len())));
+        }
+        // the placement goes from rank 8 down to rank 1
+        for (rank, text) in (0..8u8).rev().zip(ranks)
+        {
+            let mut file = 0u32;
+            for c in text.chars()
+            {
+                if let Some(empty) = c.to_digit(10).filter(|n| (1..=8).contains(n))
+                {
+                    file += empty;
+                }
+                else if let Some(piece_type) = PieceType::from_letter(c)
+                {
+                    let piece_color = if c.is_ascii_uppercase() { PieceColor::Light } else { PieceColor::Dark };
+                    // past the eighth file is an error once the rank is counted
+                    if let Some(cell) = u8::try_from(file).ok().and_then(|file| Cell::new(file, rank))
+                    {
+                        board.cells[cell.index()] = Some(Piece::new(piece_type, piece_color));
+                    }
+                    file += 1;
+                }
+                else
+                {
+                    return Err(error(format!("`{c}` in rank {} is not a piece letter nor a count of empty cells", rank + 1)));
+                }
+            }
+            if file != 8
+            {
+                return Err(error(format!("rank {} `{text}` covers {file} cells, not 8", rank + 1)));
+            }
+        }
+
+        board.to_move = match to_move
+        {
+            "w" => PieceColor::Light,
+            "b" => PieceColor::Dark,
+            _ => return Err(error(format!("the side to move is `{to_move}`, not w or b"))),
+        };
+
+        if castling != "-"
+        {
+            for c in castling.chars()
+            {
+                let (color, king_side) = match c
+                {
+                    'K' => (PieceColor::Light, true),
+                    'Q' => (PieceColor::Light, false),
+                    'k' => (PieceColor::Dark, true),
+                    'q' => (PieceColor::Dark, false),
+                    _ => return Err(error(format!("`{c}` in the castling rights `{castling}` is not one of KQkq"))),
+                };
+                let right = board.castling.get_mut(color, king_side);
+                if *right
+                {
+                    return Err(error(format!("the castling rights `{castling}` repeat `{c}`")));
+                }
+                *right = true;
+
+                let rank = color.home_rank();
+                let (king, rook) = (Cell(rank * 8 + 4), Cell(rank * 8 + if king_side { 7 } else { 0 }));
+                if board.piece_at(king) != Some(Piece::new(PieceType::King, color)) || board.piece_at(rook) != Some(Piece::new(PieceType::Rook, color))
+                {
+                    return Err(error(format!("castling `{c}` needs a king on {king} and a rook on {rook}")));
+                }
+            }
+        }
+
+        if en_passant != "-"
+        {
+            let cell: Cell = en_passant.parse().map_err(|_| error(format!("the en passant cell `{en_passant}` is not a cell, like e3")))?;
+            if let Some(piece) = board.piece_at(cell)
+            {
+                return Err(error(format!("the en passant cell {cell} has a {:?} {:?} on it", piece.piece_color, piece.piece_type)));
+            }
+            // the pawn that skipped it is one cell further along, and behind it is where it came from
+            let mover = board.to_move.opponent();
+            let (pawn, start) = (cell.offset(0, mover.forward()), cell.offset(0, -mover.forward()));
+            let skipped_rank = (mover.home_rank() as i8 + 2 * mover.forward()) as u8;
+            if cell.rank() != skipped_rank
+                || pawn.and_then(|at| board.piece_at(at)) != Some(Piece::new(PieceType::Pawn, mover))
+                || start.and_then(|at| board.piece_at(at)).is_some()
+            {
+                return Err(error(format!("no {mover:?} pawn just skipped over the en passant cell {cell}")));
+            }
+            board.en_passant = Some(cell);
+        }
+
+        board.halfmove_clock = halfmove_clock.parse().map_err(|_| error(format!("the halfmove clock `{halfmove_clock}` is not a whole number")))?;
+        board.fullmove_number = match fullmove_number.parse()
+        {
+            Ok(n) if n >= 1 => n,
+            _ => return Err(error(format!("the fullmove number `{fullmove_number}` is not a whole number from 1"))),
+        };
+
+        for color in [PieceColor::Light, PieceColor::Dark]
+        {
+            let kings = Cell::all().filter(|&cell| board.piece_at(cell) == Some(Piece::new(PieceType::King, color))).count();
+            if kings != 1
+            {
+                return Err(error(format!("{color:?} has {kings} kings, not 1")));
+            }
+        }
+        if let Some(cell) = Cell::all().find(|&cell| (cell.rank() == 0 || cell.rank() == 7) && board.piece_at(cell).is_some_and(|p| p.piece_type == PieceType::Pawn))
+        {
+            return Err(error(format!("a pawn is on {cell}, on the first or last rank")));
+        }
+        if board.in_check(board.to_move.opponent())
+        {
+            return Err(error(format!("{:?} is in check, but it's {:?}'s move", board.to_move.opponent(), board.to_move)));
+        }
+
+        Ok(board)
+    }
+
+    /// The position in FEN
+    pub fn to_fen(&self) -> String
+    {
+        let mut placement = String::new();
+        for rank in (0..8).rev()
+        {
+            let mut empty = 0;
+            for file in 0..8
+            {
+                match self.piece_at(Cell(rank * 8 + file))
+                {
+                    None => empty += 1,
+                    Some(piece) =>
+                    {
+                        if empty > 0
+                        {
+                            placement.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        placement.push(piece.letter());
+                    },
+                }
+            }
+            if empty > 0
+            {
+                placement.push_str(&empty.to_string());
+            }
+            if rank > 0
+            {
+                placement.push('/');
+            }
+        }
+
+        let to_move = match self.to_move
+        {
+            PieceColor::Light => "w",
+            PieceColor::Dark => "b",
+        };
+
+        let Castling { light_king_side, light_queen_side, dark_king_side, dark_queen_side } = self.castling;
+        let castling: String =
+            [(light_king_side, 'K'), (light_queen_side, 'Q'), (dark_king_side, 'k'), (dark_queen_side, 'q')]
+            .into_iter()
+            .filter_map(|(right, letter)| right.then_some(letter))
+            .collect();
+        let castling = if castling.is_empty() { "-".to_string() } else { castling };
+
+        let en_passant = self.en_passant.map_or("-".to_string(), |cell| cell.to_string());
+
+        format!("{placement} {to_move} {castling} {en_passant} {} {}", self.halfmove_clock, self.fullmove_number)
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+    use crate::chess::test::perft;
+
+    const START: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+    const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+    const ENDGAME: &str = "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1";
+    const MIRRORED: &str = "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1";
+    const PROMOTING: &str = "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8";
+    const SICILIAN: &str = "rnbqkbnr/pp1ppppp/8/2p5/4P3/8/PPPP1PPP/RNBQKBNR w KQkq c6 0 2";
+
+    #[test]
+    fn standard_positions_round_trip()
+    {
+        for fen in [START, KIWIPETE, ENDGAME, MIRRORED, PROMOTING, SICILIAN]
+        {
+            assert_eq!(Board::from_fen(fen).unwrap().to_fen(), fen);
+        }
+        assert_eq!(Board::default().to_fen(), START);
+    }
+
+    #[test]
+    fn parsed_positions_play_by_the_rules()
+    {
+        // move counts known from other engines
+        assert_eq!(perft(&mut Board::from_fen(KIWIPETE).unwrap(), 3), 97_862);
+        assert_eq!(perft(&mut Board::from_fen(ENDGAME).unwrap(), 4), 43_238);
+        assert_eq!(perft(&mut Board::from_fen(MIRRORED).unwrap(), 3), 9_467);
+        assert_eq!(perft(&mut Board::from_fen(PROMOTING).unwrap(), 3), 62_379);
+    }
+
+    #[test]
+    fn moves_keep_the_fields_up_to_date()
+    {
+        let mut board = Board::default();
+        for (mv, fen) in [
+            ("e2e4", "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"),
+            ("c7c5", SICILIAN),
+            ("g1f3", "rnbqkbnr/pp1ppppp/8/2p5/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2"),
+            ("b8c6", "r1bqkbnr/pp1ppppp/2n5/2p5/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3"),
+            ("e1e2", "r1bqkbnr/pp1ppppp/2n5/2p5/4P3/5N2/PPPPKPPP/RNBQ1B1R b kq - 3 3"),
+        ]
+        {
+            board.make_move(board.parse_move(mv).unwrap());
+            assert_eq!(board.to_fen(), fen);
+        }
+    }
+
+    #[test]
+    fn errors_say_what_is_wrong()
+    {
+        let error = |fen: &str| Board::from_fen(fen).unwrap_err().to_string();
+
+        assert_eq!(error("8/8/8/8 w - - 0 1"), "bad FEN: the placement `8/8/8/8` has 4 ranks, not 8");
+        assert_eq!(error(&START.replace(" 0 1", "")), "bad FEN: expected 6 fields separated by spaces, found 4");
+        assert_eq!(error(&START.replace("pppppppp", "ppppppppp")), "bad FEN: rank 7 `ppppppppp` covers 9 cells, not 8");
+        assert_eq!(error(&START.replace("/8/8/8/8/", "/8/8/3x4/8/")), "bad FEN: `x` in rank 4 is not a piece letter nor a count of empty cells");
+        assert_eq!(error(&START.replace(" w ", " white ")), "bad FEN: the side to move is `white`, not w or b");
+        assert_eq!(error(&START.replace("KQkq", "KQkx")), "bad FEN: `x` in the castling rights `KQkx` is not one of KQkq");
+        assert_eq!(error(&START.replace("KQkq", "KKq")), "bad FEN: the castling rights `KKq` repeat `K`");
+        assert_eq!(error(&ENDGAME.replace(" - - ", " K - ")), "bad FEN: castling `K` needs a king on e1 and a rook on h1");
+        assert_eq!(error(&START.replace(" - 0", " e9 0")), "bad FEN: the en passant cell `e9` is not a cell, like e3");
+        assert_eq!(error(&START.replace(" - 0", " e6 0")), "bad FEN: no Dark pawn just skipped over the en passant cell e6");
+        assert_eq!(
+            error("rnbqkbnr/pppppppp/8/8/4P3/4N3/PPPP1PPP/RNBQKB1R b KQkq e3 0 1"),
+            "bad FEN: the en passant cell e3 has a Light Knight on it");
+        assert_eq!(error(&START.replace(" 0 1", " -1 1")), "bad FEN: the halfmove clock `-1` is not a whole number");
+        assert_eq!(error(&START.replace(" 0 1", " 0 0")), "bad FEN: the fullmove number `0` is not a whole number from 1");
+        assert_eq!(error("8/8/8/8/8/8/8/K7 w - - 0 1"), "bad FEN: Dark has 0 kings, not 1");
+        assert_eq!(error("k6P/8/8/8/8/8/8/K7 w - - 0 1"), "bad FEN: a pawn is on h8, on the first or last rank");
+        assert_eq!(error("k7/8/8/8/8/8/8/R6K w - - 0 1"), "bad FEN: Dark is in check, but it's Light's move");
+    }
+}
@@ -0,0 +1,870 @@
+//! The rules of chess, kept apart from ggez so they can be played and tested on their own.
+
+use std::{fmt, str::FromStr};
+
+mod fen;
+
+/// Why a cell, move or position was refused
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChessError(pub String);
+
+impl fmt::Display for ChessError
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for ChessError {}
+
+/// A cell of the board, from a1 to h8
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Cell(u8);
+
+impl Cell
+{
+    /// The cell on `file` a to h and `rank` 1 to 8, counted from 0
+    pub fn new(file: u8, rank: u8) -> Option<Cell>
+    {
+        (file < 8 && rank < 8).then_some(Cell(rank * 8 + file))
+    }
+
+    pub fn file(self) -> u8
+    {
+        self.0 % 8
+    }
+
+    pub fn rank(self) -> u8
+    {
+        self.0 / 8
+    }
+
+    fn index(self) -> usize
+    {
+        self.0 as usize
+    }
+
+    /// The cell `files` right and `ranks` up, if it is on the board
+    fn offset(self, files: i8, ranks: i8) -> Option<Cell>
+    {
+        let file = self.file() as i8 + files;
+        let rank = self.rank() as i8 + ranks;
+        if (0..8).contains(&file) && (0..8).contains(&rank) { Cell::new(file as u8, rank as u8) } else { None }
+    }
+
+    fn all() -> impl Iterator<Item = Cell>
+    {
+        (0..64).map(Cell)
+    }
+}
+
+impl fmt::Display for Cell
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        write!(f, "{}{}", (b'a' + self.file()) as char, self.rank() + 1)
+    }
+}
+
+impl FromStr for Cell
+{
+    type Err = ChessError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err>
+    {
+        match s.as_bytes()
+        {
+            &[file @ b'a'..=b'h', rank @ b'1'..=b'8'] => Ok(Cell(8 * (rank - b'1') + file - b'a')),
+            _ => Err(ChessError(format!("`{s}` is not a cell, like e4"))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Piece
+{
+    pub piece_type: PieceType,
+    pub piece_color: PieceColor,
+}
+
+impl Piece
+{
+    pub fn new(piece_type: PieceType, piece_color: PieceColor) -> Self
+    {
+        Self { piece_type, piece_color }
+    }
+
+    /// Its letter, upper case for light and lower case for dark
+    pub fn letter(self) -> char
+    {
+        match self.piece_color
+        {
+            PieceColor::Light => self.piece_type.letter().to_ascii_uppercase(),
+            PieceColor::Dark => self.piece_type.letter(),
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PieceColor
+{
+    /// Moves first
+    #[default]
+    Light,
+    Dark
+}
+
+impl PieceColor
+{
+    pub fn opponent(self) -> PieceColor
+    {
+        match self
+        {
+            PieceColor::Light => PieceColor::Dark,
+            PieceColor::Dark => PieceColor::Light,
+        }
+    }
+
+    /// The way its pawns move, in ranks
+    fn forward(self) -> i8
+    {
+        match self
+        {
+            PieceColor::Light => 1,
+            PieceColor::Dark => -1,
+        }
+    }
+
+    /// The rank its pieces start on, counted from 0
+    fn home_rank(self) -> u8
+    {
+        match self
+        {
+            PieceColor::Light => 0,
+            PieceColor::Dark => 7,
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PieceType
+{
+    #[default]
+    Pawn,
+    Rook,
+    Bishop,
+    Knight,
+    Queen,
+    King,
+}
+
+impl PieceType
+{
+    /// What a pawn can promote to
+    pub const PROMOTIONS: [PieceType; 4] = [PieceType::Queen, PieceType::Rook, PieceType::Bishop, PieceType::Knight];
+
+    /// Its lower case letter
+    pub fn letter(self) -> char
+    {
+        match self
+        {
+            PieceType::Pawn => 'p',
+            PieceType::Rook => 'r',
+            PieceType::Bishop => 'b',
+            PieceType::Knight => 'n',
+            PieceType::Queen => 'q',
+            PieceType::King => 'k',
+        }
+    }
+
+    /// The piece of `letter`, in either case
+    pub fn from_letter(letter: char) -> Option<PieceType>
+    {
+        match letter.to_ascii_lowercase()
+        {
+            'p' => Some(PieceType::Pawn),
+            'r' => Some(PieceType::Rook),
+            'b' => Some(PieceType::Bishop),
+            'n' => Some(PieceType::Knight),
+            'q' => Some(PieceType::Queen),
+            'k' => Some(PieceType::King),
+            _ => None,
+        }
+    }
+}
+
+/// Which castles are still allowed, lost once the king or that rook moves
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Castling
+{
+    pub light_king_side: bool,
+    pub light_queen_side: bool,
+    pub dark_king_side: bool,
+    pub dark_queen_side: bool,
+}
+
+impl Castling
+{
+    pub const ALL: Castling = Castling { light_king_side: true, light_queen_side: true, dark_king_side: true, dark_queen_side: true };
+    pub const NONE: Castling = Castling { light_king_side: false, light_queen_side: false, dark_king_side: false, dark_queen_side: false };
+
+    /// The right of `color` to castle toward the h file, or the a file
+    fn get_mut(&mut self, color: PieceColor, king_side: bool) -> &mut bool
+    {
+        match (color, king_side)
+        {
+            (PieceColor::Light, true) => &mut self.light_king_side,
+            (PieceColor::Light, false) => &mut self.light_queen_side,
+            (PieceColor::Dark, true) => &mut self.dark_king_side,
+            (PieceColor::Dark, false) => &mut self.dark_queen_side,
+        }
+    }
+
+    fn get(mut self, color: PieceColor, king_side: bool) -> bool
+    {
+        *self.get_mut(color, king_side)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveKind
+{
+    Normal,
+    /// A pawn's first move of two cells, which it can be taken en passant after
+    DoublePush,
+    EnPassant,
+    /// The king's move of two cells; the rook is moved along with it
+    Castle,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Move
+{
+    pub from: Cell,
+    pub to: Cell,
+    pub promotion: Option<PieceType>,
+    pub kind: MoveKind,
+}
+
+impl fmt::Display for Move
+{
+    /// In long algebraic notation, like e2e4 or e7e8q
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        write!(f, "{}{}", self.from, self.to)?;
+        if let Some(promotion) = self.promotion
+        {
+            write!(f, "{}", promotion.letter())?;
+        }
+        Ok(())
+    }
+}
+
+/// What `make_move` needs to take a move back
+#[derive(Debug, Clone)]
+struct Undo
+{
+    mv: Move,
+    captured: Option<Piece>,
+    castling: Castling,
+    en_passant: Option<Cell>,
+    halfmove_clock: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status
+{
+    Playing,
+    /// The side to move is in check, but can get out of it
+    Check,
+    /// The side to move is in check and has no legal move, so it lost
+    Checkmate,
+    /// The side to move has no legal move but isn't in check, a draw
+    Stalemate,
+}
+
+/// A chess position, and the moves that led to it
+#[derive(Debug, Clone)]
+pub struct Board
+{
+    cells: [Option<Piece>; 64],
+    to_move: PieceColor,
+    castling: Castling,
+    /// The cell a pawn skipped over last move, where it can be taken en passant
+    en_passant: Option<Cell>,
+    /// Moves since the last capture or pawn move
+    halfmove_clock: u32,
+    /// Starts at 1, counting up after each of dark's moves
+    fullmove_number: u32,
+    history: Vec<Undo>,
+}
+
+impl Default for Board
+{
+    /// The starting position
+    fn default() -> Self
+    {
+        let back_rank = [PieceType::Rook, PieceType::Knight, PieceType::Bishop, PieceType::Queen, PieceType::King, PieceType::Bishop, PieceType::Knight, PieceType::Rook];
+        let mut board = Board::empty();
+        for (file, piece_type) in back_rank.into_iter().enumerate()
+        {
+            let file = file as u8;
+            board.cells[Cell(file).index()] = Some(Piece::new(piece_type, PieceColor::Light));
+            board.cells[Cell(8 + file).index()] = Some(Piece::new(PieceType::Pawn, PieceColor::Light));
+            board.cells[Cell(48 + file).index()] = Some(Piece::new(PieceType::Pawn, PieceColor::Dark));
+            board.cells[Cell(56 + file).index()] = Some(Piece::new(piece_type, PieceColor::Dark));
+        }
+        board.castling = Castling::ALL;
+        board
+    }
+}
+
+impl Board
+{
+    const KNIGHT_STEPS: [(i8, i8); 8] = [(1, 2), (2, 1), (2, -1), (1, -2), (-1, -2), (-2, -1), (-2, 1), (-1, 2)];
+    const KING_STEPS: [(i8, i8); 8] = [(0, 1), (1, 1), (1, 0), (1, -1), (0, -1), (-1, -1), (-1, 0), (-1, 1)];
+    const ROOK_DIRECTIONS: [(i8, i8); 4] = [(0, 1), (1, 0), (0, -1), (-1, 0)];
+    const BISHOP_DIRECTIONS: [(i8, i8); 4] = [(1, 1), (1, -1), (-1, -1), (-1, 1)];
+
+    /// No pieces, light to move and no castling
+    pub fn empty() -> Self
+    {
+        Self
+        {
+            cells: [None; 64],
+            to_move: PieceColor::Light,
+            castling: Castling::NONE,
+            en_passant: None,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+            history: Vec::new(),
+        }
+    }
+
+    pub fn piece_at(&self, cell: Cell) -> Option<Piece>
+    {
+        self.cells[cell.index()]
+    }
+
+    /// Whether the king of `color` is attacked
+    pub fn in_check(&self, color: PieceColor) -> bool
+    {
+        let king = Piece::new(PieceType::King, color);
+        Cell::all()
+        .find(|&cell| self.piece_at(cell) == Some(king))
+        .is_some_and(|cell| self.attacked(cell, color.opponent()))
+    }
+
+    pub fn status(&self) -> Status
+    {
+        let in_check = self.in_check(self.to_move);
+        match (self.legal_moves().is_empty(), in_check)
+        {
+            (false, false) => Status::Playing,
+            (false, true) => Status::Check,
+            (true, true) => Status::Checkmate,
+            (true, false) => Status::Stalemate,
+        }
+    }
+
+    /// Every move the side to move can make without leaving its king in check
+    pub fn legal_moves(&self) -> Vec<Move>
+    {
+        let color = self.to_move;
+        let mut scratch = Board { history: Vec::new(), ..*self };
+
+        self.pseudo_legal_moves()
+        .into_iter()
+        .filter(|&mv|
+        {
+            scratch.make_move(mv);
+            let legal = !scratch.in_check(color);
+            scratch.unmake_move();
+            legal
+        })
+        .collect()
+    }
+
+    /// The legal move written as `text` in long algebraic notation, like e2e4 or e7e8q
+    pub fn parse_move(&self, text: &str) -> Result<Move, ChessError>
+    {
+        // only ASCII can be sliced anywhere; anything else isn't a move anyway
+        let (from, to, promotion) = match text.len()
+        {
+            4 | 5 if text.is_ascii() => (text[0..2].parse::<Cell>(), text[2..4].parse::<Cell>(), text[4..].chars().next()),
+            _ => return Err(ChessError(format!("`{text}` is not a move, like e2e4 or e7e8q"))),
+        };
+        let (from, to) = (from?, to?);
+        let promotion = match promotion
+        {
+            None => None,
+            Some(letter) => match PieceType::from_letter(letter)
+            {
+                Some(piece_type) if PieceType::PROMOTIONS.contains(&piece_type) => Some(piece_type),
+                _ => return Err(ChessError(format!("a pawn can't promote to `{letter}`"))),
+            },
+        };
+
+        self.legal_moves()
+        .into_iter()
+        .find(|mv| mv.from == from && mv.to == to && mv.promotion == promotion)
+        .ok_or_else(|| ChessError(format!("{text} is not a legal move")))
+    }
+
+    /// Play `mv`, which should be one of `legal_moves`
+    pub fn make_move(&mut self, mv: Move)
+    {
+        let color = self.to_move;
+        let mut piece = self.cells[mv.from.index()].take().expect("a move starts on a piece");
+
+        let captured_at = match mv.kind
+        {
+            MoveKind::EnPassant => mv.to.offset(0, -color.forward()).expect("a pawn taken en passant is on the board"),
+            _ => mv.to,
+        };
+        let captured = self.cells[captured_at.index()].take();
+
+        if let Some(promotion) = mv.promotion
+        {
+            piece.piece_type = promotion;
+        }
+        self.cells[mv.to.index()] = Some(piece);
+
+        if mv.kind == MoveKind::Castle
+        {
+            let (rook_from, rook_to) = Board::castle_rook(mv);
+            self.cells[rook_to.index()] = self.cells[rook_from.index()].take();
+        }
+
+        self.history.push(Undo { mv, captured, castling: self.castling, en_passant: self.en_passant, halfmove_clock: self.halfmove_clock });
+
+        // a king gives up both castles; a rook leaving or taken in its corner gives up that one
+        if piece.piece_type == PieceType::King
+        {
+            *self.castling.get_mut(color, true) = false;
+            *self.castling.get_mut(color, false) = false;
+        }
+        for corner in [mv.from, mv.to]
+        {
+            for side in [PieceColor::Light, PieceColor::Dark]
+            {
+                if corner.rank() == side.home_rank() && (corner.file() == 0 || corner.file() == 7)
+                {
+                    *self.castling.get_mut(side, corner.file() == 7) = false;
+                }
+            }
+        }
+
+        self.en_passant = match mv.kind
+        {
+            MoveKind::DoublePush => mv.from.offset(0, color.forward()),
+            _ => None,
+        };
+        self.halfmove_clock = if captured.is_some() || piece.piece_type == PieceType::Pawn { 0 } else { self.halfmove_clock + 1 };
+        if color == PieceColor::Dark
+        {
+            self.fullmove_number += 1;
+        }
+        self.to_move = color.opponent();
+    }
+
+    /// Take back the last move made, returning it, or `None` if there is none
+    pub fn unmake_move(&mut self) -> Option<Move>
+    {
+        let Undo { mv, captured, castling, en_passant, halfmove_clock } = self.history.pop()?;
+        let color = self.to_move.opponent();
+
+        let mut piece = self.cells[mv.to.index()].take().expect("a move ends on a piece");
+        if mv.promotion.is_some()
+        {
+            piece.piece_type = PieceType::Pawn;
+        }
+        self.cells[mv.from.index()] = Some(piece);
+
+        let captured_at = match mv.kind
+        {
+            MoveKind::EnPassant => mv.to.offset(0, -color.forward()).expect("a pawn taken en passant is on the board"),
+            _ => mv.to,
+        };
+        self.cells[captured_at.index()] = captured;
+
+        if mv.kind == MoveKind::Castle
+        {
+            let (rook_from, rook_to) = Board::castle_rook(mv);
+            self.cells[rook_from.index()] = self.cells[rook_to.index()].take();
+        }
+
+        self.castling = castling;
+        self.en_passant = en_passant;
+        self.halfmove_clock = halfmove_clock;
+        if color == PieceColor::Dark
+        {
+            self.fullmove_number -= 1;
+        }
+        self.to_move = color;
+        Some(mv)
+    }
+
+    /// Where the rook of a castle moves from and to
+    fn castle_rook(mv: Move) -> (Cell, Cell)
+    {
+        let rank = mv.from.rank();
+        if mv.to.file() == 6
+        {
+            (Cell(rank * 8 + 7), Cell(rank * 8 + 5))
+        }
+        else
+        {
+            (Cell(rank * 8), Cell(rank * 8 + 3))
+        }
+    }
+
+    /// Whether any piece of `by` attacks `cell`
+    fn attacked(&self, cell: Cell, by: PieceColor) -> bool
+    {
+        let is = |at: Option<Cell>, types: &[PieceType]|
+            at.and_then(|at| self.piece_at(at)).is_some_and(|p| p.piece_color == by && types.contains(&p.piece_type));
+
+        // pawns attack forward, so look back from `cell` the way they move
+        [-1, 1].into_iter().any(|files| is(cell.offset(files, -by.forward()), &[PieceType::Pawn]))
+        || Board::KNIGHT_STEPS.into_iter().any(|(f, r)| is(cell.offset(f, r), &[PieceType::Knight]))
+        || Board::KING_STEPS.into_iter().any(|(f, r)| is(cell.offset(f, r), &[PieceType::King]))
+        || Board::ROOK_DIRECTIONS.into_iter().any(|d| is(self.slide_end(cell, d), &[PieceType::Rook, PieceType::Queen]))
+        || Board::BISHOP_DIRECTIONS.into_iter().any(|d| is(self.slide_end(cell, d), &[PieceType::Bishop, PieceType::Queen]))
+    }
+
+    /// The first piece's cell from `cell` toward `direction`
+    fn slide_end(&self, cell: Cell, (files, ranks): (i8, i8)) -> Option<Cell>
+    {
+        let mut at = cell.offset(files, ranks)?;
+        while self.piece_at(at).is_none()
+        {
+            at = at.offset(files, ranks)?;
+        }
+        Some(at)
+    }
+
+    /// Every move of the side to move, including those leaving its king in check
+    fn pseudo_legal_moves(&self) -> Vec<Move>
+    {
+        let color = self.to_move;
+        let mut moves = Vec::new();
+
+        for from in Cell::all()
+        {
+            let piece = match self.piece_at(from)
+            {
+                Some(piece) if piece.piece_color == color => piece,
+                _ => continue,
+            };
+            let mut push = |to: Cell, kind: MoveKind| moves.push(Move { from, to, promotion: None, kind });
+            let enemy_or_empty = |to: Cell| self.piece_at(to).is_none_or(|p| p.piece_color != color);
+
+            match piece.piece_type
+            {
+                PieceType::Pawn => self.pawn_moves(from, &mut moves),
+                PieceType::Knight | PieceType::King =>
+                {
+                    let steps = if piece.piece_type == PieceType::Knight { Board::KNIGHT_STEPS } else { Board::KING_STEPS };
+                    for to in steps.into_iter().filter_map(|(f, r)| from.offset(f, r)).filter(|&to| enemy_or_empty(to))
+                    {
+                        push(to, MoveKind::Normal);
+                    }
+                },
+                PieceType::Rook | PieceType::Bishop | PieceType::Queen =>
+                {
+                    let directions = match piece.piece_type
+                    {
+                        PieceType::Rook => &Board::ROOK_DIRECTIONS[..],
+                        PieceType::Bishop => &Board::BISHOP_DIRECTIONS[..],
+                        _ => &Board::KING_STEPS[..],
+                    };
+                    for &(files, ranks) in directions
+                    {
+                        let mut at = from.offset(files, ranks);
+                        while let Some(to) = at
+                        {
+                            if enemy_or_empty(to)
+                            {
+                                push(to, MoveKind::Normal);
+                            }
+                            if self.piece_at(to).is_some()
+                            {
+                                break;
+                            }
+                            at = to.offset(files, ranks);
+                        }
+                    }
+                },
+            }
+        }
+
+        self.castles(&mut moves);
+        moves
+    }
+
+    fn pawn_moves(&self, from: Cell, moves: &mut Vec<Move>)
+    {
+        let color = self.to_move;
+        let forward = color.forward();
+        let mut push = |to: Cell, kind: MoveKind|
+        {
+            // reaching the last rank, it must promote
+            if to.rank() == color.opponent().home_rank()
+            {
+                moves.extend(PieceType::PROMOTIONS.into_iter().map(|promotion| Move { from, to, promotion: Some(promotion), kind }));
+            }
+            else
+            {
+                moves.push(Move { from, to, promotion: None, kind });
+            }
+        };
+
+        if let Some(one) = from.offset(0, forward).filter(|&to| self.piece_at(to).is_none())
+        {
+            push(one, MoveKind::Normal);
+            let start_rank = (color.home_rank() as i8 + forward) as u8;
+            if let Some(two) = one.offset(0, forward).filter(|&to| from.rank() == start_rank && self.piece_at(to).is_none())
+            {
+                push(two, MoveKind::DoublePush);
+            }
+        }
+
+        for to in [-1, 1].into_iter().filter_map(|files| from.offset(files, forward))
+        {
+            if self.piece_at(to).is_some_and(|p| p.piece_color != color)
+            {
+                push(to, MoveKind::Normal);
+            }
+            else if self.en_passant == Some(to)
+            {
+                push(to, MoveKind::EnPassant);
+            }
+        }
+    }
+
+    /// Castles still allowed, with the cells between king and rook empty,
+    /// and the king not in check nor passing or landing on an attacked cell
+    fn castles(&self, moves: &mut Vec<Move>)
+    {
+        let color = self.to_move;
+        let rank = color.home_rank();
+        let king = Cell(rank * 8 + 4);
+        if self.piece_at(king) != Some(Piece::new(PieceType::King, color)) || self.attacked(king, color.opponent())
+        {
+            return;
+        }
+
+        for (king_side, between, passed) in [(true, 5..7, [5, 6]), (false, 1..4, [3, 2])]
+        {
+            let (rook_from, _) = Board::castle_rook(Move { from: king, to: Cell(rank * 8 + passed[1]), promotion: None, kind: MoveKind::Castle });
+            if self.castling.get(color, king_side)
+                && self.piece_at(rook_from) == Some(Piece::new(PieceType::Rook, color))
+                && between.map(|file| Cell(rank * 8 + file)).all(|cell| self.piece_at(cell).is_none())
+                && passed.into_iter().all(|file| !self.attacked(Cell(rank * 8 + file), color.opponent()))
+            {
+                moves.push(Move { from: king, to: Cell(rank * 8 + passed[1]), promotion: None, kind: MoveKind::Castle });
+            }
+        }
+    }
+}
+
+impl fmt::Display for Board
+{
+    /// The board from light's side, dark's pieces in lower case, then whose move it is
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        for rank in (0..8).rev()
+        {
+            write!(f, "{} ", rank + 1)?;
+            for file in 0..8
+            {
+                let letter = self.piece_at(Cell(rank * 8 + file)).map_or('.', Piece::letter);
+                write!(f, " {letter}")?;
+            }
+            writeln!(f)?;
+        }
+        writeln!(f, "   a b c d e f g h")?;
+        write!(f, "{:?} to move, move {}", self.to_move, self.fullmove_number)
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+
+    /// The number of move sequences `depth` moves long
+    pub(super) fn perft(board: &mut Board, depth: u32) -> u64
+    {
+        if depth == 0
+        {
+            return 1;
+        }
+        let mut nodes = 0;
+        for mv in board.legal_moves()
+        {
+            board.make_move(mv);
+            nodes += perft(board, depth - 1);
+            board.unmake_move();
+        }
+        nodes
+    }
+
+    fn cell(name: &str) -> Cell
+    {
+        name.parse().unwrap()
+    }
+
+    /// A board with only `pieces`, by letter and cell, and `to_move` to move
+    fn board(pieces: &[(char, &str)], to_move: PieceColor) -> Board
+    {
+        let mut board = Board::empty();
+        for &(letter, at) in pieces
+        {
+            let color = if letter.is_ascii_uppercase() { PieceColor::Light } else { PieceColor::Dark };
+            board.cells[cell(at).index()] = Some(Piece::new(PieceType::from_letter(letter).unwrap(), color));
+        }
+        board.to_move = to_move;
+        board
+    }
+
+    fn play(board: &mut Board, moves: &[&str])
+    {
+        for text in moves
+        {
+            let mv = board.parse_move(text).unwrap();
+            board.make_move(mv);
+        }
+    }
+
+    #[test]
+    fn perft_from_the_start()
+    {
+        let mut board = Board::default();
+        assert_eq!(board.legal_moves().len(), 20);
+        assert_eq!(perft(&mut board, 2), 400);
+        assert_eq!(perft(&mut board, 3), 8_902);
+        assert_eq!(perft(&mut board, 4), 197_281);
+    }
+
+    #[test]
+    fn unmaking_restores_the_position()
+    {
+        let mut board = Board::default();
+        let start = board.to_string();
+        play(&mut board, &["e2e4", "d7d5", "e4d5", "g8f6", "f1b5", "c7c6", "g1f3", "c6b5", "e1g1"]);
+        assert_eq!(board.piece_at(cell("g1")), Some(Piece::new(PieceType::King, PieceColor::Light)));
+        assert_eq!(board.piece_at(cell("f1")), Some(Piece::new(PieceType::Rook, PieceColor::Light)));
+        assert_eq!(board.castling, Castling { light_king_side: false, light_queen_side: false, ..Castling::ALL });
+
+        while board.unmake_move().is_some() {}
+        assert_eq!(board.to_string(), start);
+        assert_eq!((board.castling, board.halfmove_clock, board.fullmove_number), (Castling::ALL, 0, 1));
+    }
+
+    #[test]
+    fn castling_needs_rights_a_clear_path_and_no_attacks()
+    {
+        let castles = |board: &Board| -> Vec<String> { board.legal_moves().iter().filter(|mv| mv.kind == MoveKind::Castle).map(Move::to_string).collect() };
+
+        let mut open = board(&[('K', "e1"), ('R', "a1"), ('R', "h1"), ('k', "e8")], PieceColor::Light);
+        open.castling = Castling::ALL;
+        assert_eq!(castles(&open), vec!["e1g1", "e1c1"]);
+
+        // a knight between king and rook blocks the queen side, though it doesn't pass b1
+        let mut blocked = open.clone();
+        blocked.cells[cell("b1").index()] = Some(Piece::new(PieceType::Knight, PieceColor::Light));
+        assert_eq!(castles(&blocked), vec!["e1g1"]);
+
+        // a rook eyeing f1 stops the king passing it, but one eyeing b1 doesn't
+        let mut attacked = open.clone();
+        attacked.cells[cell("f8").index()] = Some(Piece::new(PieceType::Rook, PieceColor::Dark));
+        attacked.cells[cell("b8").index()] = Some(Piece::new(PieceType::Rook, PieceColor::Dark));
+        assert_eq!(castles(&attacked), vec!["e1c1"]);
+
+        // nor out of check
+        let mut checked = open.clone();
+        checked.cells[cell("e5").index()] = Some(Piece::new(PieceType::Rook, PieceColor::Dark));
+        assert!(castles(&checked).is_empty());
+
+        // moving the h rook gives up the king side for good
+        let mut moved = open.clone();
+        play(&mut moved, &["h1h2", "e8d8", "h2h1", "d8e8"]);
+        assert_eq!(castles(&moved), vec!["e1c1"]);
+    }
+
+    #[test]
+    fn en_passant_only_right_after_a_double_push()
+    {
+        let mut board = Board::default();
+        play(&mut board, &["e2e4", "a7a6", "e4e5", "d7d5"]);
+        let mv = board.parse_move("e5d6").unwrap();
+        assert_eq!(mv.kind, MoveKind::EnPassant);
+
+        board.make_move(mv);
+        assert_eq!(board.piece_at(cell("d5")), None);
+        board.unmake_move();
+        assert_eq!(board.piece_at(cell("d5")), Some(Piece::new(PieceType::Pawn, PieceColor::Dark)));
+
+        play(&mut board, &["g1f3", "h7h6"]);
+        assert!(board.parse_move("e5d6").is_err());
+    }
+
+    #[test]
+    fn en_passant_cannot_expose_the_king()
+    {
+        // taking would clear the rank between the rook and the king
+        let mut board = board(&[('K', "a5"), ('P', "b5"), ('p', "c7"), ('r', "h5"), ('k', "h8")], PieceColor::Dark);
+        play(&mut board, &["c7c5"]);
+        assert!(board.parse_move("b5c6").is_err());
+        assert!(board.parse_move("b5b6").is_ok());
+    }
+
+    #[test]
+    fn pawns_promote_to_any_piece_but_a_king()
+    {
+        let mut board = board(&[('K', "a1"), ('P', "g7"), ('r', "h8"), ('k', "a8")], PieceColor::Light);
+        let promotions: Vec<String> = board.legal_moves().iter().filter(|mv| mv.promotion.is_some()).map(Move::to_string).collect();
+        assert_eq!(promotions, vec!["g7g8q", "g7g8r", "g7g8b", "g7g8n", "g7h8q", "g7h8r", "g7h8b", "g7h8n"]);
+        assert!(board.parse_move("g7g8").is_err());
+        assert!(board.parse_move("g7g8k").is_err());
+
+        play(&mut board, &["g7h8n"]);
+        assert_eq!(board.piece_at(cell("h8")), Some(Piece::new(PieceType::Knight, PieceColor::Light)));
+        board.unmake_move();
+        assert_eq!(board.piece_at(cell("g7")), Some(Piece::new(PieceType::Pawn, PieceColor::Light)));
+        assert_eq!(board.piece_at(cell("h8")), Some(Piece::new(PieceType::Rook, PieceColor::Dark)));
+    }
+
+    #[test]
+    fn check_checkmate_and_stalemate()
+    {
+        let mut board = Board::default();
+        play(&mut board, &["f2f3", "e7e5", "g2g4"]);
+        assert_eq!(board.status(), Status::Playing);
+        play(&mut board, &["d8h4"]);
+        assert!(board.in_check(PieceColor::Light));
+        assert_eq!(board.status(), Status::Checkmate);
+
+        let check = self::board(&[('K', "e1"), ('q', "e7"), ('k', "a8")], PieceColor::Light);
+        assert_eq!(check.status(), Status::Check);
+        assert!(check.legal_moves().iter().all(|mv| mv.to.file() != 4));
+
+        let stalemate = self::board(&[('K', "a1"), ('q', "b3"), ('k', "h8")], PieceColor::Light);
+        assert_eq!(stalemate.status(), Status::Stalemate);
+    }
+
+    #[test]
+    fn cells_and_moves_are_named_like_e4()
+    {
+        assert_eq!(cell("e4"), Cell::new(4, 3).unwrap());
+        assert_eq!(cell("h8").to_string(), "h8");
+        assert!("i1".parse::<Cell>().is_err());
+        assert!("e9".parse::<Cell>().is_err());
+        assert!(Board::default().parse_move("e2e5").is_err());
+        assert!(Board::default().parse_move("e2").is_err());
+        assert!(Board::default().parse_move("eé2e").is_err());
+    }
+}
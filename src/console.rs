@@ -0,0 +1,372 @@
+use std::{path::Path, str::FromStr};
+
+use ggez::{glam::Vec2, GameError, GameResult};
+
+// local imports
+use crate::{chess::Board, debug_draw::Shown, replay::ReplayMode, world::World};
+
+/// What console commands act on: borrowed from `MainState` while playing,
+/// or made up by a test, so the console runs without a ggez context.
+pub struct Target<'a>
+{
+    world: &'a mut World,
+    /// Whether a replay is recording or playing, so the world mustn't change outside its ticks
+    world_locked: bool,
+    /// The camera's position, in world tiles
+    pub camera: &'a mut Vec2,
+    /// Which categories of debug drawings are drawn
+    pub debug_shown: &'a mut Shown,
+    /// How fast the fixed ticks run compared to real time
+    pub time_scale: &'a mut f32,
+    pub chess: &'a mut Board,
+    /// Where `save` and `load` keep their slots
+    pub save_dir: &'a Path,
+}
+
+impl<'a> Target<'a>
+{
+    pub fn new(world: &'a mut World, world_locked: bool, camera: &'a mut Vec2, debug_shown: &'a mut Shown, time_scale: &'a mut f32, chess: &'a mut Board, save_dir: &'a Path) -> Self
+    {
+        Self { world, world_locked, camera, debug_shown, time_scale, chess, save_dir }
+    }
+
+    pub fn world(&self) -> &World
+    {
+        self.world
+    }
+
+    /// The world, to change, unless a replay needs it left to its ticks
+    pub fn world_mut(&mut self) -> GameResult<&mut World>
+    {
+        if self.world_locked
+        {
+            return Err(GameError::CustomError(ReplayMode::WORLD_LOCKED.to_string()));
+        }
+        Ok(self.world)
+    }
+}
+
+/// The words of a command line after the command's name
+pub struct Args<'a>
+{
+    words: std::str::SplitWhitespace<'a>,
+}
+
+impl Args<'_>
+{
+    /// Parse the next word as `what`
+    pub fn next<T: FromStr>(&mut self, what: &str) -> GameResult<T>
+    {
+        let word = self.words.next().ok_or_else(|| GameError::CustomError(format!("missing {what}")))?;
+        word.parse().map_err(|_| GameError::CustomError(format!("`{word}` is not a valid {what}")))
+    }
+
+    /// Parse the next word as `on` or `off`
+    pub fn switch(&mut self) -> GameResult<bool>
+    {
+        match self.next::<String>("switch")?.as_str()
+        {
+            "on" => Ok(true),
+            "off" => Ok(false),
+            other => Err(GameError::CustomError(format!("`{other}` is not on or off"))),
+        }
+    }
+
+    /// The words left, separated by single spaces
+    pub fn rest(&mut self) -> String
+    {
+        self.words.by_ref().collect::<Vec<_>>().join(" ")
+    }
+
+    /// Fail if any words are left over
+    pub fn finish(&mut self) -> GameResult
+    {
+        match self.words.next()
+        {
+            Some(word) => Err(GameError::CustomError(format!("unexpected `{word}`"))),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A console command, registered by the part of the game it acts on
+#[derive(Debug, Clone, Copy)]
+pub struct Command
+{
+    /// The words that invoke it, e.g. `spawn enemy`
+    pub name: &'static str,
+    /// Its arguments, e.g. `<x> <y>`
+    pub args: &'static str,
+    pub help: &'static str,
+    /// Returns what to print in the console. It calls `Args::finish` before
+    /// changing anything, so a line with a bad argument changes nothing.
+    pub run: fn(&mut Args, &mut Target) -> GameResult<String>,
+}
+
+/// Runs command lines, from the console window or fed in as strings.
+#[derive(Debug, Clone, Default)]
+pub struct Console
+{
+    commands: Vec<Command>,
+    /// Every line run and everything printed, oldest first
+    history: Vec<String>,
+}
+
+impl Console
+{
+    /// Add `commands`, failing if any name is already taken
+    pub fn register(mut self, commands: impl IntoIterator<Item = Command>) -> GameResult<Self>
+    {
+        for command in commands
+        {
+            if command.name == "help" || self.commands.iter().any(|c| c.name == command.name)
+            {
+                return Err(GameError::CustomError(format!("console command `{}` is registered twice", command.name)));
+            }
+            self.commands.push(command);
+        }
+
+        Ok(self)
+    }
+
+    pub fn history(&self) -> &[String]
+    {
+        &self.history
+    }
+
+    /// Run a command line on `target`, returning what it printed.
+    /// The line and its output, or its error, are kept in the history.
+    pub fn run(&mut self, line: &str, target: &mut Target) -> GameResult<String>
+    {
+        let line = line.trim();
+        if line.is_empty()
+        {
+            return Ok(String::new());
+        }
+
+        self.history.push(format!("> {line}"));
+        let result = self.run_line(line, target);
+        match &result
+        {
+            Ok(output) if output.is_empty() => (),
+            Ok(output) => self.history.push(output.clone()),
+            Err(e) => self.history.push(format!("error: {e}")),
+        }
+        result
+    }
+
+    fn run_line(&self, line: &str, target: &mut Target) -> GameResult<String>
+    {
+        let words: Vec<&str> = line.split_whitespace().collect();
+
+        if words[0] == "help"
+        {
+            return self.help(&words[1..].join(" "));
+        }
+
+        // the longest name the line starts with, so `tile set` wins over a `tile`
+        let command =
+            self.commands
+            .iter()
+            .filter(|c| words.starts_with(&c.name.split_whitespace().collect::<Vec<_>>()))
+            .max_by_key(|c| c.name.len())
+            .ok_or_else(|| GameError::CustomError(format!("unknown command `{}`, try `help`", words[0])))?;
+
+        let rest = words[command.name.split_whitespace().count()..].join(" ");
+        let mut args = Args { words: rest.split_whitespace() };
+        (command.run)(&mut args, target)
+        .map_err(|e| GameError::CustomError(format!("{e}\nusage: {} {}", command.name, command.args)))
+    }
+
+    /// Every command, or those starting with `prefix`, with their arguments and help
+    fn help(&self, prefix: &str) -> GameResult<String>
+    {
+        let lines: Vec<String> =
+            self.commands
+            .iter()
+            .filter(|c| c.name.starts_with(prefix))
+            .map(|c| format!("{} {} - {}", c.name, c.args, c.help))
+            .collect();
+
+        if lines.is_empty()
+        {
+            return Err(GameError::CustomError(format!("no command starts with `{prefix}`")));
+        }
+        Ok(lines.join("\n"))
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+    use crate::{debug_draw::Category, game_object::{grid::{Chunk, ObjectType}, HasPosition}, MainState};
+
+    struct Headless
+    {
+        console: Console,
+        world: World,
+        world_locked: bool,
+        camera: Vec2,
+        debug_shown: Shown,
+        time_scale: f32,
+        chess: Board,
+        save_dir: std::path::PathBuf,
+    }
+
+    impl Headless
+    {
+        fn new(name: &str) -> Self
+        {
+            Self
+            {
+                console: MainState::console(),
+                world: World::new(0),
+                world_locked: false,
+                camera: Vec2::ZERO,
+                debug_shown: Shown::default(),
+                time_scale: 1.0,
+                chess: Board::default(),
+                save_dir: std::env::temp_dir().join(format!("chess_thing_console_{name}_{}", std::process::id())),
+            }
+        }
+
+        fn run(&mut self, line: &str) -> GameResult<String>
+        {
+            let mut target = Target::new(&mut self.world, self.world_locked, &mut self.camera, &mut self.debug_shown, &mut self.time_scale, &mut self.chess, &self.save_dir);
+            self.console.run(line, &mut target)
+        }
+    }
+
+    #[test]
+    fn commands_act_on_the_target()
+    {
+        let mut game = Headless::new("act");
+        game.world.enemies.clear();
+
+        game.run("spawn enemy 12 5").unwrap();
+        game.run("tile set 3 4 filled").unwrap();
+        game.run("camera goto -2 7.5").unwrap();
+        game.run("hitboxes on").unwrap();
+        game.run("debug draw paths on").unwrap();
+        game.run("timescale 0.25").unwrap();
+
+        assert_eq!(game.world.enemies.len(), 1);
+        assert_eq!(game.world.enemies.values().next().unwrap().position_get(), Vec2::new(12.0, 5.0));
+        let cell = Chunk::object_at_world_cell(&game.world.chunks, (3, 4).into()).unwrap();
+        assert!(matches!(cell.foreground_object, ObjectType::Filled));
+        assert_eq!(game.camera, Vec2::new(-2.0, 7.5));
+        assert!(game.debug_shown.get(Category::Hitboxes) && game.debug_shown.get(Category::Paths));
+        assert_eq!(game.time_scale, 0.25);
+    }
+
+    #[test]
+    fn bad_lines_are_errors_with_usage()
+    {
+        let mut game = Headless::new("bad");
+        game.run("chess move e2e4").unwrap();
+        let checksum = game.world.checksum();
+        let fen = game.chess.to_fen();
+
+        assert!(game.run("dance").is_err());
+        let Err(GameError::CustomError(message)) = game.run("tile set 3 four filled") else { panic!("four is not a number") };
+        assert!(message.contains("usage: tile set"));
+        assert!(game.run("timescale 0").is_err());
+
+        // a word too many fails before the command does anything
+        let extra =
+        [
+            "spawn enemy 12 5 junk", "tile set 3 4 filled junk", "camera goto -2 7.5 junk", "hitboxes on please",
+            "debug draw paths on junk", "timescale 0.25 junk", "save slot1 junk", "load slot1 junk", "ray 0 0 1 1 junk",
+            "chess new junk", "chess show junk", "chess moves junk", "chess move e7e5 junk", "chess undo junk",
+        ];
+        for line in extra
+        {
+            let Err(GameError::CustomError(message)) = game.run(line) else { panic!("`{line}` ran") };
+            assert!(message.contains("unexpected `"), "{line}: {message}");
+        }
+        assert_eq!(game.world.checksum(), checksum);
+        assert_eq!(game.chess.to_fen(), fen);
+        assert_eq!(game.camera, Vec2::ZERO);
+        assert_eq!(game.debug_shown, Shown::default());
+        assert_eq!(game.time_scale, 1.0);
+        assert!(!game.save_dir.exists());
+
+        // every line and error is kept
+        let lines = 4 + extra.len();
+        assert_eq!(game.console.history().iter().filter(|line| line.starts_with("> ")).count(), lines);
+        assert_eq!(game.console.history().iter().filter(|line| line.starts_with("error: ")).count(), lines - 1);
+    }
+
+    #[test]
+    fn help_lists_commands()
+    {
+        let mut game = Headless::new("help");
+
+        let help = game.run("help").unwrap();
+        assert!(help.contains("spawn enemy <x> <y>"));
+        assert!(help.contains("timescale"));
+        assert_eq!(game.run("help tile").unwrap().lines().count(), 1);
+    }
+
+    #[test]
+    fn chess_is_played_from_the_console()
+    {
+        let mut game = Headless::new("chess");
+
+        for mv in ["f2f3", "e7e5", "g2g4"]
+        {
+            game.run(&format!("chess move {mv}")).unwrap();
+        }
+        assert!(game.run("chess move e1e2").is_err());
+        assert!(game.run("chess move d8h4").unwrap().ends_with("Checkmate"));
+        assert!(game.run("chess moves").unwrap().starts_with("no legal moves"));
+
+        assert_eq!(game.run("chess undo").unwrap(), "took back d8h4");
+        assert_eq!(game.run("chess fen").unwrap(), "rnbqkbnr/pppp1ppp/8/4p3/6P1/5P2/PPPPP2P/RNBQKBNR b KQkq g3 0 2");
+        game.run("chess fen 4k3/8/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+        assert!(game.run("chess moves").unwrap().contains("e1g1"));
+        assert!(game.run("chess fen 4k3/8/8/8/8/8/8/4K2R w Q - 0 1").is_err());
+        game.run("chess new").unwrap();
+        assert_eq!(game.run("chess moves").unwrap().split_whitespace().count(), 20);
+        assert!(game.run("chess undo").is_err());
+    }
+
+    #[test]
+    fn save_and_load_a_slot()
+    {
+        let mut game = Headless::new("save");
+        game.run("tile set 1 1 filled").unwrap();
+        game.run("save slot1").unwrap();
+
+        game.run("tile set 1 1 none").unwrap();
+        game.run("load slot1").unwrap();
+        let cell = Chunk::object_at_world_cell(&game.world.chunks, (1, 1).into()).unwrap();
+        assert!(matches!(cell.foreground_object, ObjectType::Filled));
+
+        assert!(game.run("load ../escape").is_err());
+        std::fs::remove_dir_all(&game.save_dir).unwrap();
+    }
+
+    #[test]
+    fn a_locked_world_only_changes_through_ticks()
+    {
+        let mut game = Headless::new("locked");
+        game.run("save slot1").unwrap();
+        game.world_locked = true;
+        let enemies = game.world.enemies.len();
+
+        for line in ["spawn enemy 12 5", "tile set 1 1 filled", "load slot1"]
+        {
+            let Err(GameError::CustomError(message)) = game.run(line) else { panic!("`{line}` changed a locked world") };
+            assert!(message.contains(ReplayMode::WORLD_LOCKED));
+        }
+        assert_eq!(game.world.enemies.len(), enemies);
+        let cell = Chunk::object_at_world_cell(&game.world.chunks, (1, 1).into()).unwrap();
+        assert!(matches!(cell.foreground_object, ObjectType::None));
+
+        // reading it is still fine
+        game.run("save slot2").unwrap();
+        std::fs::remove_dir_all(&game.save_dir).unwrap();
+    }
+}
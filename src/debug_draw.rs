@@ -0,0 +1,286 @@
+use std::{cell::RefCell, str::FromStr};
+
+use ggez::{glam::Vec2, graphics::Color, GameError, GameResult};
+
+// local imports
+use crate::game_object::shape::Shape;
+
+/// What a debug drawing shows, so each kind can be shown or hidden on its own
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Category
+{
+    /// Collision regions
+    Hitboxes,
+    ChunkBorders,
+    /// Where missiles went and are heading
+    Paths,
+    /// Ray casts, like the cannon's aim
+    Rays,
+}
+
+impl Category
+{
+    pub const ALL: [Category; 4] = [Category::Hitboxes, Category::ChunkBorders, Category::Paths, Category::Rays];
+
+    pub fn name(self) -> &'static str
+    {
+        match self
+        {
+            Category::Hitboxes => "hitboxes",
+            Category::ChunkBorders => "chunks",
+            Category::Paths => "paths",
+            Category::Rays => "rays",
+        }
+    }
+}
+
+impl FromStr for Category
+{
+    type Err = GameError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err>
+    {
+        Category::ALL
+        .into_iter()
+        .find(|c| c.name() == s)
+        .ok_or_else(|| GameError::CustomError(format!("`{s}` is not a debug draw category")))
+    }
+}
+
+/// Which categories are drawn
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Shown([bool; Category::ALL.len()]);
+
+impl Shown
+{
+    pub fn get(&self, category: Category) -> bool
+    {
+        self.0[category as usize]
+    }
+
+    pub fn get_mut(&mut self, category: Category) -> &mut bool
+    {
+        &mut self.0[category as usize]
+    }
+}
+
+/// Something to draw, in world tiles
+#[derive(Debug, Clone, PartialEq)]
+pub enum Primitive
+{
+    Line { from: Vec2, to: Vec2 },
+    /// A line with a head at `to`
+    Arrow { from: Vec2, to: Vec2 },
+    Rect { min: Vec2, max: Vec2 },
+    Circle { center: Vec2, radius: f32 },
+    /// Text with its top left at `at`
+    Text { at: Vec2, text: String },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DebugItem
+{
+    pub category: Category,
+    pub primitive: Primitive,
+    pub color: Color,
+}
+
+/// A queue of debug drawings, pushed by any system and drawn in one batch on top of the scene.
+///
+/// Pushing only needs a shared reference, so draw systems can push too. The world's
+/// queue holds what its fixed systems pushed during the last tick; `MainState` has
+/// another for what is pushed each frame, emptied once drawn.
+#[derive(Debug, Clone, Default)]
+pub struct DebugDraw
+{
+    items: RefCell<Vec<DebugItem>>,
+}
+
+impl DebugDraw
+{
+    pub fn push(&self, category: Category, primitive: Primitive, color: Color)
+    {
+        self.items.borrow_mut().push(DebugItem { category, primitive, color });
+    }
+
+    pub fn line(&self, category: Category, from: Vec2, to: Vec2, color: Color)
+    {
+        self.push(category, Primitive::Line { from, to }, color);
+    }
+
+    pub fn arrow(&self, category: Category, from: Vec2, to: Vec2, color: Color)
+    {
+        self.push(category, Primitive::Arrow { from, to }, color);
+    }
+
+    pub fn rect(&self, category: Category, min: Vec2, max: Vec2, color: Color)
+    {
+        self.push(category, Primitive::Rect { min, max }, color);
+    }
+
+    pub fn circle(&self, category: Category, center: Vec2, radius: f32, color: Color)
+    {
+        self.push(category, Primitive::Circle { center, radius }, color);
+    }
+
+    pub fn text(&self, category: Category, at: Vec2, text: impl Into<String>, color: Color)
+    {
+        self.push(category, Primitive::Text { at, text: text.into() }, color);
+    }
+
+    /// The outline of `shape`
+    pub fn shape(&self, category: Category, shape: &Shape, color: Color)
+    {
+        match *shape
+        {
+            Shape::Aabb { min, max } => self.rect(category, min, max, color),
+            Shape::Circle { center, radius } => self.circle(category, center, radius, color),
+            _ =>
+            {
+                let outline = shape.outline();
+                for (i, &from) in outline.iter().enumerate()
+                {
+                    self.line(category, from, outline[(i + 1) % outline.len()], color);
+                }
+            },
+        }
+    }
+
+    /// The items of the categories in `shown`, in the order they were pushed
+    pub fn visible(&self, shown: &Shown) -> Vec<DebugItem>
+    {
+        self.items.borrow().iter().filter(|item| shown.get(item.category)).cloned().collect()
+    }
+
+    pub fn clear(&self)
+    {
+        self.items.borrow_mut().clear();
+    }
+}
+
+impl crate::Draw<DebugDraw> for crate::MainState
+{
+    /// Draw the world's and the frame's debug drawings, then forget the frame's
+    fn draw(&self, context: &mut ggez::Context, canvas: &mut ggez::graphics::Canvas) -> ggez::GameResult
+    {
+        use ggez::graphics;
+
+        let shown = &self.debug_state.debug_shown;
+        let mut items = self.world.debug_draw.visible(shown);
+        items.extend(self.debug_draw.visible(shown));
+        self.debug_draw.clear();
+
+        let to_screen = |p: Vec2| 16.0 * (p - self.world_pos);
+        let mut builder = graphics::MeshBuilder::new();
+        let mut texts = Vec::new();
+        let mut empty = true;
+
+        for DebugItem { primitive, color, .. } in items
+        {
+            match primitive
+            {
+                Primitive::Line { from, to } => { line(&mut builder, to_screen(from), to_screen(to), color)?; },
+                Primitive::Arrow { from, to } =>
+                {
+                    let (from, to) = (to_screen(from), to_screen(to));
+                    line(&mut builder, from, to, color)?;
+                    // a head a quarter as long as the arrow, up to 6 pixels
+                    let back = (from - to).clamp_length_max((from - to).length() / 4.0).clamp_length_max(6.0);
+                    line(&mut builder, to, to + Vec2::from_angle(0.5).rotate(back), color)?;
+                    line(&mut builder, to, to + Vec2::from_angle(-0.5).rotate(back), color)?;
+                },
+                Primitive::Rect { min, max } =>
+                {
+                    let (min, size) = (to_screen(min), 16.0 * (max - min));
+                    builder.rectangle(graphics::DrawMode::stroke(1.0), graphics::Rect::new(min.x, min.y, size.x, size.y), color)?;
+                },
+                Primitive::Circle { center, radius } =>
+                {
+                    builder.circle(graphics::DrawMode::stroke(1.0), to_screen(center), (16.0 * radius).max(1.0), 0.5, color)?;
+                },
+                Primitive::Text { at, text } =>
+                {
+                    texts.push((to_screen(at), text, color));
+                    continue;
+                },
+            }
+            empty = false;
+        }
+
+        if !empty
+        {
+            let mesh = graphics::Mesh::from_data(context, builder.build());
+            canvas.draw(&mesh, graphics::DrawParam::new());
+        }
+        for (at, text, color) in texts
+        {
+            canvas.draw(&graphics::Text::new(text), graphics::DrawParam::new().dest(at).color(color));
+        }
+
+        Ok(())
+    }
+}
+
+/// A line in screen pixels, skipping lines too short for the mesh builder
+fn line(builder: &mut ggez::graphics::MeshBuilder, from: Vec2, to: Vec2, color: Color) -> GameResult
+{
+    if from.distance_squared(to) > f32::EPSILON
+    {
+        builder.line(&[from, to], 1.0, color)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+    use crate::{game_object::missile::Missile, world::{TickInput, World}};
+
+    #[test]
+    fn only_shown_categories_are_visible()
+    {
+        let debug = DebugDraw::default();
+        debug.line(Category::Rays, Vec2::ZERO, Vec2::X, Color::WHITE);
+        debug.text(Category::ChunkBorders, Vec2::ZERO, "chunk", Color::WHITE);
+        debug.shape(Category::Hitboxes, &Shape::capsule(Vec2::ZERO, Vec2::Y, 0.5).unwrap(), Color::MAGENTA);
+
+        let mut shown = Shown::default();
+        assert!(debug.visible(&shown).is_empty());
+
+        *shown.get_mut(Category::Rays) = true;
+        *shown.get_mut(Category::ChunkBorders) = true;
+        let visible: Vec<Category> = debug.visible(&shown).iter().map(|item| item.category).collect();
+        assert_eq!(visible, vec![Category::Rays, Category::ChunkBorders]);
+
+        *shown.get_mut(Category::Hitboxes) = true;
+        assert!(debug.visible(&shown).len() > 3);
+
+        debug.clear();
+        assert!(debug.visible(&shown).is_empty());
+    }
+
+    #[test]
+    fn fixed_systems_draw_without_a_context()
+    {
+        let mut shown = Shown::default();
+        *shown.get_mut(Category::Paths) = true;
+
+        let mut world = World::new(0);
+        world.missiles.push(Missile::new(Vec2::new(2.0, 2.0), Vec2::X));
+        world.step(&TickInput::default()).unwrap();
+        assert!(matches!(world.debug_draw.visible(&shown)[0].primitive, Primitive::Line { from, .. } if from == Vec2::new(2.0, 2.0)));
+
+        // the next tick replaces the last one's drawings
+        world.missiles.clear();
+        world.step(&TickInput::default()).unwrap();
+        assert!(world.debug_draw.visible(&shown).is_empty());
+    }
+
+    #[test]
+    fn categories_parse_by_name()
+    {
+        assert_eq!("chunks".parse::<Category>().unwrap(), Category::ChunkBorders);
+        assert!("everything".parse::<Category>().is_err());
+    }
+}
@@ -0,0 +1,279 @@
+use ggez::glam::Vec2;
+use serde::{Deserialize, Serialize};
+
+// local imports
+use crate::game_object::{grid::ObjectType, missile::Missile};
+
+/// A missile was fired this tick, and is spawned by the missile system
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MissileFired
+{
+    pub missile: Missile,
+}
+
+/// A cell's foreground object was set
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TileChanged
+{
+    /// World cell coordinates
+    pub cell: (i32, i32),
+    pub object: ObjectType,
+}
+
+/// An enemy was hit, and loses the health when the enemy system next runs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnemyDamaged
+{
+    /// Its id in `World::enemies`
+    pub enemy: u16,
+    pub amount: i32,
+}
+
+/// An enemy ran out of health and was removed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EnemyKilled
+{
+    /// The id it had in `World::enemies`
+    pub enemy: u16,
+    #[serde(with = "crate::util::vec_extension::_Vec2Ser")]
+    pub position: Vec2,
+}
+
+/// Any event, as kept in the event log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum GameEvent
+{
+    MissileFired(MissileFired),
+    TileChanged(TileChanged),
+    EnemyDamaged(EnemyDamaged),
+    EnemyKilled(EnemyKilled),
+}
+
+/// An event and the tick it was sent in
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoggedEvent
+{
+    pub tick: u64,
+    pub event: GameEvent,
+}
+
+/// The events of one type sent this tick and the tick before
+#[derive(Debug, Clone)]
+pub struct Channel<E>
+{
+    previous: Vec<E>,
+    current: Vec<E>,
+}
+
+impl<E> Default for Channel<E>
+{
+    fn default() -> Self
+    {
+        Self { previous: Vec::new(), current: Vec::new() }
+    }
+}
+
+impl<E> Channel<E>
+{
+    fn begin_tick(&mut self)
+    {
+        std::mem::swap(&mut self.previous, &mut self.current);
+        self.current.clear();
+    }
+}
+
+/// A type of event that can be sent through `Events`
+pub trait Event: Clone + Into<GameEvent>
+{
+    fn channel(events: &Events) -> &Channel<Self>;
+    fn channel_mut(events: &mut Events) -> &mut Channel<Self>;
+}
+
+macro_rules! event
+{
+    ($t:ident, $field:ident) =>
+    {
+        impl Event for $t
+        {
+            fn channel(events: &Events) -> &Channel<Self> { &events.$field }
+            fn channel_mut(events: &mut Events) -> &mut Channel<Self> { &mut events.$field }
+        }
+
+        impl From<$t> for GameEvent
+        {
+            fn from(event: $t) -> Self { GameEvent::$t(event) }
+        }
+    };
+}
+
+event!(MissileFired, missile_fired);
+event!(TileChanged, tile_changed);
+event!(EnemyDamaged, enemy_damaged);
+event!(EnemyKilled, enemy_killed);
+
+/// Typed events that fixed systems send each other during a tick.
+///
+/// A system sees the events sent earlier in the same tick by the systems
+/// ordered before it with `current`, and those sent during the previous tick
+/// with `previous`, so what it sees depends only on the schedule, never on
+/// timing. Events older than that are dropped.
+///
+/// Between ticks, only the events of the tick just done can still be seen, by
+/// the next one, so they are all that's serialized.
+#[derive(Debug, Clone, Default)]
+pub struct Events
+{
+    tick: u64,
+    missile_fired: Channel<MissileFired>,
+    tile_changed: Channel<TileChanged>,
+    enemy_damaged: Channel<EnemyDamaged>,
+    enemy_killed: Channel<EnemyKilled>,
+
+    /// Every event sent since logging started, if it has
+    log: Option<Vec<LoggedEvent>>,
+}
+
+impl Events
+{
+    /// Drop the events of the tick before last, making this tick's events the previous ones
+    pub fn begin_tick(&mut self, tick: u64)
+    {
+        self.tick = tick;
+        self.missile_fired.begin_tick();
+        self.tile_changed.begin_tick();
+        self.enemy_damaged.begin_tick();
+        self.enemy_killed.begin_tick();
+    }
+
+    pub fn send<E: Event>(&mut self, event: E)
+    {
+        if let Some(log) = &mut self.log
+        {
+            log.push(LoggedEvent { tick: self.tick, event: event.clone().into() });
+        }
+        E::channel_mut(self).current.push(event);
+    }
+
+    /// The events sent so far this tick
+    pub fn current<E: Event>(&self) -> &[E]
+    {
+        &E::channel(self).current
+    }
+
+    /// The events sent during the previous tick
+    pub fn previous<E: Event>(&self) -> &[E]
+    {
+        &E::channel(self).previous
+    }
+
+    /// Keep every event sent from now on, discarding any log so far
+    pub fn start_log(&mut self)
+    {
+        self.log = Some(Vec::new());
+    }
+
+    /// Every event sent since logging started, or none if it hasn't
+    pub fn log(&self) -> &[LoggedEvent]
+    {
+        self.log.as_deref().unwrap_or_default()
+    }
+
+    /// Stop logging, returning every event sent since logging started
+    pub fn take_log(&mut self) -> Vec<LoggedEvent>
+    {
+        self.log.take().unwrap_or_default()
+    }
+}
+
+impl Serialize for Events
+{
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    {
+        let last_tick: Vec<GameEvent> =
+            self.missile_fired.current.iter().cloned().map(GameEvent::from)
+            .chain(self.tile_changed.current.iter().cloned().map(GameEvent::from))
+            .chain(self.enemy_damaged.current.iter().cloned().map(GameEvent::from))
+            .chain(self.enemy_killed.current.iter().cloned().map(GameEvent::from))
+            .collect();
+        last_tick.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Events
+{
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error>
+    {
+        let mut events = Events::default();
+        for event in Vec::<GameEvent>::deserialize(deserializer)?
+        {
+            match event
+            {
+                GameEvent::MissileFired(event) => events.missile_fired.current.push(event),
+                GameEvent::TileChanged(event) => events.tile_changed.current.push(event),
+                GameEvent::EnemyDamaged(event) => events.enemy_damaged.current.push(event),
+                GameEvent::EnemyKilled(event) => events.enemy_killed.current.push(event),
+            }
+        }
+        Ok(events)
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+
+    fn killed(x: f32) -> EnemyKilled
+    {
+        EnemyKilled { enemy: 0, position: Vec2::new(x, 0.0) }
+    }
+
+    #[test]
+    fn events_last_until_the_end_of_the_next_tick()
+    {
+        let mut events = Events::default();
+        events.send(killed(1.0));
+        assert_eq!(events.current::<EnemyKilled>().len(), 1);
+        assert!(events.current::<EnemyDamaged>().is_empty());
+
+        events.begin_tick(1);
+        events.send(killed(2.0));
+        assert_eq!(events.previous::<EnemyKilled>()[0].position.x, 1.0);
+        assert_eq!(events.current::<EnemyKilled>()[0].position.x, 2.0);
+
+        events.begin_tick(2);
+        events.begin_tick(3);
+        assert!(events.previous::<EnemyKilled>().is_empty());
+    }
+
+    #[test]
+    fn saved_events_are_seen_by_the_next_tick()
+    {
+        let mut events = Events::default();
+        events.send(killed(1.0));
+        events.begin_tick(1);
+        events.send(killed(2.0));
+
+        let mut loaded: Events = postcard::from_bytes(&postcard::to_allocvec(&events).unwrap()).unwrap();
+        loaded.begin_tick(2);
+        assert_eq!(loaded.previous::<EnemyKilled>().len(), 1);
+        assert_eq!(loaded.previous::<EnemyKilled>()[0].position.x, 2.0);
+    }
+
+    #[test]
+    fn log_keeps_events_with_their_tick()
+    {
+        let mut events = Events::default();
+        events.send(killed(1.0));
+        events.start_log();
+        events.begin_tick(5);
+        events.send(killed(2.0));
+        events.send(EnemyDamaged { enemy: 0, amount: 3 });
+
+        let log = events.take_log();
+        assert_eq!(log.len(), 2);
+        assert!(log.iter().all(|logged| logged.tick == 5));
+        assert!(matches!(log[1].event, GameEvent::EnemyDamaged(EnemyDamaged { amount: 3, .. })));
+        assert!(events.take_log().is_empty());
+    }
+}
@@ -4,8 +4,10 @@ use std::f32::consts::PI;
 use ggez::glam::Vec2;
 use serde::{Deserialize, Serialize};
 
-use crate::{game_object::HasPosition, input::ActionCode, util::{message::Message, vec_extension::{Flip, RotateBy}}, MainState};
-use super::{has_position, missile::Missile};
+use ggegui::egui;
+
+use crate::{debug_draw::Category, events::MissileFired, game_object::HasPosition, gui::inspector::{edit_region, edit_vec2, Inspect}, input::ActionCode, util::vec_extension::{Flip, RotateBy}, MainState};
+use super::{collider_type::Selection, has_position, has_region, missile::Missile, raycast::{Layers, Ray}, shape::Shape, weapon::Armory, Region};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum RotateDir
@@ -14,6 +16,29 @@ pub enum RotateDir
     Right,
 }
 
+/// How the player turns the cannon
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AimMode
+{
+    /// Turn with the `TurnLeft` / `TurnRight` actions
+    #[default]
+    Keys,
+    /// Turn toward the mouse
+    Mouse,
+}
+
+impl AimMode
+{
+    pub fn toggled(self) -> Self
+    {
+        match self
+        {
+            AimMode::Keys => AimMode::Mouse,
+            AimMode::Mouse => AimMode::Keys,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Cannon
 {
@@ -23,25 +48,37 @@ pub struct Cannon
     #[serde(with = "crate::util::vec_extension::_Vec2Ser")]
     pub position: Vec2,
     rot_vel: f32,
-    refire_block: Message
+    #[serde(default)]
+    pub aim: AimMode,
+    pub armory: Armory,
+    selection_region: Region<Selection>,
 }
 
 has_position!(Cannon);
+has_region!(Cannon, selection_region, Selection);
 
 impl Default for Cannon
 {
     /// Spawn the cannon in the center of the screen
     fn default() -> Self {
         let center_pos = [0.0, 0.0].into();
-        Self { facing: Vec2::X, position: center_pos, rot_vel: 0.0, refire_block: Default::default() }
+        Self 
+        { 
+            facing: Vec2::X, 
+            position: center_pos, 
+            rot_vel: 0.0, 
+            aim: AimMode::default(), 
+            armory: Armory::default(), 
+            selection_region: Region::shaped(Shape::circle(Vec2::ZERO, 1.5).expect("the radius is positive")),
+        }
     }
 }
 
 impl Cannon
 {
-    const VELOCITY : f32 = 20.0;
-    const REFIRE_DELAY: f32 = 0.02; // FIXME: Express in ticks rather than float seconds for greater precision
     const BARREL_LENGTH: f32 = 3.0;
+    /// Tiles the aim line reaches when it hits nothing
+    const AIM_LINE_LENGTH: f32 = 48.0;
     
     /// constants relateed to rotation of cannon.
     /// Numbers seem to act twice as high as expected
@@ -49,7 +86,86 @@ impl Cannon
     const ROT_ACC: f32 = PI / 2.0;
     const ROT_DE_ACC: f32 = Cannon::MAX_ROT_PER_SEC * 2.0; // takes 0.5 second to brake to 0.
 
-    fn new(facing: Vec2, position: Vec2) -> Self
+    /// The rotation velocity at which braking with `ROT_DE_ACC` stops the cannon
+    /// exactly `angle` radians further on, capped at `MAX_ROT_PER_SEC`.
+    /// Turning at this speed toward a target never overshoots it.
+    pub(super) fn braking_rot_vel(angle: f32) -> f32
+    {
+        let speed = (2.0 * Cannon::ROT_DE_ACC * angle.abs()).sqrt();
+        angle.signum() * speed.min(Cannon::MAX_ROT_PER_SEC)
+    }
+
+    /// Accelerate the rotation velocity toward `target` for one tick,
+    /// or brake to a stop if `target` is zero.
+    fn steer_toward(&mut self, target: f32)
+    {
+        let mut new_rot_vel: f32 = 0.0;
+        if target != 0.0
+        {
+            // if the desired acceleration is in the opposite direction
+            // of movement, or the cannon is turning faster than desired,
+            // pick the maximum of ROT_ACC and ROT_DE_ACC to
+            // helpfully do the fastest turn
+            let sign = target.signum();
+            let mut acc = Cannon::ROT_ACC;
+            if self.rot_vel.signum() != sign || self.rot_vel.abs() > target.abs()
+            {
+                acc = f32::max(Cannon::ROT_ACC, Cannon::ROT_DE_ACC);
+            }
+
+            // accelerate toward the target speed without overshooting it
+            let step = acc * MainState::FIXED_PHYSICS_TIMESTEP;
+            new_rot_vel = self.rot_vel + (target - self.rot_vel).clamp(-step, step);
+        }
+        else
+        {
+            if self.rot_vel.abs() > 0.0
+            {
+                let sign = self.rot_vel.signum();
+                new_rot_vel = self.rot_vel - sign * Cannon::ROT_DE_ACC * MainState::FIXED_PHYSICS_TIMESTEP;
+                if new_rot_vel.signum() != sign { new_rot_vel = 0.0; }
+            }
+        }
+
+        self.rot_vel = new_rot_vel;
+    }
+
+    /// The rotation velocity that turns the short way round toward world point `target`,
+    /// slowing to stop on it. `facing` is y-flipped relative to the world.
+    pub(super) fn rot_vel_toward(&self, target: Vec2) -> f32
+    {
+        if target == self.position
+        {
+            return 0.0;
+        }
+
+        Cannon::braking_rot_vel(self.facing.angle_between((target - self.position).flip_y()))
+    }
+
+    /// Accelerate toward `target_rot_vel` and turn by the resulting velocity, for one tick
+    pub(super) fn turn(&mut self, target_rot_vel: f32)
+    {
+        self.steer_toward(target_rot_vel);
+        self.facing = self.facing.rotate_by(self.rot_vel * MainState::FIXED_PHYSICS_TIMESTEP);
+    }
+
+    /// Advance the weapons one tick with the trigger held or not, returning the missiles fired
+    pub(super) fn fire(&mut self, trigger: bool) -> Vec<Missile>
+    {
+        // the selected weapon decides whether the trigger fires this tick, and what
+        let spawn_pos = self.position + Cannon::BARREL_LENGTH * self.facing.flip_y();
+        self.armory
+        .tick(trigger)
+        .into_iter()
+        .map(|(angle, projectile)|
+        {
+            let missile_vel = projectile.speed * self.facing.rotate_by(angle).flip_y();
+            Missile::new(spawn_pos, missile_vel).behaviors(projectile.behaviors)
+        })
+        .collect()
+    }
+
+    pub(super) fn new(facing: Vec2, position: Vec2) -> Self
     {
         let mut s = Self::default();
         s.facing = facing;
@@ -59,59 +175,83 @@ impl Cannon
     }
 }
 
-impl crate::FixedUpdate<Cannon> for crate::MainState
+impl Inspect for Cannon
 {
-    fn fixed_update(&mut self, _context: &mut ggez::Context) -> ggez::GameResult {
-        let ref mut cannon = self.cannon;
+    fn inspect(&mut self, ui: &mut egui::Ui)
+    {
+        edit_vec2(ui, "position", &mut self.position);
 
-        // Handle potential fire-action (if unblocked)
-        // check that mouse is clicked & refire block is inactive
-        if self.key_input_state.held_actions.contains(&ActionCode::Shoot)
-        {
-            if let Message::Inactive = cannon.refire_block
+        // facing is y-flipped, so the angle shown turns the same way as the cannon
+        // and it's only written back when dragged, as the round trip through the angle drifts
+        let mut angle = self.facing.y.atan2(self.facing.x);
+        ui.horizontal(
+        |ui| {
+            ui.label("facing");
+            if ui.drag_angle(&mut angle).changed()
             {
-                // fire a missile
-                let missile_vel = Cannon::VELOCITY * cannon.facing.flip_y();
-                let spawn_pos = cannon.position + Cannon::BARREL_LENGTH * cannon.facing.flip_y();
-
-                let m = Missile::new(spawn_pos, missile_vel);
-                self.missiles.push(m);
+                self.facing = Vec2::from_angle(angle);
+            }
+        });
 
-                // set refire block with refire delay
-                cannon.refire_block.set_active_ticking((), Cannon::REFIRE_DELAY);
+        ui.horizontal(
+        |ui| {
+            ui.label("rotation velocity");
+            let mut rot_vel = self.rot_vel;
+            if ui.add(egui::DragValue::new(&mut rot_vel).speed(0.05).clamp_range(-Cannon::MAX_ROT_PER_SEC..=Cannon::MAX_ROT_PER_SEC)).changed()
+            {
+                self.rot_vel = rot_vel;
             }
+        });
+        ui.horizontal(
+        |ui| {
+            ui.label("aim");
+            ui.radio_value(&mut self.aim, AimMode::Keys, "keys");
+            ui.radio_value(&mut self.aim, AimMode::Mouse, "mouse");
+        });
+        ui.label(format!("weapon: {} ({})", self.armory.selected().def.name, self.armory.selected().status()));
+        edit_region(ui, "selection region", &mut self.selection_region);
+    }
+}
+
+impl crate::FixedUpdate<Cannon> for crate::World
+{
+    fn fixed_update(&mut self, input: &crate::world::TickInput) -> ggez::GameResult {
+        let ref mut cannon = self.cannon;
+
+        if input.actions.just_pressed(ActionCode::NextWeapon)
+        {
+            cannon.armory.cycle(1);
+        }
+        if input.actions.just_pressed(ActionCode::PrevWeapon)
+        {
+            cannon.armory.cycle(-1);
         }
 
-        let mut new_rot_vel: f32 = 0.0;
-        // match self.input_state.cannon_rotate
-        let left_turn = self.key_input_state.held_actions.contains(&ActionCode::TurnLeft);
-        let right_turn = self.key_input_state.held_actions.contains(&ActionCode::TurnRight);
-        // println!("{left_turn}_{right_turn}");
-        if left_turn || right_turn
+        for missile in cannon.fire(input.actions.is_held(ActionCode::Shoot))
         {
-            // if the desired acceleration is in the opposite direction
-            // of movement, pick the maximum of ROT_ACC and ROT_DE_ACC to
-            // helpfully do the fastest turn
-            let sign = if left_turn { 1.0 } else { -1.0 };
-            let mut acc = Cannon::ROT_ACC;
-            if cannon.rot_vel.signum() != sign
-            {
-                acc = f32::max(Cannon::ROT_ACC, Cannon::ROT_DE_ACC);
-            }
-            acc *= sign;
+            self.events.send(MissileFired { missile });
+        }
 
-            new_rot_vel = cannon.rot_vel + acc * MainState::FIXED_PHYSICS_TIMESTEP;
-            new_rot_vel = new_rot_vel.clamp(-Cannon::MAX_ROT_PER_SEC, Cannon::MAX_ROT_PER_SEC);
+        if input.actions.just_pressed(ActionCode::ToggleAimMode)
+        {
+            cannon.aim = cannon.aim.toggled();
         }
-        else
+
+        // match self.input_state.cannon_rotate
+        let target_rot_vel = match cannon.aim
         {
-            if cannon.rot_vel.abs() > 0.0
+            // digital turns are 1.0, a gamepad stick anywhere in between,
+            // so a stick turns at most as fast as it is pushed
+            AimMode::Keys =>
             {
-                let sign = cannon.rot_vel.signum();
-                new_rot_vel = cannon.rot_vel - sign * Cannon::ROT_DE_ACC * MainState::FIXED_PHYSICS_TIMESTEP;
-                if new_rot_vel.signum() != sign { new_rot_vel = 0.0; }
-            }
-        }
+                let actions = &input.actions;
+                let turn = actions.value(ActionCode::TurnLeft) - actions.value(ActionCode::TurnRight);
+                turn * Cannon::MAX_ROT_PER_SEC
+            },
+            // turn the short way round toward the mouse, slowing to stop on it
+            AimMode::Mouse => input.mouse_world().map_or(0.0, |mouse| cannon.rot_vel_toward(mouse)),
+        };
+        cannon.turn(target_rot_vel);
         // match (left_turn, right_turn)
         // {
         //     // Either left or right is being held
@@ -145,21 +285,17 @@ impl crate::FixedUpdate<Cannon> for crate::MainState
         //     },
         // };
 
-        cannon.rot_vel = new_rot_vel;
-        cannon.facing = cannon.facing.rotate_by(cannon.rot_vel * MainState::FIXED_PHYSICS_TIMESTEP);
-
-        cannon.refire_block.tick(MainState::FIXED_PHYSICS_TIMESTEP);
-
         Ok(())
     }
 }
 
-impl crate::Draw<Cannon> for crate::MainState
+impl crate::MainState
 {
-    fn draw(&self, _context: &mut ggez::Context, canvas: &mut ggez::graphics::Canvas) -> ggez::GameResult {
+    /// Draw `cannon` with the cannon sprite, marking its center with `center_color`
+    pub(super) fn draw_cannon(&self, cannon: &Cannon, center_color: ggez::graphics::Color, canvas: &mut ggez::graphics::Canvas)
+    {
         use ggez::graphics;
         
-        let ref cannon = self.cannon;
         let ref cannon_image = self.assets.cannon_image;
 
         let cannon_screen_pos = 16.0 * (cannon.position_get() - self.world_pos);
@@ -183,11 +319,151 @@ impl crate::Draw<Cannon> for crate::MainState
         let center_param = 
             graphics::DrawParam::new()
             .dest(cannon_screen_pos)
-            .color(graphics::Color::MAGENTA)
+            .color(center_color)
             .scale([2.0, 2.0]);
 
         canvas.draw(&center_dot, center_param);
+    }
+
+    /// Show where the cannon's aim first meets something, and which way that faces
+    fn debug_draw_aim(&self) -> ggez::GameResult
+    {
+        use ggez::graphics;
+
+        let cannon = &self.world.cannon;
+        let ray = Ray::new(cannon.position, cannon.facing.flip_y(), Cannon::AIM_LINE_LENGTH)?;
+        let hit = ray.cast(&self.world, Layers { missiles: false, ..Layers::ALL });
+        let end = hit.map_or(ray.at(ray.max_distance()), |hit| hit.point);
+
+        self.debug_draw.line(Category::Rays, ray.origin(), end, graphics::Color::from_rgba(255, 0, 255, 128));
+        if let Some(hit) = hit
+        {
+            self.debug_draw.arrow(Category::Rays, hit.point, hit.point + hit.normal, graphics::Color::WHITE);
+        }
+
+        Ok(())
+    }
+}
+
+impl crate::Draw<Cannon> for crate::MainState
+{
+    fn draw(&self, _context: &mut ggez::Context, canvas: &mut ggez::graphics::Canvas) -> ggez::GameResult {
+        self.draw_cannon(&self.world.cannon, ggez::graphics::Color::MAGENTA, canvas);
+
+        // casting is only worth it while rays are shown
+        if self.debug_state.debug_shown.get(Category::Rays)
+        {
+            self.debug_draw_aim()?;
+        }
 
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+    use crate::{input::action_state::ActionState, world::{TickInput, World}};
+
+    fn aim_at(world: &mut World, mouse: Vec2, ticks: u32)
+    {
+        let input = TickInput::new(ActionState::default(), Some(mouse));
+        for _ in 0..ticks
+        {
+            world.step(&input).unwrap();
+        }
+    }
+
+    fn mouse_aiming_world() -> World
+    {
+        let mut world = World::new(0);
+        world.cannon.aim = AimMode::Mouse;
+        world
+    }
+
+    #[test]
+    fn toggle_action_switches_aim_mode()
+    {
+        let mut world = World::new(0);
+        let mut actions = ActionState::default();
+        actions.tap(&[ActionCode::ToggleAimMode]);
+        actions.begin_tick();
+
+        world.step(&TickInput::new(actions, None)).unwrap();
+        assert_eq!(world.cannon.aim, AimMode::Mouse);
+    }
+
+    #[test]
+    fn weapon_actions_cycle_weapons()
+    {
+        let mut world = World::new(0);
+        let first = world.cannon.armory.selected().def.name.clone();
+
+        let mut actions = ActionState::default();
+        actions.tap(&[ActionCode::NextWeapon]);
+        actions.begin_tick();
+        world.step(&TickInput::new(actions, None)).unwrap();
+        assert_ne!(world.cannon.armory.selected().def.name, first);
+
+        let mut actions = ActionState::default();
+        actions.tap(&[ActionCode::PrevWeapon]);
+        actions.begin_tick();
+        world.step(&TickInput::new(actions, None)).unwrap();
+        assert_eq!(world.cannon.armory.selected().def.name, first);
+    }
+
+    #[test]
+    fn turns_the_short_way_toward_the_mouse()
+    {
+        // the cannon starts facing +x; up on screen is -y in the world
+        let mut world = mouse_aiming_world();
+        aim_at(&mut world, Vec2::new(1.0, -5.0), 1);
+        assert!(world.cannon.rot_vel > 0.0);
+
+        let mut world = mouse_aiming_world();
+        aim_at(&mut world, Vec2::new(1.0, 5.0), 1);
+        assert!(world.cannon.rot_vel < 0.0);
+    }
+
+    #[test]
+    fn cannot_snap_to_the_mouse()
+    {
+        let mut world = mouse_aiming_world();
+        let max_step = f32::max(Cannon::ROT_ACC, Cannon::ROT_DE_ACC) * MainState::FIXED_PHYSICS_TIMESTEP;
+
+        let mut prev_rot_vel = 0.0;
+        for _ in 0..120
+        {
+            aim_at(&mut world, Vec2::new(-5.0, 0.1), 1);
+            assert!(world.cannon.rot_vel.abs() <= Cannon::MAX_ROT_PER_SEC);
+            assert!((world.cannon.rot_vel - prev_rot_vel).abs() <= max_step + 1e-5);
+            prev_rot_vel = world.cannon.rot_vel;
+        }
+    }
+
+    #[test]
+    fn settles_on_the_mouse()
+    {
+        let mut world = mouse_aiming_world();
+        let mouse = Vec2::new(-3.0, -4.0);
+        aim_at(&mut world, mouse, 5 * MainState::FIXED_PHYSICS_FRAMERATE);
+
+        let aimed = world.cannon.facing.flip_y();
+        assert!(aimed.angle_between(mouse).abs() < 0.02);
+        assert!(world.cannon.rot_vel.abs() < 0.05);
+    }
+
+    #[test]
+    fn inspecting_leaves_facing_alone()
+    {
+        let mut cannon = Cannon::new(Vec2::from_angle(0.37), Vec2::ZERO);
+
+        let context = egui::Context::default();
+        for _ in 0..3
+        {
+            let _ = context.run(egui::RawInput::default(), |context| { egui::CentralPanel::default().show(context, |ui| cannon.inspect(ui)); });
+        }
+        assert_eq!(cannon.facing, Vec2::from_angle(0.37));
+    }
+}
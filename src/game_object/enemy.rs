@@ -1,45 +1,223 @@
 use ggez::glam::Vec2;
 use serde::{Deserialize, Serialize};
 
-use crate::game_object::HasRegion;
+use ggegui::egui;
+
+use crate::{console::Command, events::{EnemyDamaged, EnemyKilled}, game_object::{HasPosition, HasRegion}, gui::inspector::{edit_region, edit_vec2, Inspect}, util::hash_map_tracker::{ForTracker, HashMapTracker, Status, WithIndex}};
 
 use super::{has_region, has_position, Region, collider_type::*};
 
-#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+/// What an enemy does every fixed tick, assigned to the selected enemies
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum Behavior
+{
+    /// Stay put
+    #[default]
+    Hold,
+    /// Walk to a world position, then hold there
+    MoveTo(#[serde(with = "crate::util::vec_extension::_Vec2Ser")] Vec2),
+    /// Walk toward the cannon
+    Charge,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Enemy
 {
     health: i32,
     collision_region: Region::<Collider>,
     selection_region: Region::<Selection>,
     #[serde(with = "crate::util::vec_extension::_Vec2Ser")]
-    position: Vec2
+    position: Vec2,
+    pub behavior: Behavior,
+
+    index: Option<u16>,
+
+    /// Whether the cannon can currently see this enemy.
+    /// Recomputed every fixed tick from the visible cells.
+    #[serde(skip)]
+    pub spotted: bool,
 }
 
 has_position!(Enemy);
 has_region!(Enemy, collision_region, Collider);
 has_region!(Enemy, selection_region, Selection);
 
+impl Default for Enemy
+{
+    fn default() -> Self
+    {
+        Self
+        {
+            health: Enemy::MAX_HEALTH,
+            collision_region: ((-0.75, -0.75), (0.75, 0.75)).into(),
+            selection_region: ((-1.0, -1.0), (1.0, 1.0)).into(),
+            position: Default::default(),
+            behavior: Behavior::default(),
+            index: None,
+            spotted: false,
+        }
+    }
+}
 
-impl crate::FixedUpdate<Vec<Enemy>> for crate::MainState
+impl Enemy
 {
-    fn fixed_update(&mut self, context: &mut ggez::Context) -> ggez::GameResult 
+    pub const MAX_HEALTH: i32 = 100;
+    /// Tiles per second
+    pub const SPEED: f32 = 3.0;
+
+    pub fn health(&self) -> i32
     {
-        
-        
+        self.health
+    }
+
+    pub fn damage(&mut self, amount: i32)
+    {
+        self.health -= amount;
+    }
+
+    /// Dead enemies are removed by the enemy system
+    pub fn is_dead(&self) -> bool
+    {
+        self.health <= 0
+    }
+
+    /// Take one fixed tick's step of this enemy's behavior
+    fn act(&mut self, cannon: Vec2)
+    {
+        match self.behavior
+        {
+            Behavior::Hold => (),
+            Behavior::MoveTo(target) =>
+            {
+                if self.step_toward(target)
+                {
+                    self.behavior = Behavior::Hold;
+                }
+            }
+            Behavior::Charge => { self.step_toward(cannon); }
+        }
+    }
+
+    /// Walk a tick's worth toward `target`, returning whether it was reached
+    fn step_toward(&mut self, target: Vec2) -> bool
+    {
+        let step = Enemy::SPEED * crate::MainState::FIXED_PHYSICS_TIMESTEP;
+        let to_target = target - self.position;
+        if to_target.length() <= step
+        {
+            self.position = target;
+            return true;
+        }
+
+        self.position += to_target.normalize() * step;
+        false
+    }
+
+    /// Console commands acting on enemies
+    pub fn commands() -> Vec<Command>
+    {
+        vec![
+            Command
+            {
+                name: "spawn enemy",
+                args: "<x> <y>",
+                help: "spawn an enemy centered on world position x, y",
+                run: |args, target|
+                {
+                    let position = Vec2::new(args.next("x")?, args.next("y")?);
+                    args.finish()?;
+                    let enemy = HasRegion::<Collider>::region_set(Enemy::default().position_set(position), ((-0.75, -0.75), (0.75, 0.75)).into());
+                    let enemies = &mut target.world_mut()?.enemies;
+                    if let Status::Failure = enemies.push(enemy)
+                    {
+                        return Err(ggez::GameError::CustomError(format!("there are already {} enemies", enemies.len())));
+                    }
+                    Ok(format!("{} enemies", enemies.len()))
+                },
+            },
+        ]
+    }
+}
+
+
+impl Inspect for Enemy
+{
+    fn inspect(&mut self, ui: &mut egui::Ui)
+    {
+        edit_vec2(ui, "position", &mut self.position);
+        ui.horizontal(
+        |ui| {
+            ui.label("health");
+            let mut health = self.health;
+            if ui.add(egui::DragValue::new(&mut health).clamp_range(0..=Enemy::MAX_HEALTH)).changed()
+            {
+                self.health = health;
+            }
+        });
+        edit_region(ui, "collision region", &mut self.collision_region);
+        edit_region(ui, "selection region", &mut self.selection_region);
+        ui.label(format!("behavior: {:?}", self.behavior));
+        ui.label(format!("spotted: {}", self.spotted));
+    }
+}
+
+impl ForTracker for Enemy {}
+impl WithIndex for Enemy
+{
+    fn with_index(mut self, index: u16) -> Self
+    {
+        self.index = Some(index);
+        self
+    }
+}
+
+impl crate::FixedUpdate<HashMapTracker<Enemy>> for crate::World
+{
+    fn fixed_update(&mut self, _input: &crate::world::TickInput) -> ggez::GameResult 
+    {
+        let crate::World { enemies, events, cannon, .. } = self;
+
+        for damaged in events.current::<EnemyDamaged>()
+        {
+            // it may have been deleted since the hit
+            if let Some(enemy) = enemies.get_mut(&damaged.enemy)
+            {
+                enemy.damage(damaged.amount);
+            }
+        }
+
+        let dead: Vec<(u16, Vec2)> = enemies.by_id().filter(|(_, e)| e.is_dead()).map(|(id, e)| (id, e.position_get())).collect();
+        for (enemy, position) in dead
+        {
+            enemies.delete(enemy);
+            events.send(EnemyKilled { enemy, position });
+        }
+
+        for enemy in enemies.values_mut()
+        {
+            enemy.act(cannon.position_get());
+        }
+
         Ok(())
     }
 }
 
-impl crate::Draw<Vec<Enemy>> for crate::MainState
+impl crate::Draw<HashMapTracker<Enemy>> for crate::MainState
 {
     fn draw(&self, context: &mut ggez::Context, canvas: &mut ggez::graphics::Canvas) -> ggez::GameResult 
     {
         use ggez::graphics;
 
-        let ref enemies = self.enemies;
+        let enemies = &self.world.enemies;
+        let cursor = Vec2::from(context.mouse.position()) / 16.0 + self.world_pos;
 
-        for enemy in enemies
+        for enemy in enemies.values()
         {
+            if !enemy.spotted
+            {
+                continue;
+            }
+
             let img = graphics::Image::from_path(context, "/enemy.png")?;
 
             let screen_pos = (enemy.position - self.world_pos) * 16.0;
@@ -58,7 +236,7 @@ impl crate::Draw<Vec<Enemy>> for crate::MainState
             canvas.draw(&img, params);
 
 
-            HasRegion::<Collider>::region_get(enemy).draw(enemy, self.world_pos, screen_pos, context, canvas)?;
+            HasRegion::<Collider>::region_debug_draw(enemy, &self.debug_draw, cursor);
         }
         
         Ok(())    
@@ -2,39 +2,91 @@ use ggez::glam::Vec2;
 use ggez::graphics;
 use serde::{Deserialize, Serialize};
 
-use crate::util::hash_map_tracker::{ForTracker, HashMapTracker, WithIndex};
+use ggegui::egui;
+
+use crate::gui::inspector::{edit_vec2, Inspect};
+use crate::util::{hash_map_tracker::{ForTracker, HashMapTracker, WithIndex}, vec_extension::RotateBy};
 use crate::MainState;
 
-use super::{Draw, FixedUpdate};
+use super::{collider_type::Selection, has_position, has_region, shape::Shape, Draw, FixedUpdate, Region};
 
 #[derive(Debug, Clone, Default)]
 pub struct EnemyWall
 {
-    center_position: Vec2,
-    rect: graphics::Rect,
-    transform: graphics::Transform,
+    /// The corner the wall rotates about, in world tiles
+    position: Vec2,
+    /// Tiles
+    size: Vec2,
+    /// Radians
+    rotation: f32,
+    /// Bounds the rotated wall, refit whenever it moves or turns
+    selection_region: Region<Selection>,
     index: u16
 }
 
+has_position!(EnemyWall);
+has_region!(EnemyWall, selection_region, Selection);
+
 impl EnemyWall
 {
-    pub fn center_position(mut self, pos: Vec2) -> Self
+    pub fn position(mut self, pos: Vec2) -> Self
     {
-        self.center_position = pos;
+        self.position = pos;
         self
     }
 
-    pub fn rect(mut self, rect: graphics::Rect) -> Self
+    pub fn size(mut self, size: Vec2) -> Self
     {
-        self.rect = rect;
+        self.size = size;
+        self.fit_selection();
         self
     }
 
-    pub fn transform(mut self, transform: graphics::Transform) -> Self
+    pub fn rotation(mut self, rotation: f32) -> Self
     {
-        self.transform = transform;
+        self.rotation = rotation;
+        self.fit_selection();
         self
     }
+
+    /// Fit the selection region to the corners of the rotated wall,
+    /// or just around them if the wall is too thin to be a polygon
+    fn fit_selection(&mut self)
+    {
+        let corners = [Vec2::ZERO, Vec2::new(self.size.x, 0.0), self.size, Vec2::new(0.0, self.size.y)].map(|c| c.rotate_by(self.rotation));
+        self.selection_region =
+            match Shape::polygon(corners.to_vec())
+            {
+                Ok(shape) => Region::shaped(shape),
+                Err(_) =>
+                {
+                    let min = corners.into_iter().reduce(Vec2::min).expect("there are corners");
+                    let max = corners.into_iter().reduce(Vec2::max).expect("there are corners");
+                    (min, max).into()
+                },
+            };
+    }
+}
+
+impl Inspect for EnemyWall
+{
+    fn inspect(&mut self, ui: &mut egui::Ui)
+    {
+        let mut changed = edit_vec2(ui, "position", &mut self.position);
+        changed |= edit_vec2(ui, "size", &mut self.size);
+        changed |=
+            ui.horizontal(
+            |ui| {
+                ui.label("rotation");
+                ui.drag_angle(&mut self.rotation).changed()
+            })
+            .inner;
+        if changed
+        {
+            self.fit_selection();
+        }
+        ui.label(format!("selection {:?} to {:?}", self.selection_region.p0, self.selection_region.p1));
+    }
 }
 
 impl ForTracker for EnemyWall {}
@@ -63,7 +115,9 @@ impl Draw<HashMapTracker<EnemyWall>> for MainState
         {
             let params = 
                 graphics::DrawParam::new()
-                .transform(wall.transform.to_bare_matrix())
+                .dest(16.0 * (wall.position - self.world_pos))
+                .rotation(wall.rotation)
+                .scale(16.0 * wall.size)
                 .color(graphics::Color::BLUE);
 
             canvas.draw(&graphics::Quad, params);
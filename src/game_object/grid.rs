@@ -1,11 +1,11 @@
 use std::ops::{Index, IndexMut};
 
-use ggez::glam::Vec2;
+use ggez::glam::{IVec2, Vec2};
 
 use serde::{Serialize, Deserialize};
 use serde_with::serde_as;
 
-use crate::input::ActionCode;
+use crate::{console::Command, debug_draw::Category, events::TileChanged, input::ActionCode};
 
 #[derive(Debug, Clone, Default, Copy, Serialize, Deserialize)]
 pub struct Object
@@ -72,6 +72,21 @@ pub enum ObjectType
     Filled
 }
 
+impl std::str::FromStr for ObjectType
+{
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err>
+    {
+        match s
+        {
+            "none" => Ok(ObjectType::None),
+            "filled" => Ok(ObjectType::Filled),
+            _ => Err(format!("unknown object type {s}")),
+        }
+    }
+}
+
 #[serde_as]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Chunk
@@ -105,6 +120,9 @@ impl IndexMut<PackedU8> for Chunk
 
 impl Chunk
 {
+    /// Tint applied to cells outside the cannon's line of sight
+    const UNSEEN_COLOR: ggez::graphics::Color = ggez::graphics::Color::new(0.3, 0.3, 0.3, 1.0);
+
     fn contains_world_point(&self, pt: Vec2) -> bool
     {
         let ref ul = self.upper_left_position;
@@ -113,6 +131,72 @@ impl Chunk
         
         x_range.contains(&pt.x) && y_range.contains(&pt.y)
     }
+
+    /// Finds the object occupying the integer world cell `cell`,
+    /// searching every chunk. Returns `None` if no chunk covers it.
+    pub fn object_at_world_cell(chunks: &[Chunk], cell: IVec2) -> Option<&Object>
+    {
+        chunks
+        .iter()
+        .find_map(|chunk|
+        {
+            let local = cell - chunk.upper_left_position.floor().as_ivec2();
+            if (0..16).contains(&local.x) && (0..16).contains(&local.y)
+            {
+                Some(&chunk[(local.x as u8, local.y as u8).into()])
+            }
+            else
+            {
+                None
+            }
+        })
+    }
+
+    /// Like `object_at_world_cell`, but mutable
+    pub fn object_at_world_cell_mut(chunks: &mut [Chunk], cell: IVec2) -> Option<&mut Object>
+    {
+        chunks
+        .iter_mut()
+        .find_map(|chunk|
+        {
+            let local = cell - chunk.upper_left_position.floor().as_ivec2();
+            if (0..16).contains(&local.x) && (0..16).contains(&local.y)
+            {
+                Some(&mut chunk[(local.x as u8, local.y as u8).into()])
+            }
+            else
+            {
+                None
+            }
+        })
+    }
+
+    /// Console commands acting on the cells
+    pub fn commands() -> Vec<Command>
+    {
+        vec![
+            Command
+            {
+                name: "tile set",
+                args: "<x> <y> <none|filled>",
+                help: "set the foreground object of world cell x, y",
+                run: |args, target|
+                {
+                    let cell = IVec2::new(args.next("x")?, args.next("y")?);
+                    let object = args.next("object type")?;
+                    args.finish()?;
+                    let world = target.world_mut()?;
+                    let found = 
+                        Chunk::object_at_world_cell_mut(&mut world.chunks, cell)
+                        .ok_or_else(|| ggez::GameError::CustomError(format!("no chunk covers ({}, {})", cell.x, cell.y)))?;
+                    found.foreground_object = object;
+
+                    world.events.send(TileChanged { cell: (cell.x, cell.y), object });
+                    Ok(String::new())
+                },
+            },
+        ]
+    }
 }
 
 impl Default for Chunk
@@ -128,20 +212,17 @@ impl Default for Chunk
     }
 }
 
-impl crate::FixedUpdate<Vec<Chunk>> for crate::MainState
+impl crate::FixedUpdate<Vec<Chunk>> for crate::World
 {
-    fn fixed_update(&mut self, context: &mut ggez::Context) -> ggez::GameResult 
+    fn fixed_update(&mut self, input: &crate::world::TickInput) -> ggez::GameResult 
     {
-        let ref mut chunks = self.chunks;
+        let crate::World { chunks, events, .. } = self;
 
         // get mouse click location and map it to a cell and fill it if possible
         // if let Some(mut pos) = self.input_state.left_click
-        if self.key_input_state.held_actions.contains(&ActionCode::Click)
+        // the click is already in world coordinate space
+        if let (true, Some(mut pos)) = (input.actions.is_held(ActionCode::Click), input.mouse_world())
         {
-            let mut pos: Vec2 = context.mouse.position().into();
-            // let mut pos: Vec2 = context.mouse.position().into();
-            // map mouse click to somewhere in world coordinate space
-            pos = pos / 16.0 + self.world_pos;
 
             for chunk in chunks
             {
@@ -153,7 +234,13 @@ impl crate::FixedUpdate<Vec<Chunk>> for crate::MainState
                     // truncate / round down to get x and y position within chunk
                     let Vec2 {x, y} = pos.trunc();
                     // println!("{}, {}", x, y);
-                    chunk[(x, y).into()].foreground_object = ObjectType::Filled;
+                    let object = &mut chunk[(x, y).into()].foreground_object;
+                    if !matches!(object, ObjectType::Filled)
+                    {
+                        *object = ObjectType::Filled;
+                        let cell = (chunk.upper_left_position + Vec2::new(x, y)).as_ivec2();
+                        events.send(TileChanged { cell: (cell.x, cell.y), object: ObjectType::Filled });
+                    }
 
                     // chunk.array[0].foreground_object = ObjectType::Filled;
 
@@ -177,7 +264,7 @@ impl crate::Draw<Vec<Chunk>> for crate::MainState
 
         // apparent size of a given Object: 16x16 pixels
         // this means a given chunk is 256x256 pixels
-        let ref chunks = self.chunks;
+        let chunks = &self.world.chunks;
 
         for chunk in chunks
         {
@@ -188,9 +275,16 @@ impl crate::Draw<Vec<Chunk>> for crate::MainState
                 let dest_pos = 
                     16.0 * (pos + Vec2::new(object.id.a() as f32, object.id.b() as f32));
 
+                // darken cells the cannon cannot currently see
+                let cell = (chunk.upper_left_position + Vec2::new(object.id.a() as f32, object.id.b() as f32)).floor().as_ivec2();
+                let color = 
+                    if self.world.visibility.contains_cell(cell) { graphics::Color::WHITE } 
+                    else { Chunk::UNSEEN_COLOR };
+
                 let params = 
                     graphics::DrawParam::new()
                     .dest(dest_pos)
+                    .color(color)
                     .z(-100);
 
                 match object.foreground_object
@@ -205,6 +299,9 @@ impl crate::Draw<Vec<Chunk>> for crate::MainState
             }
 
             // Show boundary of chunk
+            let ul = chunk.upper_left_position;
+            self.debug_draw.rect(Category::ChunkBorders, ul, ul + Vec2::splat(16.0), graphics::Color::CYAN);
+            self.debug_draw.text(Category::ChunkBorders, ul + Vec2::splat(0.25), format!("chunk {ul}"), graphics::Color::CYAN);
         }
 
         Ok(())
@@ -1,10 +1,29 @@
-use ggez::glam::Vec2;
+use ggez::{glam::Vec2, graphics::Color};
 use serde::{Deserialize, Serialize};
 
 // local imports
-use crate::{game_object::{HasPosition, HasRegion}, util::hash_map_tracker::{ForTracker, HashMapTracker, WithIndex}, MainState};
+use ggegui::egui;
 
-use super::{collider_type::Collider, has_position, has_region, Region};
+use crate::{debug_draw::Category, events::{EnemyDamaged, Events, MissileFired}, game_object::{HasPosition, HasRegion}, gui::inspector::{edit_region, edit_vec2, Inspect}, util::{hash_map_tracker::{ForTracker, HashMapTracker, WithIndex}, vec_extension::RotateBy}, MainState};
+
+use super::{collider_type::{Collider, Selection}, enemy::Enemy, grid::Chunk, has_position, has_region, shape::Shape, sweep::{sweep_cells, sweep_region, Hit}, Region};
+
+/// Something a missile does besides flying straight.
+/// A missile may have any combination of these.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Behavior
+{
+    /// Steer toward the nearest enemy within `range` tiles,
+    /// turning at most `turn_rate` radians per second
+    Homing { turn_rate: f32, range: f32 },
+    /// After `after_ticks`, burst into `into` missiles fanned across `arc_degrees`.
+    /// The children carry every other behavior of their parent.
+    Split { after_ticks: u32, into: u32, arc_degrees: f32 },
+    /// On impact, deal `damage` to every enemy within `radius` tiles
+    Explosive { radius: f32, damage: i32 },
+    /// Expire after this many ticks
+    Lifetime { ticks: u32 },
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Missile
@@ -14,15 +33,37 @@ pub struct Missile
     #[serde(with = "crate::util::vec_extension::_Vec2Ser")]
     vel: Vec2,
     collision_region: Region<Collider>,
+    selection_region: Region<Selection>,
 
     index: Option<u16>,
+
+    behaviors: Vec<Behavior>,
+    /// Ticks since the missile was fired
+    age: u32,
 }
 
 has_position!(Missile);
 has_region!(Missile, collision_region, Collider);
+has_region!(Missile, selection_region, Selection);
+
+/// What happened to a missile during a tick
+enum Fate
+{
+    Flying,
+    Expired,
+    /// Replaced by these children
+    Split(Vec<Missile>),
+    /// Hit the enemy with this id in `World::enemies`, or a wall if `None`
+    Impact(Option<u16>),
+}
 
 impl Missile
 {
+    /// Damage of a direct hit from a missile that is not explosive
+    const DIRECT_DAMAGE: i32 = 10;
+    /// Half the length and width of the hitbox, which points the way the missile flies
+    const HALF_SIZE: Vec2 = Vec2::new(0.5, 0.25);
+
     pub fn new(pos: Vec2, vel: Vec2) -> Self
     {
         Self
@@ -30,9 +71,156 @@ impl Missile
             position: pos,
             vel,
             index: Default::default(),
-            collision_region: ((-0.4, -0.4), (0.4, 0.4)).into(),
+            collision_region: Missile::hitbox(vel),
+            selection_region: ((-0.75, -0.75), (0.75, 0.75)).into(),
+            behaviors: Vec::new(),
+            age: 0,
         }
     }
+
+    fn hitbox(vel: Vec2) -> Region<Collider>
+    {
+        let heading = vel.y.atan2(vel.x);
+        Region::shaped(Shape::obb(Vec2::ZERO, Missile::HALF_SIZE, heading).expect("the missile's size is positive"))
+    }
+
+    pub fn behaviors(mut self, behaviors: Vec<Behavior>) -> Self
+    {
+        self.behaviors = behaviors;
+        self
+    }
+
+    /// Turn toward the nearest enemy in range, if homing
+    fn home(&mut self, enemies: &HashMapTracker<Enemy>)
+    {
+        for behavior in &self.behaviors
+        {
+            let &Behavior::Homing { turn_rate, range } = behavior else { continue };
+
+            let nearest =
+                enemies
+                .by_id()
+                .map(|(_, e)| e.position_get() - self.position)
+                .filter(|to| to.length() <= range)
+                .min_by(|a, b| a.length().total_cmp(&b.length()));
+
+            // standing still, or already on the enemy, there is no angle to turn by
+            if let Some((heading, to)) = nearest.and_then(|to| self.vel.try_normalize().zip(to.try_normalize()))
+            {
+                let max_turn = turn_rate * MainState::FIXED_PHYSICS_TIMESTEP;
+                let turn = heading.angle_between(to).clamp(-max_turn, max_turn);
+                self.vel = self.vel.rotate_by(turn);
+                self.collision_region = Missile::hitbox(self.vel);
+            }
+        }
+    }
+
+    /// The children this missile bursts into on this tick, if it splits now
+    fn split(&self) -> Option<Vec<Missile>>
+    {
+        self.behaviors
+        .iter()
+        .find_map(|behavior| match *behavior
+        {
+            Behavior::Split { after_ticks, into, arc_degrees } if self.age == after_ticks =>
+            {
+                let arc = arc_degrees.to_radians();
+                let inherited: Vec<Behavior> =
+                    self.behaviors
+                    .iter()
+                    .filter(|b| !matches!(b, Behavior::Split { .. }))
+                    .cloned()
+                    .collect();
+
+                let children =
+                    (0..into)
+                    .map(|i|
+                    {
+                        let angle = if into > 1 { -arc / 2.0 + arc * i as f32 / (into - 1) as f32 } else { 0.0 };
+                        Missile::new(self.position, self.vel.rotate_by(angle)).behaviors(inherited.clone())
+                    })
+                    .collect();
+                Some(children)
+            },
+            _ => None,
+        })
+    }
+
+    fn expired(&self) -> bool
+    {
+        self.behaviors
+        .iter()
+        .any(|behavior| matches!(*behavior, Behavior::Lifetime { ticks } if self.age >= ticks))
+    }
+
+    /// The first thing the missile ran into moving from `from` to where it is now,
+    /// swept so that however fast it goes it can't pass through anything:
+    /// the id of an enemy or `None` for a filled cell, and where it hit.
+    fn impact(&self, from: Vec2, enemies: &HashMapTracker<Enemy>, chunks: &[Chunk]) -> Option<(Option<u16>, Hit)>
+    {
+        let enemy_hits =
+            enemies
+            .by_id()
+            .filter_map(|(id, e)| sweep_region::<Collider, _>(e, from, self.position).map(|hit| (Some(id), hit)));
+        let cell_hit = sweep_cells(chunks, from, self.position).map(|hit| (None, hit));
+
+        // the earliest, favoring enemies on a tie
+        enemy_hits
+        .chain(cell_hit)
+        .reduce(|first, next| if next.1.t < first.1.t { next } else { first })
+    }
+
+    /// Send the damage of this missile hitting `hit` (or a wall) at its position
+    fn detonate(&self, hit: Option<u16>, enemies: &HashMapTracker<Enemy>, events: &mut Events)
+    {
+        let explosion =
+            self.behaviors
+            .iter()
+            .find_map(|behavior| match *behavior { Behavior::Explosive { radius, damage } => Some((radius, damage)), _ => None });
+
+        match (explosion, hit)
+        {
+            (Some((radius, damage)), _) =>
+            {
+                let Ok(blast) = Shape::circle(self.position, radius) else { return };
+                enemies
+                .by_id()
+                .filter(|(_, e)| blast.contains(e.position_get()))
+                .for_each(|(enemy, _)| events.send(EnemyDamaged { enemy, amount: damage }));
+            },
+            (None, Some(enemy)) => events.send(EnemyDamaged { enemy, amount: Missile::DIRECT_DAMAGE }),
+            (None, None) => (),
+        }
+    }
+
+    fn in_bounds(&self) -> bool
+    {
+        let x_range = -100.0..(MainState::WINDOW_X+100.0);
+        let y_range = -100.0..(MainState::WINDOW_Y+100.0);
+
+        x_range.contains(&self.position.x) && y_range.contains(&self.position.y)
+    }
+}
+
+impl Inspect for Missile
+{
+    fn inspect(&mut self, ui: &mut egui::Ui)
+    {
+        edit_vec2(ui, "position", &mut self.position);
+        edit_vec2(ui, "velocity", &mut self.vel);
+        ui.horizontal(
+        |ui| {
+            ui.label("age");
+            let mut age = self.age;
+            if ui.add(egui::DragValue::new(&mut age)).changed()
+            {
+                self.age = age;
+            }
+        });
+        ui.label(format!("behaviors: {:?}", self.behaviors));
+        edit_region(ui, "collision region", &mut self.collision_region);
+        edit_region(ui, "selection region", &mut self.selection_region);
+    }
 }
 
 impl ForTracker for Missile {}
@@ -45,42 +233,63 @@ impl WithIndex for Missile
     }
 }
 
-impl crate::FixedUpdate<HashMapTracker<Missile>> for crate::MainState
+impl crate::FixedUpdate<HashMapTracker<Missile>> for crate::World
 {
-    fn fixed_update(&mut self, _context: &mut ggez::Context) -> ggez::GameResult {
-        let missiles = &mut self.missiles;
-        
-        for (_, missile) in missiles.get_tracker_mut()
+    fn fixed_update(&mut self, _input: &crate::world::TickInput) -> ggez::GameResult {
+        let crate::World { missiles, enemies, chunks, events, debug_draw, .. } = self;
+
+        for fired in events.current::<MissileFired>()
         {
-            missile.position += missile.vel * MainState::FIXED_PHYSICS_TIMESTEP;
+            missiles.push(fired.missile.clone());
         }
 
-        // TODO: Do this boundary check elsewhere, in the first loop over all
-        missiles
-        .iter()
-        .map(
-        |(&ind, m)|
+        // the order missiles go in decides the ids their children get and the order
+        // of the events they send, so go in id order rather than the hash map's
+        let mut ids: Vec<u16> = missiles.keys().copied().collect();
+        ids.sort();
+
+        let mut children = Vec::new();
+        for ind in ids
         {
-            let x_range = -100.0..(Self::WINDOW_X+100.0);
-            let y_range = -100.0..(Self::WINDOW_Y+100.0);
+            let missile = missiles.get_mut(&ind).expect("ids were just collected");
 
-            if x_range.contains(&m.position.x) && y_range.contains(&m.position.y)
+            missile.age += 1;
+            missile.home(enemies);
+            let from = missile.position;
+            missile.position += missile.vel * MainState::FIXED_PHYSICS_TIMESTEP;
+
+            let fate = 
+                if let Some((target, hit)) = missile.impact(from, enemies, chunks)
+                {
+                    // explode where it hit, not past it
+                    missile.position = hit.point;
+                    Fate::Impact(target)
+                }
+                else if let Some(split) = missile.split() { Fate::Split(split) }
+                else if missile.expired() || !missile.in_bounds() { Fate::Expired }
+                else { Fate::Flying };
+
+            // this tick's step, and where the next quarter second takes it
+            debug_draw.line(Category::Paths, from, missile.position, Color::YELLOW);
+            if let Fate::Flying = fate
             {
-                None
+                debug_draw.arrow(Category::Paths, missile.position, missile.position + 0.25 * missile.vel, Color::YELLOW);
             }
-            else
+
+            match fate
             {
-                Some(ind)
+                Fate::Flying => continue,
+                Fate::Expired => (),
+                Fate::Split(split) => children.extend(split),
+                Fate::Impact(hit) => missile.detonate(hit, enemies, events),
             }
-        })
-        .flatten()
-        .collect::<Vec<u16>>()
-        .iter()
-        .for_each(
-        |&ind|
-        {
             missiles.delete(ind);
-        });
+        }
+
+        for child in children
+        {
+            missiles.push(child);
+        }
 
         Ok(())
     }
@@ -89,7 +298,8 @@ impl crate::FixedUpdate<HashMapTracker<Missile>> for crate::MainState
 impl crate::Draw<HashMapTracker<Missile>> for crate::MainState
 {
     fn draw(&self, context: &mut ggez::Context, canvas: &mut ggez::graphics::Canvas) -> ggez::GameResult {
-        let missiles = &self.missiles;
+        let missiles = &self.world.missiles;
+        let cursor = Vec2::from(context.mouse.position()) / 16.0 + self.world_pos;
         
         use ggez::graphics;
 
@@ -120,12 +330,159 @@ impl crate::Draw<HashMapTracker<Missile>> for crate::MainState
             // canvas.draw(&self.assets.missile_image, param);
             canvas.draw(&big_missile, param);
 
-            if self.debug_state.draw_hitboxes
-            {
-                missile.region_get().draw(missile, self.world_pos, missile_screen_pos, context, canvas)?;
-            }
+            HasRegion::<Collider>::region_debug_draw(missile, &self.debug_draw, cursor);
         }
         
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+    use crate::{game_object::grid::ObjectType, world::{TickInput, World}, FixedUpdate};
+
+    /// A world with no missiles, and a single enemy at `enemy` if any
+    fn world_with_enemy(enemy: Option<Vec2>) -> World
+    {
+        let mut world = World::new(0);
+        world.enemies = 
+            enemy
+            .into_iter()
+            .map(|pos| HasRegion::<Collider>::region_set(Enemy::default().position_set(pos), ((-0.75, -0.75), (0.75, 0.75)).into()))
+            .collect();
+        world
+    }
+
+    fn step(world: &mut World, ticks: u32)
+    {
+        for _ in 0..ticks
+        {
+            world.events.begin_tick(world.tick);
+            FixedUpdate::<HashMapTracker<Missile>>::fixed_update(world, &TickInput::default()).unwrap();
+            FixedUpdate::<HashMapTracker<Enemy>>::fixed_update(world, &TickInput::default()).unwrap();
+        }
+    }
+
+
+    #[test]
+    fn lifetime_expires()
+    {
+        let mut world = world_with_enemy(None);
+        world.missiles.push(Missile::new(Vec2::ZERO, Vec2::X).behaviors(vec![Behavior::Lifetime { ticks: 3 }]));
+
+        step(&mut world, 2);
+        assert_eq!(world.missiles.len(), 1);
+        step(&mut world, 1);
+        assert!(world.missiles.is_empty());
+    }
+
+    #[test]
+    fn homing_turns_toward_enemy_at_limited_rate()
+    {
+        let turn_rate = 1.0;
+        let mut world = world_with_enemy(Some(Vec2::new(0.0, 10.0)));
+        world.missiles.push(Missile::new(Vec2::ZERO, Vec2::X).behaviors(vec![Behavior::Homing { turn_rate, range: 20.0 }]));
+
+        step(&mut world, 1);
+        let missile = world.missiles.values().next().unwrap();
+        let turned = Vec2::X.angle_between(missile.vel);
+        assert!(turned > 0.0);
+        assert!(turned <= turn_rate * MainState::FIXED_PHYSICS_TIMESTEP + 1e-5);
+    }
+
+    #[test]
+    fn homing_ignores_enemies_out_of_range()
+    {
+        let mut world = world_with_enemy(Some(Vec2::new(0.0, 10.0)));
+        world.missiles.push(Missile::new(Vec2::ZERO, Vec2::X).behaviors(vec![Behavior::Homing { turn_rate: 1.0, range: 5.0 }]));
+
+        step(&mut world, 1);
+        assert_eq!(world.missiles.values().next().unwrap().vel, Vec2::X);
+    }
+
+    #[test]
+    fn homing_without_a_direction_stays_finite()
+    {
+        let homing = vec![Behavior::Homing { turn_rate: 1.0, range: 5.0 }];
+        let mut world = world_with_enemy(Some(Vec2::new(2.0, 2.0)));
+        world.missiles.push(Missile::new(Vec2::new(1.0, 1.0), Vec2::ZERO).behaviors(homing.clone()));
+        world.missiles.push(Missile::new(Vec2::new(2.0, 2.0), Vec2::X).behaviors(homing));
+
+        step(&mut world, 1);
+        assert!(world.missiles.values().all(|missile| missile.position.is_finite() && missile.vel.is_finite()));
+    }
+
+    #[test]
+    fn split_spawns_children_with_inherited_behaviors()
+    {
+        let mut world = world_with_enemy(None);
+        let behaviors = vec![Behavior::Split { after_ticks: 2, into: 3, arc_degrees: 90.0 }, Behavior::Lifetime { ticks: 50 }];
+        world.missiles.push(Missile::new(Vec2::ZERO, Vec2::X).behaviors(behaviors));
+
+        step(&mut world, 1);
+        assert_eq!(world.missiles.len(), 1);
+        step(&mut world, 1);
+        assert_eq!(world.missiles.len(), 3);
+
+        // children don't split again
+        step(&mut world, 5);
+        assert_eq!(world.missiles.len(), 3);
+        assert!(world.missiles.values().all(|m| m.behaviors == vec![Behavior::Lifetime { ticks: 50 }]));
+    }
+
+    #[test]
+    fn fast_missile_cannot_pass_a_filled_cell()
+    {
+        let mut world = world_with_enemy(None);
+        world.chunks[0][(6u8, 0u8).into()].foreground_object = ObjectType::Filled;
+
+        // crosses the whole chunk, and far past the cell, in a single tick
+        let speed = 100.0 / MainState::FIXED_PHYSICS_TIMESTEP;
+        world.missiles.push(Missile::new(Vec2::new(0.5, 0.5), Vec2::X * speed).behaviors(vec![Behavior::Explosive { radius: 0.5, damage: 1 }]));
+
+        step(&mut world, 1);
+        assert!(world.missiles.is_empty());
+
+        // it stopped and exploded at the cell, so an enemy past the cell,
+        // out of reach of an explosion there, is untouched
+        let mut world = world_with_enemy(Some(Vec2::new(8.5, 0.5)));
+        world.chunks[0][(6u8, 0u8).into()].foreground_object = ObjectType::Filled;
+        world.missiles.push(Missile::new(Vec2::new(0.5, 0.5), Vec2::X * speed).behaviors(vec![Behavior::Explosive { radius: 2.0, damage: Enemy::MAX_HEALTH }]));
+
+        step(&mut world, 1);
+        assert_eq!(world.enemies.len(), 1);
+    }
+
+    #[test]
+    fn fast_missile_hits_a_small_region()
+    {
+        let mut world = world_with_enemy(None);
+        let small = HasRegion::<Collider>::region_set(Enemy::default().position_set((8.0, 0.5).into()), ((-0.1, -0.1), (0.1, 0.1)).into());
+        world.enemies.push(small);
+
+        let speed = 50.0 / MainState::FIXED_PHYSICS_TIMESTEP;
+        world.missiles.push(Missile::new(Vec2::new(0.5, 0.5), Vec2::X * speed).behaviors(vec![Behavior::Explosive { radius: 0.5, damage: Enemy::MAX_HEALTH }]));
+
+        step(&mut world, 1);
+        assert!(world.missiles.is_empty());
+        assert!(world.enemies.is_empty());
+    }
+
+    #[test]
+    fn explosion_damages_enemies_in_radius()
+    {
+        let mut world = world_with_enemy(Some(Vec2::new(1.0, 0.0)));
+        let far = HasRegion::<Collider>::region_set(Enemy::default().position_set((1.0, 4.0).into()), ((-0.75, -0.75), (0.75, 0.75)).into());
+        world.enemies.push(far);
+
+        let explosive = vec![Behavior::Explosive { radius: 2.0, damage: Enemy::MAX_HEALTH }];
+        world.missiles.push(Missile::new(Vec2::ZERO, Vec2::X * 30.0).behaviors(explosive));
+
+        step(&mut world, 2);
+        assert!(world.missiles.is_empty());
+        assert_eq!(world.enemies.len(), 1);
+        assert_eq!(world.enemies[&1].position_get(), Vec2::new(1.0, 4.0));
+    }
+}
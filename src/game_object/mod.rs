@@ -1,13 +1,24 @@
 pub mod cannon;
 pub mod missile;
-// pub mod player;
+pub mod player;
 pub mod enemy_wall;
 pub mod grid;
 pub mod enemy;
+pub mod visibility;
+pub mod sweep;
+pub mod turret;
+pub mod raycast;
+pub mod selection;
+pub mod shape;
+pub mod weapon;
 
 use ggez::glam::Vec2;
 use serde::{Deserialize, Serialize};
 
+// local imports
+use crate::debug_draw::{Category, DebugDraw};
+use shape::Shape;
+
 /// Update is a trait describing an object which should be updated every single frame
 pub(crate) trait Update<I>
 {
@@ -18,13 +29,15 @@ pub(crate) trait Update<I>
 /// every fixed interval, such as 60 times a second for 60fps.
 /// 
 /// Use for things like physics especially.
+/// Fixed updates see only the `World` and the tick's input, never the ggez
+/// context, so the simulation can be replayed and run in tests.
 /// 
 /// Implement like 
-/// `impl crate::FixedUpdate<Cannon> for crate::MainState`, where 
-/// MainState has member variable(s) of type Cannon
+/// `impl crate::FixedUpdate<Cannon> for crate::World`, where 
+/// World has member variable(s) of type Cannon
 pub(crate) trait FixedUpdate<I>
 {
-    fn fixed_update(&mut self, context: &mut ggez::Context) -> ggez::GameResult;
+    fn fixed_update(&mut self, input: &crate::world::TickInput) -> ggez::GameResult;
 }
 
 /// Draw is a trait describing an object which will be drawn to the 
@@ -75,6 +88,11 @@ macro_rules! has_position {
 
 pub(crate) use has_position;
 
+/// An axis-aligned box from `p0` to `p1`, relative to its owner's position,
+/// optionally narrowed to a `Shape` fitting inside it.
+///
+/// The box alone is used where only boxes will do, like sweeping and the grid;
+/// point and overlap queries go by the shape.
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct Region<T: ColliderType>
 {
@@ -82,6 +100,7 @@ pub struct Region<T: ColliderType>
     pub p0: Vec2,
     #[serde(with = "crate::util::vec_extension::_Vec2Ser")]
     pub p1: Vec2,
+    shape: Option<Shape>,
     t: std::marker::PhantomData<T>
 }
 
@@ -103,79 +122,39 @@ impl<T: ColliderType> Region<T>
 {
     const DRAWN_COLOR: ggez::graphics::Color = ggez::graphics::Color::MAGENTA;
 
-    pub fn new(p0: Vec2, p1: Vec2) -> Self
+    /// A plain box, failing if `p0` is not above and left of `p1`
+    pub fn new(p0: Vec2, p1: Vec2) -> ggez::GameResult<Self>
     {
-        assert!(p0.x <= p1.x);
-        assert!(p0.y <= p1.y);
-
-        Region::<T>
-        {
-            p0,
-            p1,
-            t: std::marker::PhantomData
-        }
+        Shape::aabb(p0, p1)?;
+        Ok((p0, p1).into())
     }
 
-    fn intersects(&self, local_pos: Vec2) -> bool
+    /// A region of `shape`, boxed by its bounds
+    pub fn shaped(shape: Shape) -> Self
     {
-        self.p0.x <= local_pos.x && self.p0.y <= local_pos.y &&
-        local_pos.x <= self.p1.x && local_pos.y <= self.p1.y
+        let (p0, p1) = shape.bounds();
+        Region::<T> { p0, p1, shape: Some(shape), t: std::marker::PhantomData }
     }
 
-    pub fn draw<Parent>
-    (
-        &self, 
-        parent: &Parent, 
-        world_pos: Vec2, 
-        screen_pos: Vec2, 
-        context: &mut ggez::Context, 
-        canvas: &mut ggez::graphics::Canvas
-    ) -> ggez::GameResult
-    where
-        Parent: HasRegion<T> 
+    /// The region's shape, or its box if it has none
+    pub fn shape(&self) -> Shape
     {
-        use ggez::graphics;
-
-        // let cb = enemy.collision_box_get();
-        let cb = self;
-            
-            
-        let a: Vec2 = cb.p0;
-        let b: Vec2 = (cb.p1.x, cb.p0.y).into();
-        let c: Vec2 = cb.p1;
-        let d: Vec2 = (cb.p0.x, cb.p1.y).into();
+        self.shape.clone().unwrap_or(Shape::Aabb { min: self.p0, max: self.p1 })
+    }
 
-        let points = [a * 16.0, b * 16.0, c * 16.0, d * 16.0];
+    /// Whether the region has a shape narrower than its box
+    pub fn is_shaped(&self) -> bool
+    {
+        self.shape.is_some()
+    }
 
-        let mouse_pos: Vec2 = context.mouse.position().into();
-        let mouse_world_pos = mouse_pos / 16.0 + world_pos;
+    fn intersects(&self, local_pos: Vec2) -> bool
+    {
+        let in_box =
+            self.p0.x <= local_pos.x && self.p0.y <= local_pos.y &&
+            local_pos.x <= self.p1.x && local_pos.y <= self.p1.y;
 
-        let mut color = graphics::Color::MAGENTA;
-        if parent.intersects_region(mouse_world_pos)
-        {
-            color = graphics::Color::BLACK;
-        }
-        
-        let mesh = 
-            graphics::Mesh::new_polygon(
-                context, 
-                graphics::DrawMode::stroke(2.0), 
-                &points, 
-                color
-            )?;
-        
-        let cb_transform = 
-            graphics::Transform::Values 
-            { 
-                dest: screen_pos.into(), 
-                rotation: 0.0, 
-                scale: [1.0, 1.0].into(), 
-                offset: [0.0, 0.0].into() 
-            };
-
-        canvas.draw(&mesh, graphics::DrawParam::new().transform(cb_transform.to_bare_matrix()));
-        
-        Ok(())
+        in_box && self.shape.as_ref().is_none_or(|shape| shape.contains(local_pos))
     }
 }
 
@@ -191,7 +170,7 @@ impl<T: ColliderType> From<(Vec2, Vec2)> for Region::<T>
 {
     fn from(value: (Vec2, Vec2)) -> Self 
     {
-        Region::<T> { p0: value.0, p1: value.1, t: std::marker::PhantomData }    
+        Region::<T> { p0: value.0, p1: value.1, shape: None, t: std::marker::PhantomData }    
     }
 }
 
@@ -199,7 +178,7 @@ impl<T: ColliderType> From<((f32, f32), (f32, f32))> for Region::<T>
 {
     fn from(value: ((f32, f32), (f32, f32))) -> Self 
     {
-        Region::<T> { p0: value.0.into(), p1: value.1.into(), t: std::marker::PhantomData }    
+        Region::<T> { p0: value.0.into(), p1: value.1.into(), shape: None, t: std::marker::PhantomData }    
     }
 }
 
@@ -219,6 +198,19 @@ pub trait HasRegion<T: ColliderType>: HasPosition
         let local_pos = world_pos - self.position_get();
         self.region_get().intersects(local_pos)
     }
+
+    /// The region's shape where it is in the world, to test against other shapes
+    fn region_shape(&self) -> Shape
+    {
+        self.region_get().shape().translated(self.position_get())
+    }
+
+    /// Outline the region with the other hitboxes, darker under the cursor
+    fn region_debug_draw(&self, debug_draw: &DebugDraw, cursor: Vec2)
+    {
+        let color = if self.intersects_region(cursor) { ggez::graphics::Color::BLACK } else { Region::<T>::DRAWN_COLOR };
+        debug_draw.shape(Category::Hitboxes, &self.region_shape(), color);
+    }
 }
 
 macro_rules! has_region {
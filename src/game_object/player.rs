@@ -1,62 +1,171 @@
-use ggez::glam::Vec2;
+use ggez::glam::{IVec2, Vec2};
+use serde::{Deserialize, Serialize};
 
 // local imports
-use crate::util::message::Message;
+use crate::{events::TileChanged, game_object::HasPosition, input::ActionCode, util::message::Message, MainState};
+use super::{collider_type::Collider, grid::{Chunk, ObjectType}, has_position, has_region, shape::Shape, Region};
 
-#[derive(Default, Debug, Clone, derive_setters::Setters)]
+/// A platformer character that runs and jumps among the filled cells of the chunks.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Player
 {
-    pos: Vec2,
+    #[serde(with = "crate::util::vec_extension::_Vec2Ser")]
+    position: Vec2,
+    #[serde(with = "crate::util::vec_extension::_Vec2Ser")]
     vel: Vec2,
-    feet_offset: Vec2,
-    jump: Option<Message<()>>,
+    collision_region: Region<Collider>,
+    /// Where the player returns to after falling out of the world
+    #[serde(with = "crate::util::vec_extension::_Vec2Ser")]
+    spawn: Vec2,
+
+    /// A jump pressed shortly before landing, still waiting to happen
+    jump: Message,
+    /// Active while standing, and for a moment after walking off a ledge
+    coyote: Message,
     grounded: bool,
 }
 
+has_position!(Player);
+has_region!(Player, collision_region, Collider);
+
 impl Player
 {
+    /// Seconds a jump pressed in the air is remembered, so it happens on landing
     pub const JUMP_TIME_BUFFER: f32 = 0.25;
-    pub const _COYOTE_TIME: f32 = 0.25;
+    /// Seconds after walking off a ledge during which the player can still jump
+    pub const COYOTE_TIME: f32 = 0.25;
+
+    /// Tiles per second
+    const RUN_SPEED: f32 = 8.0;
+    const JUMP_SPEED: f32 = 16.0;
+    /// Tiles per second squared
+    const GRAVITY: f32 = 40.0;
+    /// Kept under a tile per tick, so falling can't pass through a floor
+    const MAX_FALL_SPEED: f32 = 30.0;
+    /// Falling this far below the spawn point respawns the player
+    const FALL_LIMIT: f32 = 100.0;
+    /// Overlaps smaller than this don't count as touching a cell
+    const EPSILON: f32 = 1e-4;
+
+    pub fn new(spawn: Vec2) -> Self
+    {
+        Self
+        {
+            position: spawn,
+            vel: Vec2::ZERO,
+            // boxed from (-0.5, -0.75) to (0.5, 0.75), which is all the grid sees
+            collision_region: Region::shaped(Shape::capsule(Vec2::new(0.0, -0.25), Vec2::new(0.0, 0.25), 0.5).expect("the radius is positive")),
+            spawn,
+            jump: Message::default(),
+            coyote: Message::default(),
+            grounded: false,
+        }
+    }
+
+    /// Every filled cell the collision region overlaps
+    fn overlapped_cells(&self, chunks: &[Chunk]) -> Vec<IVec2>
+    {
+        let min = (self.position + self.collision_region.p0 + Player::EPSILON).floor().as_ivec2();
+        let max = (self.position + self.collision_region.p1 - Player::EPSILON).floor().as_ivec2();
+
+        (min.y..=max.y)
+        .flat_map(|y| (min.x..=max.x).map(move |x| IVec2::new(x, y)))
+        .filter(|&cell| matches!(Chunk::object_at_world_cell(chunks, cell), Some(object) if matches!(object.foreground_object, ObjectType::Filled)))
+        .collect()
+    }
 
-    pub fn issue_jump(&mut self)
+    /// Move `delta` tiles along `axis` (0 for x, 1 for y), stopping flush against
+    /// any filled cell in the way. Returns whether a cell was hit.
+    fn move_axis(&mut self, chunks: &[Chunk], axis: usize, delta: f32) -> bool
     {
-        // a jump message with some lifetime
-        self.jump = Some(Message::new((), Player::JUMP_TIME_BUFFER));
+        self.position[axis] += delta;
+
+        let cells = self.overlapped_cells(chunks);
+        if cells.is_empty() || delta == 0.0
+        {
+            return false;
+        }
+
+        self.position[axis] =
+            if delta > 0.0
+            {
+                let nearest = cells.iter().map(|c| c[axis]).min().expect("cells is not empty");
+                nearest as f32 - self.collision_region.p1[axis]
+            }
+            else
+            {
+                let nearest = cells.iter().map(|c| c[axis]).max().expect("cells is not empty");
+                (nearest + 1) as f32 - self.collision_region.p0[axis]
+            };
+        true
     }
 }
 
-impl crate::FixedUpdate<Player> for crate::MainState
+impl crate::FixedUpdate<Player> for crate::World
 {
-    fn fixed_update(&mut self, context: &mut ggez::Context) -> ggez::GameResult 
+    fn fixed_update(&mut self, input: &crate::world::TickInput) -> ggez::GameResult
     {
         let player = &mut self.player;
+        let actions = &input.actions;
+        let dt = MainState::FIXED_PHYSICS_TIMESTEP;
 
-        player.jump = match player.jump
+        // cells are filled after the player moves, so one filled onto the player
+        // last tick lifts them out on top of it
+        let buried =
+            self.events.previous::<TileChanged>()
+            .iter()
+            .any(|changed| player.overlapped_cells(&self.chunks).contains(&changed.cell.into()));
+        if buried
         {
-            Some(m) => 
+            while let Some(top) = player.overlapped_cells(&self.chunks).iter().map(|cell| cell.y).min()
             {
-                m.act(player.grounded, || {player.vel.y -= 30.0; player.grounded = false; }, context.time.average_delta().as_secs_f32())
-            },
-            None => None,
-        };
+                player.position.y = top as f32 - player.collision_region.p1.y;
+            }
+        }
 
-        // gravity
-        player.vel.y += 9.8 / 30.0;
+        player.vel.x = (actions.value(ActionCode::MoveRight) - actions.value(ActionCode::MoveLeft)) * Player::RUN_SPEED;
 
-        player.pos += player.vel / 30.0;
+        if actions.just_pressed(ActionCode::Jump)
+        {
+            player.jump.set_active_ticking((), Player::JUMP_TIME_BUFFER);
+        }
+        if player.grounded
+        {
+            player.coyote.set_active_ticking((), Player::COYOTE_TIME);
+        }
+
+        // a buffered jump happens as soon as the player is, or just was, on the ground
+        if player.jump.is_active().is_some() && player.coyote.is_active().is_some()
+        {
+            player.vel.y = -Player::JUMP_SPEED;
+            player.jump = Message::Inactive;
+            player.coyote = Message::Inactive;
+        }
+
+        // gravity, y pointing down
+        player.vel.y = (player.vel.y + Player::GRAVITY * dt).min(Player::MAX_FALL_SPEED);
 
-        if (player.pos + player.feet_offset).y > 400.0
+        if player.move_axis(&self.chunks, 0, player.vel.x * dt)
+        {
+            player.vel.x = 0.0;
+        }
+
+        let falling = player.vel.y > 0.0;
+        let hit = player.move_axis(&self.chunks, 1, player.vel.y * dt);
+        if hit
         {
-            player.pos.y = 400.0 - player.feet_offset.y;
-            player.grounded = true;
             player.vel.y = 0.0;
         }
+        player.grounded = hit && falling;
 
-        if player.pos.x > 400.0
+        if player.position.y > player.spawn.y + Player::FALL_LIMIT
         {
-            player.pos.x = 0.0;
+            *player = Player::new(player.spawn);
         }
 
+        player.jump.tick(dt);
+        player.coyote.tick(dt);
+
         Ok(())
     }
 }
@@ -64,9 +173,173 @@ impl crate::FixedUpdate<Player> for crate::MainState
 impl crate::Draw<Player> for crate::MainState
 {
     fn draw(&self, _context: &mut ggez::Context, canvas: &mut ggez::graphics::Canvas) -> ggez::GameResult {
-        let draw_params = ggez::graphics::DrawParam::new().dest(self.player.pos).offset(Vec2::new(0.5, 0.5));
+        let player = &self.world.player;
+        let screen_pos = 16.0 * (player.position_get() - self.world_pos);
+
+        // the sprite is 48 pixels, drawn to cover the 1.5 tile tall collision region
+        let draw_params =
+            ggez::graphics::DrawParam::new()
+            .dest(screen_pos)
+            .offset(Vec2::new(0.5, 0.5))
+            .scale([0.5, 0.5]);
         canvas.draw(&self.assets.player_image, draw_params);
 
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+    use crate::{game_object::FixedUpdate, input::action_state::ActionState, world::{TickInput, World}};
+
+    /// A world with a floor along row 10 of the first chunk, and the player standing on it
+    fn floor_world() -> World
+    {
+        let mut world = World::new(0);
+        for x in 0..16u8
+        {
+            world.chunks[0][(x, 10u8).into()].foreground_object = ObjectType::Filled;
+        }
+        world.player = Player::new(Vec2::new(4.5, 9.25));
+        step(&mut world, &[], 1);
+        world
+    }
+
+    fn step(world: &mut World, held: &[ActionCode], ticks: u32)
+    {
+        for _ in 0..ticks
+        {
+            let mut actions = ActionState::default();
+            actions.tap(held);
+            actions.begin_tick();
+            FixedUpdate::<Player>::fixed_update(world, &TickInput::new(actions, None)).unwrap();
+        }
+    }
+
+    #[test]
+    fn lands_on_a_filled_cell()
+    {
+        let mut world = floor_world();
+        world.player.position.y = 5.0;
+        step(&mut world, &[], 60);
+
+        assert!(world.player.grounded);
+        assert!((world.player.position.y - 9.25).abs() < 1e-4);
+    }
+
+    #[test]
+    fn walls_stop_running()
+    {
+        let mut world = floor_world();
+        world.chunks[0][(7u8, 9u8).into()].foreground_object = ObjectType::Filled;
+        step(&mut world, &[ActionCode::MoveRight], 60);
+
+        assert!((world.player.position.x - 6.5).abs() < 1e-4);
+    }
+
+    #[test]
+    fn jump_leaves_the_ground()
+    {
+        let mut world = floor_world();
+        step(&mut world, &[ActionCode::Jump], 1);
+        step(&mut world, &[], 5);
+
+        assert!(!world.player.grounded);
+        assert!(world.player.position.y < 9.0);
+    }
+
+    #[test]
+    fn jump_pressed_just_before_landing_is_buffered()
+    {
+        let mut world = floor_world();
+        // falling fast, a couple of ticks above the floor
+        world.player.position.y = 8.6;
+        world.player.vel.y = Player::MAX_FALL_SPEED;
+        world.player.grounded = false;
+
+        step(&mut world, &[ActionCode::Jump], 1);
+        step(&mut world, &[], 3);
+        assert!(world.player.vel.y < 0.0);
+    }
+
+    #[test]
+    fn coyote_time_allows_a_late_jump()
+    {
+        let mut world = floor_world();
+        // run off the right end of the floor
+        world.player.position.x = 15.5;
+        step(&mut world, &[ActionCode::MoveRight], 10);
+        assert!(!world.player.grounded);
+
+        // still within coyote time right after leaving; not long after
+        let mut late = world.clone();
+        step(&mut world, &[ActionCode::Jump], 1);
+        assert!(world.player.vel.y < 0.0);
+
+        step(&mut late, &[], 20);
+        step(&mut late, &[ActionCode::Jump], 1);
+        assert!(late.player.vel.y > 0.0);
+    }
+
+    #[test]
+    fn a_cell_filled_onto_the_player_lifts_them()
+    {
+        let mut world = floor_world();
+        world.chunks[0][(4u8, 9u8).into()].foreground_object = ObjectType::Filled;
+        world.events.send(TileChanged { cell: (4, 9), object: ObjectType::Filled });
+        world.events.begin_tick(1);
+        step(&mut world, &[], 1);
+
+        assert!((world.player.position.y - 8.25).abs() < 1e-4);
+    }
+
+    #[test]
+    fn a_saved_world_still_lifts_a_player_filled_over()
+    {
+        // jumping, so only the lift can take the player above the cell
+        let mut world = floor_world();
+        let mut actions = ActionState::default();
+        actions.tap(&[ActionCode::Jump, ActionCode::Click]);
+        actions.begin_tick();
+        world.step(&TickInput::new(actions, Some(Vec2::new(4.5, 9.5)))).unwrap();
+
+        // the cell is filled after the player moves, so the lift comes on the next tick
+        let mut loaded = World::from_bytes(&world.to_bytes().unwrap()).unwrap();
+        assert_eq!(loaded.checksum(), world.checksum());
+        for world in [&mut world, &mut loaded]
+        {
+            world.step(&TickInput::default()).unwrap();
+            assert!(world.player.position.y < 8.25);
+        }
+    }
+
+    #[test]
+    fn falling_out_of_the_world_respawns()
+    {
+        let spawn = Vec2::new(4.5, 0.0);
+        let mut world = World::new(0);
+        // nothing to land on
+        world.chunks.clear();
+        world.player = Player::new(spawn);
+
+        // fall until the tick that goes past the limit, and back to the spawn point
+        let mut fell = false;
+        for _ in 0..600
+        {
+            let before = world.player.position.y;
+            step(&mut world, &[], 1);
+            if world.player.position.y < before
+            {
+                fell = true;
+                break;
+            }
+        }
+
+        assert!(fell);
+        assert_eq!(world.player.position, spawn);
+        assert_eq!(world.player.vel, Vec2::ZERO);
+        assert!(!world.player.grounded);
+    }
+}
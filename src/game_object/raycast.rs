@@ -0,0 +1,317 @@
+use ggez::{glam::{IVec2, Vec2}, GameError, GameResult};
+
+// local imports
+use crate::{console::Command, world::World};
+use super::{collider_type::Collider, grid::{Chunk, ObjectType}, shape::Shape, HasRegion};
+
+/// A half-line from `origin`, cut off after `max_distance` tiles
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ray
+{
+    origin: Vec2,
+    /// Always of length 1
+    direction: Vec2,
+    max_distance: f32,
+}
+
+/// What a ray can hit, like `Capture` picks what a layer consumes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Layers
+{
+    /// Filled cells of the chunks
+    pub cells: bool,
+    pub enemies: bool,
+    pub missiles: bool,
+    pub player: bool,
+}
+
+impl Layers
+{
+    pub const ALL: Layers = Layers { cells: true, enemies: true, missiles: true, player: true };
+}
+
+/// What a ray hit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target
+{
+    Cell(IVec2),
+    /// An id in `World::enemies`
+    Enemy(u16),
+    Missile(u16),
+    Player,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RayHit
+{
+    pub target: Target,
+    /// Tiles from the ray's origin
+    pub distance: f32,
+    pub point: Vec2,
+    /// The outward normal of what was hit, where it was hit
+    pub normal: Vec2,
+}
+
+impl Ray
+{
+    /// A ray from `origin` heading toward `direction`, which needn't be normalized
+    pub fn new(origin: Vec2, direction: Vec2, max_distance: f32) -> GameResult<Self>
+    {
+        let direction = direction.try_normalize().ok_or_else(|| GameError::CustomError(format!("a ray can't head toward {direction}")))?;
+        if !origin.is_finite() || !max_distance.is_finite() || max_distance < 0.0
+        {
+            return Err(GameError::CustomError(format!("a ray can't start at {origin} and reach {max_distance} tiles")));
+        }
+        Ok(Self { origin, direction, max_distance })
+    }
+
+    pub fn origin(&self) -> Vec2
+    {
+        self.origin
+    }
+
+    pub fn direction(&self) -> Vec2
+    {
+        self.direction
+    }
+
+    pub fn max_distance(&self) -> f32
+    {
+        self.max_distance
+    }
+
+    /// The point `distance` tiles along the ray
+    pub fn at(&self, distance: f32) -> Vec2
+    {
+        self.origin + distance * self.direction
+    }
+
+    /// The first thing in `layers` the ray hits, favoring colliders over cells on a tie
+    pub fn cast(&self, world: &World, layers: Layers) -> Option<RayHit>
+    {
+        let cell = layers.cells.then(|| self.cells(&world.chunks, true).into_iter().next()).flatten();
+
+        self.colliders(world, layers)
+        .into_iter()
+        .chain(cell)
+        .reduce(|first, next| if next.distance < first.distance { next } else { first })
+    }
+
+    /// Everything in `layers` the ray hits, nearest first
+    pub fn cast_all(&self, world: &World, layers: Layers) -> Vec<RayHit>
+    {
+        let mut hits = self.colliders(world, layers);
+        if layers.cells
+        {
+            hits.extend(self.cells(&world.chunks, false));
+        }
+        hits.sort_by(|a, b| a.distance.total_cmp(&b.distance));
+        hits
+    }
+
+    /// Every collider in `layers` the ray meets
+    fn colliders(&self, world: &World, layers: Layers) -> Vec<RayHit>
+    {
+        let mut colliders: Vec<(Target, Shape)> = Vec::new();
+        if layers.enemies
+        {
+            colliders.extend(world.enemies.by_id().map(|(id, e)| (Target::Enemy(id), HasRegion::<Collider>::region_shape(e))));
+        }
+        if layers.missiles
+        {
+            colliders.extend(world.missiles.by_id().map(|(id, m)| (Target::Missile(id), HasRegion::<Collider>::region_shape(m))));
+        }
+        if layers.player
+        {
+            colliders.push((Target::Player, world.player.region_shape()));
+        }
+
+        colliders
+        .into_iter()
+        .filter_map(|(target, shape)| shape.raycast(self).map(|(distance, normal)| RayHit { target, distance, point: self.at(distance), normal }))
+        .collect()
+    }
+
+    /// Walk the cells the ray crosses, with DDA, collecting the filled ones it
+    /// enters, or just the first. A ray starting inside a filled cell hits it at once.
+    fn cells(&self, chunks: &[Chunk], first_only: bool) -> Vec<RayHit>
+    {
+        let filled = |cell: IVec2|
+            matches!(Chunk::object_at_world_cell(chunks, cell), Some(object) if matches!(object.foreground_object, ObjectType::Filled));
+
+        let d = self.direction;
+        let step = IVec2::new(d.x.signum() as i32, d.y.signum() as i32);
+
+        // the distance to the next cell boundary on each axis, and across a whole cell
+        let boundary = |p: f32, d: f32| if d > 0.0 { (p.floor() + 1.0 - p) / d } else if d < 0.0 { (p - p.floor()) / -d } else { f32::INFINITY };
+        let mut next = Vec2::new(boundary(self.origin.x, d.x), boundary(self.origin.y, d.y));
+        let across = Vec2::new(1.0 / d.x.abs(), 1.0 / d.y.abs());
+
+        // past where the ray leaves the box around every chunk, there are no cells to hit
+        let (min, max) = chunks.iter().fold((Vec2::INFINITY, Vec2::NEG_INFINITY),
+            |(min, max), chunk| (min.min(chunk.upper_left_position), max.max(chunk.upper_left_position + 16.0)));
+        let leave = |p: f32, d: f32, min: f32, max: f32| if d > 0.0 { (max - p) / d } else if d < 0.0 { (min - p) / d } else { f32::INFINITY };
+        let end = self.max_distance.min(leave(self.origin.x, d.x, min.x, max.x)).min(leave(self.origin.y, d.y, min.y, max.y));
+
+        let mut cell = self.origin.floor().as_ivec2();
+        let mut distance = 0.0;
+        // facing back along the ray, for a ray starting inside a cell
+        let mut normal = -d;
+        let mut hits = Vec::new();
+
+        while distance <= end
+        {
+            if filled(cell)
+            {
+                hits.push(RayHit { target: Target::Cell(cell), distance, point: self.at(distance), normal });
+                if first_only
+                {
+                    break;
+                }
+            }
+
+            if next.x < next.y
+            {
+                distance = next.x;
+                next.x += across.x;
+                cell.x += step.x;
+                normal = Vec2::new(-step.x as f32, 0.0);
+            }
+            else
+            {
+                distance = next.y;
+                next.y += across.y;
+                cell.y += step.y;
+                normal = Vec2::new(0.0, -step.y as f32);
+            }
+        }
+
+        hits
+    }
+
+    /// Console commands casting rays
+    pub fn commands() -> Vec<Command>
+    {
+        vec![
+            Command
+            {
+                name: "ray",
+                args: "<x> <y> <toward x> <toward y>",
+                help: "list everything a ray from x, y hits, up to 64 tiles away",
+                run: |args, target|
+                {
+                    let origin = Vec2::new(args.next("x")?, args.next("y")?);
+                    let ray = Ray::new(origin, Vec2::new(args.next("toward x")?, args.next("toward y")?), 64.0)?;
+                    args.finish()?;
+                    let hits: Vec<String> =
+                        ray.cast_all(target.world(), Layers::ALL)
+                        .iter()
+                        .map(|hit| format!("{:?} at {:.2} tiles, {}", hit.target, hit.distance, hit.point))
+                        .collect();
+
+                    Ok(if hits.is_empty() { "nothing hit".to_string() } else { hits.join("\n") })
+                },
+            },
+        ]
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+    use crate::game_object::{enemy::Enemy, HasPosition};
+
+    /// The default world without its enemy, a cell filled at `filled`, and enemies at `enemies`
+    fn world(filled: &[(i32, i32)], enemies: &[Vec2]) -> World
+    {
+        let mut world = World::new(0);
+        world.enemies = enemies.iter().map(|&p| HasRegion::<Collider>::region_set(Enemy::default().position_set(p), ((-0.5, -0.5), (0.5, 0.5)).into())).collect();
+        for &cell in filled
+        {
+            Chunk::object_at_world_cell_mut(&mut world.chunks, cell.into()).unwrap().foreground_object = ObjectType::Filled;
+        }
+        world
+    }
+
+    const CELLS: Layers = Layers { cells: true, enemies: false, missiles: false, player: false };
+
+    #[test]
+    fn first_hit_is_the_nearest_cell_or_collider()
+    {
+        let world = world(&[(8, 2)], &[Vec2::new(5.0, 2.5)]);
+        let ray = Ray::new(Vec2::new(0.5, 2.5), Vec2::X, 20.0).unwrap();
+
+        let hit = ray.cast(&world, Layers::ALL).unwrap();
+        assert_eq!(hit.target, Target::Enemy(0));
+        assert_eq!(hit.distance, 4.0);
+        assert_eq!(hit.normal, -Vec2::X);
+
+        // filtered down to cells, the enemy is ignored
+        let hit = ray.cast(&world, CELLS).unwrap();
+        assert_eq!(hit.target, Target::Cell((8, 2).into()));
+        assert_eq!(hit.point, Vec2::new(8.0, 2.5));
+        assert_eq!(hit.normal, -Vec2::X);
+
+        // and nothing is hit past the ray's end
+        assert!(Ray::new(Vec2::new(0.5, 2.5), Vec2::X, 3.0).unwrap().cast(&world, Layers::ALL).is_none());
+    }
+
+    #[test]
+    fn cast_all_crosses_chunks_nearest_first()
+    {
+        // the default world's chunks meet at 0 on both axes
+        let world = world(&[(2, -2), (-5, 5)], &[Vec2::new(-1.5, 2.0)]);
+        let ray = Ray::new(Vec2::new(4.2, -3.5), Vec2::new(-1.0, 1.0), 20.0).unwrap();
+
+        let targets: Vec<Target> = ray.cast_all(&world, Layers::ALL).iter().map(|hit| hit.target).collect();
+        assert_eq!(targets, vec![Target::Cell((2, -2).into()), Target::Enemy(0), Target::Cell((-5, 5).into())]);
+    }
+
+    #[test]
+    fn starting_inside_a_cell_hits_it_at_once()
+    {
+        let world = world(&[(2, 2), (4, 2)], &[]);
+        let ray = Ray::new(Vec2::new(2.25, 2.5), Vec2::X, 10.0).unwrap();
+
+        let hits = ray.cast_all(&world, CELLS);
+        assert_eq!(hits.len(), 2);
+        assert_eq!((hits[0].target, hits[0].distance, hits[0].normal), (Target::Cell((2, 2).into()), 0.0, -Vec2::X));
+        assert_eq!((hits[1].target, hits[1].distance), (Target::Cell((4, 2).into()), 1.75));
+    }
+
+    #[test]
+    fn rays_meet_shaped_colliders()
+    {
+        // the player is a capsule, rounded at the top and bottom
+        let mut world = world(&[], &[]);
+        world.player = world.player.position_set(Vec2::new(3.0, 3.0));
+
+        let down = Ray::new(Vec2::new(3.0, 0.0), Vec2::Y, 10.0).unwrap();
+        let hit = down.cast(&world, Layers::ALL).unwrap();
+        assert_eq!(hit.target, Target::Player);
+        assert!((hit.distance - 2.25).abs() < 1e-4);
+        assert!((hit.normal + Vec2::Y).length() < 1e-4);
+
+        // past the corner of its box, but not its rounded end
+        let grazing = Ray::new(Vec2::new(3.45, 0.0), Vec2::Y, 2.3).unwrap();
+        assert!(grazing.cast(&world, Layers::ALL).is_none());
+
+        assert!(Ray::new(Vec2::ZERO, Vec2::ZERO, 1.0).is_err());
+    }
+
+    #[test]
+    fn rays_that_hit_nothing_end()
+    {
+        let world = world(&[], &[]);
+        for direction in [Vec2::X, Vec2::new(-1.0, 0.3), Vec2::NEG_Y]
+        {
+            // far longer than the chunks reach, so the walk has to stop where they do
+            let ray = Ray::new(Vec2::new(0.5, 0.5), direction, 1e9).unwrap();
+            assert!(ray.cast_all(&world, Layers::ALL).is_empty());
+        }
+
+        assert!(Ray::new(Vec2::ZERO, Vec2::X, f32::INFINITY).is_err());
+        assert!(Ray::new(Vec2::ZERO, Vec2::X, f32::NAN).is_err());
+    }
+}
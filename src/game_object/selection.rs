@@ -0,0 +1,443 @@
+use ggez::glam::Vec2;
+
+// local imports
+use crate::{gui::inspector::Inspect, util::hash_map_tracker::HashMapTracker, world::World};
+use super::{collider_type::Selection, enemy::Behavior, enemy_wall::EnemyWall, shape::Shape, HasPosition, HasRegion};
+
+/// Something that can be selected by clicking inside its selection region
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Entity
+{
+    Cannon,
+    Enemy(u16),
+    Missile(u16),
+    Wall(u16),
+}
+
+impl Entity
+{
+    /// Every entity, the ones drawn smallest first, so a click picks a missile
+    /// over the enemy behind it
+    pub fn all(world: &World, walls: &HashMapTracker<EnemyWall>) -> Vec<Entity>
+    {
+        world.missiles.by_id().map(|(id, _)| Entity::Missile(id))
+        .chain(world.enemies.by_id().map(|(id, _)| Entity::Enemy(id)))
+        .chain(walls.by_id().map(|(id, _)| Entity::Wall(id)))
+        .chain([Entity::Cannon])
+        .collect()
+    }
+
+    /// The world-space corners of this entity's selection region, if it still exists
+    pub fn bounds(self, world: &World, walls: &HashMapTracker<EnemyWall>) -> Option<(Vec2, Vec2)>
+    {
+        fn corners<P: HasRegion<Selection>>(p: &P) -> (Vec2, Vec2)
+        {
+            (p.position_get() + p.region_get().p0, p.position_get() + p.region_get().p1)
+        }
+
+        match self
+        {
+            Entity::Cannon => Some(corners(&world.cannon)),
+            Entity::Enemy(id) => world.enemies.get(&id).map(corners),
+            Entity::Missile(id) => world.missiles.get(&id).map(corners),
+            Entity::Wall(id) => walls.get(&id).map(corners),
+        }
+    }
+
+    /// Every entity whose selection region overlaps the world rectangle from `min` to `max`
+    pub fn overlapping(min: Vec2, max: Vec2, world: &World, walls: &HashMapTracker<EnemyWall>) -> Vec<Entity>
+    {
+        let Ok(rect) = Shape::aabb(min, max) else { return Vec::new() };
+        Entity::all(world, walls)
+        .into_iter()
+        .filter(|e| e.shape(world, walls).is_some_and(|shape| rect.contact(&shape).is_some()))
+        .collect()
+    }
+
+    /// The entity whose selection region contains world point `pos`
+    pub fn at(pos: Vec2, world: &World, walls: &HashMapTracker<EnemyWall>) -> Option<Entity>
+    {
+        Entity::all(world, walls)
+        .into_iter()
+        .find(|e| e.shape(world, walls).is_some_and(|shape| shape.contains(pos)))
+    }
+
+    /// The world-space shape of this entity's selection region, if it still exists
+    fn shape(self, world: &World, walls: &HashMapTracker<EnemyWall>) -> Option<Shape>
+    {
+        match self
+        {
+            Entity::Cannon => Some(world.cannon.region_shape()),
+            Entity::Enemy(id) => world.enemies.get(&id).map(HasRegion::<Selection>::region_shape),
+            Entity::Missile(id) => world.missiles.get(&id).map(HasRegion::<Selection>::region_shape),
+            Entity::Wall(id) => walls.get(&id).map(HasRegion::region_shape),
+        }
+    }
+
+    fn position(self, world: &World, walls: &HashMapTracker<EnemyWall>) -> Option<Vec2>
+    {
+        match self
+        {
+            Entity::Cannon => Some(world.cannon.position_get()),
+            Entity::Enemy(id) => world.enemies.get(&id).map(HasPosition::position_get),
+            Entity::Missile(id) => world.missiles.get(&id).map(HasPosition::position_get),
+            Entity::Wall(id) => walls.get(&id).map(HasPosition::position_get),
+        }
+    }
+
+    /// The entity itself, to be inspected, if it still exists
+    pub fn inspectable<'a>(self, world: &'a mut World, walls: &'a mut HashMapTracker<EnemyWall>) -> Option<&'a mut dyn Inspect>
+    {
+        match self
+        {
+            Entity::Cannon => Some(&mut world.cannon),
+            Entity::Enemy(id) => world.enemies.get_mut(&id).map(|e| e as &mut dyn Inspect),
+            Entity::Missile(id) => world.missiles.get_mut(&id).map(|m| m as &mut dyn Inspect),
+            Entity::Wall(id) => walls.get_mut(&id).map(|w| w as &mut dyn Inspect),
+        }
+    }
+}
+
+/// How a click or drag changes what is already selected
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelectMode
+{
+    Replace,
+    /// Keep what is selected, and add to it
+    Add,
+    /// Deselect what was selected, and select the rest
+    Toggle,
+}
+
+/// The selected entities, and the rectangle being dragged out to change them
+#[derive(Debug, Clone, Default)]
+pub struct Group
+{
+    entities: Vec<Entity>,
+    /// Where the drag started, in world tiles, and how it changes the selection
+    drag: Option<(Vec2, SelectMode)>,
+}
+
+impl Group
+{
+    /// A drag shorter than this, in tiles, is a click on what is under it
+    const CLICK_DISTANCE: f32 = 0.25;
+
+    pub fn entities(&self) -> &[Entity]
+    {
+        &self.entities
+    }
+
+    pub fn is_empty(&self) -> bool
+    {
+        self.entities.is_empty()
+    }
+
+    pub fn clear(&mut self)
+    {
+        self.entities.clear();
+    }
+
+    /// Forget entities that no longer exist
+    pub fn retain_existing(&mut self, world: &World, walls: &HashMapTracker<EnemyWall>)
+    {
+        self.entities.retain(|e| e.bounds(world, walls).is_some());
+    }
+
+    pub fn begin_drag(&mut self, pos: Vec2, mode: SelectMode)
+    {
+        self.drag = Some((pos, mode));
+    }
+
+    /// Where the drag being made started
+    pub fn drag_start(&self) -> Option<Vec2>
+    {
+        self.drag.map(|(start, _)| start)
+    }
+
+    /// Select what the drag ending at `pos` picked: the entity under it if it
+    /// barely moved, otherwise every entity overlapping its rectangle
+    pub fn end_drag(&mut self, pos: Vec2, world: &World, walls: &HashMapTracker<EnemyWall>)
+    {
+        let Some((start, mode)) = self.drag.take() else { return };
+
+        let picked =
+            if (pos - start).abs().max_element() < Group::CLICK_DISTANCE
+            {
+                Entity::at(pos, world, walls).into_iter().collect()
+            }
+            else
+            {
+                Entity::overlapping(start.min(pos), start.max(pos), world, walls)
+            };
+        self.select(picked, mode);
+    }
+
+    pub fn select(&mut self, picked: Vec<Entity>, mode: SelectMode)
+    {
+        match mode
+        {
+            SelectMode::Replace => self.entities = picked,
+            SelectMode::Add =>
+            {
+                for e in picked
+                {
+                    if !self.entities.contains(&e)
+                    {
+                        self.entities.push(e);
+                    }
+                }
+            }
+            SelectMode::Toggle =>
+            {
+                for e in picked
+                {
+                    match self.entities.iter().position(|&s| s == e)
+                    {
+                        Some(at) => { self.entities.remove(at); }
+                        None => self.entities.push(e),
+                    }
+                }
+            }
+        }
+    }
+
+    /// Remove every selected entity but the cannon, returning how many were removed
+    pub fn delete(&mut self, world: &mut World, walls: &mut HashMapTracker<EnemyWall>) -> usize
+    {
+        self.retain_existing(world, walls);
+
+        let mut deleted = 0;
+        for e in &self.entities
+        {
+            match *e
+            {
+                Entity::Enemy(id) => { world.enemies.delete(id); deleted += 1; }
+                Entity::Missile(id) => { world.missiles.delete(id); deleted += 1; }
+                Entity::Wall(id) => { walls.delete(id); deleted += 1; }
+                Entity::Cannon => (),
+            }
+        }
+
+        self.entities.retain(|e| *e == Entity::Cannon);
+        deleted
+    }
+
+    /// Send the group to `target`, keeping its formation: enemies walk there,
+    /// everything else is put there at once
+    pub fn move_to(&self, target: Vec2, world: &mut World, walls: &mut HashMapTracker<EnemyWall>)
+    {
+        fn put<P: HasPosition + Clone>(p: &mut P, at: Vec2)
+        {
+            *p = p.clone().position_set(at);
+        }
+
+        let positions: Vec<(Entity, Vec2)> = self.entities.iter().filter_map(|&e| Some((e, e.position(world, walls)?))).collect();
+        if positions.is_empty()
+        {
+            return;
+        }
+        let center = positions.iter().map(|(_, p)| *p).sum::<Vec2>() / positions.len() as f32;
+
+        for (e, pos) in positions
+        {
+            let at = target + pos - center;
+            match e
+            {
+                Entity::Cannon => put(&mut world.cannon, at),
+                Entity::Enemy(id) => if let Some(e) = world.enemies.get_mut(&id) { e.behavior = Behavior::MoveTo(at) },
+                Entity::Missile(id) => if let Some(m) = world.missiles.get_mut(&id) { put(m, at) },
+                Entity::Wall(id) => if let Some(w) = walls.get_mut(&id) { put(w, at) },
+            }
+        }
+    }
+
+    /// Give every selected enemy `behavior`
+    pub fn assign(&self, behavior: Behavior, world: &mut World)
+    {
+        for e in &self.entities
+        {
+            if let Entity::Enemy(id) = *e
+            {
+                if let Some(enemy) = world.enemies.get_mut(&id)
+                {
+                    enemy.behavior = behavior;
+                }
+            }
+        }
+    }
+
+    pub fn enemy_count(&self) -> usize
+    {
+        self.entities.iter().filter(|e| matches!(e, Entity::Enemy(_))).count()
+    }
+}
+
+impl crate::Draw<Entity> for crate::MainState
+{
+    /// Outline every selected entity's selection region, and the rectangle being dragged
+    fn draw(&self, context: &mut ggez::Context, canvas: &mut ggez::graphics::Canvas) -> ggez::GameResult
+    {
+        use ggez::graphics;
+
+        let mouse = Vec2::from(context.mouse.position()) / 16.0 + self.world_pos;
+        let mut outline = |min: Vec2, max: Vec2, color: graphics::Color| -> ggez::GameResult
+        {
+            let top_left = 16.0 * (min - self.world_pos);
+            let size = 16.0 * (max - min);
+            let mesh =
+                graphics::Mesh::new_rectangle(
+                    context,
+                    graphics::DrawMode::stroke(2.0),
+                    graphics::Rect::new(top_left.x, top_left.y, size.x, size.y),
+                    color,
+                )?;
+            canvas.draw(&mesh, graphics::DrawParam::new());
+            Ok(())
+        };
+
+        for (min, max) in self.selection.entities().iter().filter_map(|e| e.bounds(&self.world, &self.enemy_walls))
+        {
+            outline(min, max, graphics::Color::YELLOW)?;
+        }
+
+        if let Some(start) = self.selection.drag_start()
+        {
+            outline(start.min(mouse), start.max(mouse), graphics::Color::from_rgba(255, 255, 0, 128))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+    use crate::{game_object::{enemy::Enemy, missile::Missile}, world::TickInput};
+
+    /// The default world's enemy at (12, 5), and another at (14, 5)
+    fn two_enemies() -> World
+    {
+        let mut world = World::new(0);
+        world.enemies.push(Enemy::default().position_set(Vec2::new(14.0, 5.0)));
+        world
+    }
+
+    #[test]
+    fn click_picks_the_entity_under_it()
+    {
+        let mut world = World::new(0);
+        let mut walls = HashMapTracker::new();
+        walls.push(EnemyWall::default().position(Vec2::new(20.0, 20.0)).size(Vec2::new(4.0, 1.0)));
+
+        // the default enemy sits at (12, 5), the cannon at the origin
+        assert_eq!(Entity::at(Vec2::new(12.5, 5.5), &world, &walls), Some(Entity::Enemy(0)));
+        assert_eq!(Entity::at(Vec2::new(0.2, -0.3), &world, &walls), Some(Entity::Cannon));
+        assert_eq!(Entity::at(Vec2::new(23.0, 20.5), &world, &walls), Some(Entity::Wall(0)));
+        assert_eq!(Entity::at(Vec2::new(30.0, 30.0), &world, &walls), None);
+
+        // a missile in front of the enemy is picked over it
+        world.missiles.push(Missile::new(world.enemies[&0].position_get(), Vec2::X));
+        assert_eq!(Entity::at(Vec2::new(12.0, 5.0), &world, &walls), Some(Entity::Missile(0)));
+    }
+
+    #[test]
+    fn removed_entities_have_no_bounds()
+    {
+        let mut world = World::new(0);
+        let walls = HashMapTracker::new();
+
+        assert!(Entity::Enemy(0).bounds(&world, &walls).is_some());
+        world.enemies.clear();
+        assert!(Entity::Enemy(0).bounds(&world, &walls).is_none());
+        assert!(Entity::Missile(3).inspectable(&mut world, &mut HashMapTracker::new()).is_none());
+    }
+
+    #[test]
+    fn drags_replace_add_and_toggle()
+    {
+        let world = two_enemies();
+        let walls = HashMapTracker::new();
+        let mut group = Group::default();
+
+        group.begin_drag(Vec2::new(15.0, 6.5), SelectMode::Replace);
+        group.end_drag(Vec2::new(11.5, 4.5), &world, &walls);
+        assert_eq!(group.entities(), [Entity::Enemy(0), Entity::Enemy(1)]);
+
+        // a click toggles just what is under it
+        group.begin_drag(Vec2::new(12.0, 5.0), SelectMode::Toggle);
+        group.end_drag(Vec2::new(12.1, 5.0), &world, &walls);
+        assert_eq!(group.entities(), [Entity::Enemy(1)]);
+
+        group.begin_drag(Vec2::new(-0.5, -0.5), SelectMode::Add);
+        group.end_drag(Vec2::new(0.5, 0.5), &world, &walls);
+        assert_eq!(group.entities(), [Entity::Enemy(1), Entity::Cannon]);
+
+        // a click on nothing deselects everything
+        group.begin_drag(Vec2::new(30.0, 30.0), SelectMode::Replace);
+        group.end_drag(Vec2::new(30.0, 30.0), &world, &walls);
+        assert!(group.is_empty());
+    }
+
+    #[test]
+    fn groups_move_in_formation()
+    {
+        let mut world = two_enemies();
+        let mut walls = HashMapTracker::new();
+        walls.push(EnemyWall::default().position(Vec2::new(20.0, 20.0)));
+        let mut group = Group::default();
+        group.select(vec![Entity::Enemy(0), Entity::Enemy(1), Entity::Wall(0)], SelectMode::Replace);
+
+        // the group is centered on (15.33, 10), so its wall lands 4.67 right and 10 down of the target
+        group.move_to(Vec2::new(15.0, 0.0), &mut world, &mut walls);
+        let wall = walls[&0].position_get();
+        assert!((wall - Vec2::new(19.6667, 10.0)).length() < 1e-3, "{wall}");
+
+        // enemies walk there instead
+        assert_eq!(world.enemies[&0].position_get(), Vec2::new(12.0, 5.0));
+        for _ in 0..240
+        {
+            world.step(&TickInput::default()).unwrap();
+        }
+        let first = world.enemies[&0].position_get();
+        assert!((first - Vec2::new(11.6667, -5.0)).length() < 1e-3, "{first}");
+        assert_eq!(world.enemies[&1].position_get() - first, Vec2::new(2.0, 0.0));
+        assert_eq!(world.enemies[&1].behavior, Behavior::Hold);
+    }
+
+    #[test]
+    fn selected_enemies_keep_their_ids_as_others_die()
+    {
+        let mut world = two_enemies();
+        world.enemies.push(Enemy::default().position_set(Vec2::new(16.0, 5.0)));
+        let mut group = Group::default();
+        group.select(vec![Entity::Enemy(1), Entity::Enemy(2), Entity::Cannon], SelectMode::Replace);
+
+        world.enemies.get_mut(&0).unwrap().damage(Enemy::MAX_HEALTH);
+        world.enemies.get_mut(&1).unwrap().damage(Enemy::MAX_HEALTH);
+        world.step(&TickInput::default()).unwrap();
+        group.retain_existing(&world, &HashMapTracker::new());
+
+        // the enemy at (16, 5) is still the one selected
+        assert_eq!(group.entities(), [Entity::Enemy(2), Entity::Cannon]);
+        assert_eq!(world.enemies[&2].position_get(), Vec2::new(16.0, 5.0));
+    }
+
+    #[test]
+    fn deleting_keeps_the_cannon()
+    {
+        let mut world = two_enemies();
+        world.enemies.push(Enemy::default().position_set(Vec2::new(16.0, 5.0)));
+        world.missiles.push(Missile::new(Vec2::new(3.0, 3.0), Vec2::X));
+        let mut walls = HashMapTracker::new();
+        let mut group = Group::default();
+        group.select(vec![Entity::Enemy(0), Entity::Cannon, Entity::Enemy(2), Entity::Missile(0)], SelectMode::Replace);
+
+        assert_eq!(group.delete(&mut world, &mut walls), 3);
+        assert_eq!(group.entities(), [Entity::Cannon]);
+        assert_eq!(world.enemies.len(), 1);
+        assert_eq!(world.enemies[&1].position_get(), Vec2::new(14.0, 5.0));
+        assert!(world.missiles.is_empty());
+    }
+}
@@ -0,0 +1,526 @@
+use ggez::{glam::Vec2, GameError, GameResult};
+use serde::{Deserialize, Serialize};
+
+// local imports
+use crate::util::vec_extension::RotateBy;
+use super::raycast::Ray;
+
+/// The outline of a collider, in whatever space its points are given in.
+///
+/// Every shape is convex, so any two can be tested against each other with the
+/// separating axis theorem. Build them with the constructors, which refuse
+/// shapes that are inverted, negative or concave.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Shape
+{
+    Aabb
+    {
+        #[serde(with = "crate::util::vec_extension::_Vec2Ser")]
+        min: Vec2,
+        #[serde(with = "crate::util::vec_extension::_Vec2Ser")]
+        max: Vec2,
+    },
+    Circle
+    {
+        #[serde(with = "crate::util::vec_extension::_Vec2Ser")]
+        center: Vec2,
+        radius: f32,
+    },
+    /// Every point within `radius` of the segment from `a` to `b`
+    Capsule
+    {
+        #[serde(with = "crate::util::vec_extension::_Vec2Ser")]
+        a: Vec2,
+        #[serde(with = "crate::util::vec_extension::_Vec2Ser")]
+        b: Vec2,
+        radius: f32,
+    },
+    /// A box turned `rotation` radians about its center
+    Obb
+    {
+        #[serde(with = "crate::util::vec_extension::_Vec2Ser")]
+        center: Vec2,
+        #[serde(with = "crate::util::vec_extension::_Vec2Ser")]
+        half_extents: Vec2,
+        rotation: f32,
+    },
+    /// Wound counter-clockwise
+    Polygon(#[serde(with = "vec2s")] Vec<Vec2>),
+}
+
+/// How two overlapping shapes overlap
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Contact
+{
+    /// The direction to push the second shape to separate it from the first
+    pub normal: Vec2,
+    /// How far it has to be pushed
+    pub depth: f32,
+}
+
+impl Shape
+{
+    pub fn aabb(min: Vec2, max: Vec2) -> GameResult<Self>
+    {
+        finite(&[min, max])?;
+        if min.cmpgt(max).any()
+        {
+            return Err(GameError::CustomError(format!("box corner {min} is not above and left of {max}")));
+        }
+        Ok(Shape::Aabb { min, max })
+    }
+
+    pub fn circle(center: Vec2, radius: f32) -> GameResult<Self>
+    {
+        finite(&[center])?;
+        non_negative("radius", radius)?;
+        Ok(Shape::Circle { center, radius })
+    }
+
+    pub fn capsule(a: Vec2, b: Vec2, radius: f32) -> GameResult<Self>
+    {
+        finite(&[a, b])?;
+        non_negative("radius", radius)?;
+        Ok(Shape::Capsule { a, b, radius })
+    }
+
+    pub fn obb(center: Vec2, half_extents: Vec2, rotation: f32) -> GameResult<Self>
+    {
+        finite(&[center, half_extents, Vec2::splat(rotation)])?;
+        non_negative("half width", half_extents.x)?;
+        non_negative("half height", half_extents.y)?;
+        Ok(Shape::Obb { center, half_extents, rotation })
+    }
+
+    /// A convex polygon through `points`, in either winding
+    pub fn polygon(mut points: Vec<Vec2>) -> GameResult<Self>
+    {
+        finite(&points)?;
+        if points.len() < 3
+        {
+            return Err(GameError::CustomError(format!("a polygon needs 3 points, not {}", points.len())));
+        }
+
+        let turns: Vec<f32> =
+            (0..points.len())
+            .map(|i| edge(&points, i).perp_dot(edge(&points, i + 1)))
+            .collect();
+        let area: f32 = (0..points.len()).map(|i| points[i].perp_dot(points[(i + 1) % points.len()])).sum();
+        if area == 0.0
+        {
+            return Err(GameError::CustomError("a polygon needs some area".to_string()));
+        }
+        if turns.iter().any(|&t| t * area < 0.0)
+        {
+            return Err(GameError::CustomError("a polygon must be convex".to_string()));
+        }
+
+        if area < 0.0
+        {
+            points.reverse();
+        }
+        Ok(Shape::Polygon(points))
+    }
+
+    /// The same shape moved by `by`
+    pub fn translated(&self, by: Vec2) -> Self
+    {
+        match self.clone()
+        {
+            Shape::Aabb { min, max } => Shape::Aabb { min: min + by, max: max + by },
+            Shape::Circle { center, radius } => Shape::Circle { center: center + by, radius },
+            Shape::Capsule { a, b, radius } => Shape::Capsule { a: a + by, b: b + by, radius },
+            Shape::Obb { center, half_extents, rotation } => Shape::Obb { center: center + by, half_extents, rotation },
+            Shape::Polygon(points) => Shape::Polygon(points.into_iter().map(|p| p + by).collect()),
+        }
+    }
+
+    /// The corners of the smallest axis-aligned box around the shape
+    pub fn bounds(&self) -> (Vec2, Vec2)
+    {
+        let (points, radius) = self.core();
+        let min = points.iter().copied().reduce(Vec2::min).expect("every shape has a point");
+        let max = points.iter().copied().reduce(Vec2::max).expect("every shape has a point");
+        (min - radius, max + radius)
+    }
+
+    /// Whether `point` is inside the shape or on its edge
+    pub fn contains(&self, point: Vec2) -> bool
+    {
+        separation(self, &Shape::Circle { center: point, radius: 0.0 }).0 >= 0.0
+    }
+
+    /// How `other` overlaps this shape, if it does. Shapes only touching don't.
+    pub fn contact(&self, other: &Shape) -> Option<Contact>
+    {
+        let (depth, normal) = separation(self, other);
+        (depth > 0.0).then_some(Contact { normal, depth })
+    }
+
+    /// How far along `ray` it first meets the shape, and the shape's outward normal there.
+    /// A ray starting inside meets it at once, facing back along the ray.
+    pub fn raycast(&self, ray: &Ray) -> Option<(f32, Vec2)>
+    {
+        let hit =
+            match self
+            {
+                Shape::Circle { center, radius } => raycast_circle(ray, *center, *radius),
+                Shape::Capsule { a, b, radius } =>
+                {
+                    let n = (*b - *a).perp().normalize_or_zero() * *radius;
+                    let side = Shape::polygon(vec![*a - n, *b - n, *b + n, *a + n]).ok().and_then(|rect| rect.raycast(ray));
+                    [raycast_circle(ray, *a, *radius), raycast_circle(ray, *b, *radius), side]
+                    .into_iter()
+                    .flatten()
+                    .min_by(|x, y| x.0.total_cmp(&y.0))
+                },
+                _ => raycast_polygon(ray, &self.core().0, &self.axes()),
+            };
+
+        hit.filter(|(distance, _)| *distance <= ray.max_distance())
+    }
+
+    /// Points around the edge of the shape, to draw it through
+    pub fn outline(&self) -> Vec<Vec2>
+    {
+        const ARC_POINTS: usize = 8;
+        let arc = |center: Vec2, radius: f32, from: f32| (0..=ARC_POINTS).map(move |i| center + radius * Vec2::from_angle(from + std::f32::consts::PI * i as f32 / ARC_POINTS as f32));
+
+        match self
+        {
+            Shape::Circle { center, radius } => arc(*center, *radius, 0.0).chain(arc(*center, *radius, std::f32::consts::PI)).collect(),
+            Shape::Capsule { a, b, radius } =>
+            {
+                let along = (*b - *a).try_normalize().unwrap_or(Vec2::X);
+                let start = along.perp().y.atan2(along.perp().x);
+                arc(*b, *radius, start - std::f32::consts::PI).chain(arc(*a, *radius, start)).collect()
+            },
+            _ => self.core().0,
+        }
+    }
+
+    /// The convex polygon, segment or point the shape is swept out from, and how far it is swept
+    fn core(&self) -> (Vec<Vec2>, f32)
+    {
+        match self
+        {
+            Shape::Aabb { min, max } => (vec![*min, Vec2::new(max.x, min.y), *max, Vec2::new(min.x, max.y)], 0.0),
+            Shape::Circle { center, radius } => (vec![*center], *radius),
+            Shape::Capsule { a, b, radius } => (vec![*a, *b], *radius),
+            Shape::Obb { center, half_extents: h, rotation } =>
+            {
+                let corners = [Vec2::new(-h.x, -h.y), Vec2::new(h.x, -h.y), Vec2::new(h.x, h.y), Vec2::new(-h.x, h.y)];
+                (corners.into_iter().map(|c| *center + c.rotate_by(*rotation)).collect(), 0.0)
+            },
+            Shape::Polygon(points) => (points.clone(), 0.0),
+        }
+    }
+
+    /// The directions to test the core along: the normals of its edges, and
+    /// both axes of a box, which may have no width or height to give edges
+    fn axes(&self) -> Vec<Vec2>
+    {
+        match self
+        {
+            Shape::Aabb { .. } => vec![Vec2::Y, Vec2::X],
+            Shape::Obb { rotation, .. } => vec![Vec2::Y.rotate_by(*rotation), Vec2::X.rotate_by(*rotation)],
+            Shape::Circle { .. } => Vec::new(),
+            Shape::Capsule { a, b, .. } => (*b - *a).perp().try_normalize().into_iter().collect(),
+            Shape::Polygon(points) => (0..points.len()).filter_map(|i| edge(points, i).perp().try_normalize()).collect(),
+        }
+    }
+}
+
+fn finite(points: &[Vec2]) -> GameResult
+{
+    match points.iter().find(|p| !p.is_finite())
+    {
+        Some(p) => Err(GameError::CustomError(format!("{p} is not a finite point"))),
+        None => Ok(()),
+    }
+}
+
+fn non_negative(what: &str, value: f32) -> GameResult
+{
+    if value >= 0.0
+    {
+        Ok(())
+    }
+    else
+    {
+        Err(GameError::CustomError(format!("{what} {value} is not zero or more")))
+    }
+}
+
+fn raycast_circle(ray: &Ray, center: Vec2, radius: f32) -> Option<(f32, Vec2)>
+{
+    let from_center = ray.origin() - center;
+    let c = from_center.length_squared() - radius * radius;
+    if c <= 0.0
+    {
+        return Some((0.0, -ray.direction()));
+    }
+
+    let b = from_center.dot(ray.direction());
+    let discriminant = b * b - c;
+    if b > 0.0 || discriminant < 0.0
+    {
+        return None;
+    }
+
+    let distance = -b - discriminant.sqrt();
+    let normal = (ray.at(distance) - center).try_normalize().unwrap_or(-ray.direction());
+    Some((distance, normal))
+}
+
+/// Clip the ray to the band the polygon spans along each of its `axes` in turn
+fn raycast_polygon(ray: &Ray, points: &[Vec2], axes: &[Vec2]) -> Option<(f32, Vec2)>
+{
+    let mut enter = (0.0, -ray.direction());
+    let mut exit = f32::INFINITY;
+
+    for &axis in axes
+    {
+        let (min, max) = project(points, 0.0, axis);
+        let from = ray.origin().dot(axis);
+        let toward = ray.direction().dot(axis);
+
+        if toward == 0.0
+        {
+            // parallel to the band, so always in or out of it
+            if from < min || from > max
+            {
+                return None;
+            }
+            continue;
+        }
+
+        // the side the ray comes in through faces back against it
+        let (near, far, normal) =
+            if toward > 0.0 { ((min - from) / toward, (max - from) / toward, -axis) }
+            else { ((max - from) / toward, (min - from) / toward, axis) };
+        if near > enter.0
+        {
+            enter = (near, normal);
+        }
+        exit = exit.min(far);
+
+        if enter.0 > exit
+        {
+            return None;
+        }
+    }
+
+    Some(enter)
+}
+
+/// The edge from point `i` to the next, wrapping around
+fn edge(points: &[Vec2], i: usize) -> Vec2
+{
+    points[(i + 1) % points.len()] - points[i % points.len()]
+}
+
+/// Where `points` swept by `radius` reach from and to along `axis`
+fn project(points: &[Vec2], radius: f32, axis: Vec2) -> (f32, f32)
+{
+    let dots = points.iter().map(|p| p.dot(axis));
+    (dots.clone().fold(f32::INFINITY, f32::min) - radius, dots.fold(f32::NEG_INFINITY, f32::max) + radius)
+}
+
+/// How far two shapes overlap along the axis they overlap least on, and
+/// that axis pointing from `a` toward `b`. Negative if they are apart.
+///
+/// Candidate axes are each shape's own, and, where either is rounded, the
+/// directions between the points of their cores, which cover every way the
+/// rounded corners can meet.
+fn separation(a: &Shape, b: &Shape) -> (f32, Vec2)
+{
+    let mut axes = a.axes();
+    axes.extend(b.axes());
+    let ((a, a_radius), (b, b_radius)) = (a.core(), b.core());
+
+    if a_radius + b_radius > 0.0
+    {
+        axes.extend(a.iter().flat_map(|pa| b.iter().filter_map(move |pb| (*pb - *pa).try_normalize())));
+    }
+    if axes.is_empty()
+    {
+        axes.push(Vec2::X);
+    }
+
+    let (depth, axis) =
+        axes
+        .into_iter()
+        .map(|axis|
+        {
+            let (a_min, a_max) = project(&a, a_radius, axis);
+            let (b_min, b_max) = project(&b, b_radius, axis);
+            ((a_max - b_min).min(b_max - a_min), axis)
+        })
+        .min_by(|x, y| x.0.total_cmp(&y.0))
+        .expect("there is always an axis");
+
+    let centroid = |points: &[Vec2]| points.iter().sum::<Vec2>() / points.len() as f32;
+    if (centroid(&b) - centroid(&a)).dot(axis) < 0.0
+    {
+        (depth, -axis)
+    }
+    else
+    {
+        (depth, axis)
+    }
+}
+
+/// (De)serializes polygon points the way `_Vec2Ser` does single ones
+mod vec2s
+{
+    use ggez::glam::Vec2;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(points: &[Vec2], serializer: S) -> Result<S::Ok, S::Error>
+    {
+        serializer.collect_seq(points.iter().map(|p| (p.x, p.y)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Vec2>, D::Error>
+    {
+        Ok(Vec::<(f32, f32)>::deserialize(deserializer)?.into_iter().map(Vec2::from).collect())
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+    use crate::game_object::{collider_type::Collider, Region};
+
+    fn contact(a: &Shape, b: &Shape) -> (Vec2, f32)
+    {
+        let c = a.contact(b).expect("the shapes overlap");
+        (c.normal, c.depth)
+    }
+
+    fn close(a: Vec2, b: Vec2) -> bool
+    {
+        (a - b).length() < 1e-4
+    }
+
+    #[test]
+    fn bad_shapes_are_errors()
+    {
+        assert!(Shape::aabb(Vec2::ONE, Vec2::ZERO).is_err());
+        assert!(Shape::circle(Vec2::ZERO, -1.0).is_err());
+        assert!(Shape::capsule(Vec2::ZERO, Vec2::NAN, 1.0).is_err());
+        assert!(Shape::obb(Vec2::ZERO, Vec2::new(1.0, -1.0), 0.0).is_err());
+        assert!(Shape::polygon(vec![Vec2::ZERO, Vec2::X]).is_err());
+        assert!(Shape::polygon(vec![Vec2::ZERO, Vec2::X, Vec2::new(2.0, 0.0)]).is_err());
+        // an arrowhead is concave
+        assert!(Shape::polygon(vec![Vec2::ZERO, Vec2::new(2.0, 1.0), Vec2::new(0.0, 2.0), Vec2::new(0.5, 1.0)]).is_err());
+
+        assert!(Region::<Collider>::new(Vec2::ONE, Vec2::ZERO).is_err());
+        assert!(Region::<Collider>::new(Vec2::ZERO, Vec2::ONE).is_ok());
+    }
+
+    #[test]
+    fn polygons_wind_counter_clockwise()
+    {
+        let clockwise = Shape::polygon(vec![Vec2::ZERO, Vec2::new(0.0, 1.0), Vec2::ONE, Vec2::X]).unwrap();
+        assert_eq!(clockwise, Shape::Polygon(vec![Vec2::X, Vec2::ONE, Vec2::new(0.0, 1.0), Vec2::ZERO]));
+        assert!(clockwise.contains(Vec2::splat(0.5)));
+        assert!(!clockwise.contains(Vec2::new(1.5, 0.5)));
+    }
+
+    #[test]
+    fn circles_push_apart_along_their_centers()
+    {
+        let a = Shape::circle(Vec2::ZERO, 1.0).unwrap();
+        let b = Shape::circle(Vec2::new(1.5, 0.0), 1.0).unwrap();
+        let (normal, depth) = contact(&a, &b);
+        assert!(close(normal, Vec2::X));
+        assert!((depth - 0.5).abs() < 1e-4);
+
+        // and the other way around
+        assert!(close(contact(&b, &a).0, -Vec2::X));
+        assert!(a.contact(&Shape::circle(Vec2::new(2.0, 0.0), 1.0).unwrap()).is_none());
+    }
+
+    #[test]
+    fn boxes_separate_along_their_least_overlap()
+    {
+        let a = Shape::aabb(Vec2::ZERO, Vec2::new(4.0, 4.0)).unwrap();
+        let b = Shape::aabb(Vec2::new(3.0, 1.0), Vec2::new(6.0, 2.0)).unwrap();
+        let (normal, depth) = contact(&a, &b);
+        assert!(close(normal, Vec2::X));
+        assert!((depth - 1.0).abs() < 1e-4);
+
+        // a box turned 45 degrees reaches only as far as its corner
+        let diamond = Shape::obb(Vec2::new(5.0, 2.0), Vec2::splat(1.0), std::f32::consts::FRAC_PI_4).unwrap();
+        let (normal, depth) = contact(&a, &diamond);
+        assert!(close(normal, Vec2::X));
+        assert!((depth - (2.0f32.sqrt() - 1.0)).abs() < 1e-4);
+        assert!(!diamond.contains(Vec2::new(4.1, 1.1)));
+        assert!(diamond.contains(Vec2::new(4.1, 2.0)));
+    }
+
+    #[test]
+    fn rounded_shapes_meet_polygons()
+    {
+        let square = Shape::polygon(vec![Vec2::ZERO, Vec2::X, Vec2::ONE, Vec2::new(0.0, 1.0)]).unwrap();
+
+        // a circle off the square's corner is nearer the corner than either edge line
+        let corner = Shape::circle(Vec2::new(1.5, 1.5), 0.6).unwrap();
+        assert!(square.contact(&corner).is_none());
+        let corner = Shape::circle(Vec2::new(1.4, 1.4), 0.6).unwrap();
+        let (normal, depth) = contact(&square, &corner);
+        assert!(close(normal, Vec2::ONE.normalize()));
+        assert!((depth - (0.6 - 0.4 * 2.0f32.sqrt())).abs() < 1e-4);
+
+        // a capsule lying over the top of the square
+        let capsule = Shape::capsule(Vec2::new(-1.0, -0.25), Vec2::new(2.0, -0.25), 0.5).unwrap();
+        let (normal, depth) = contact(&square, &capsule);
+        assert!(close(normal, -Vec2::Y));
+        assert!((depth - 0.25).abs() < 1e-4);
+        assert!(capsule.contains(Vec2::new(2.4, -0.25)));
+        assert!(!capsule.contains(Vec2::new(2.4, 0.25)));
+        assert_eq!(capsule.bounds(), (Vec2::new(-1.5, -0.75), Vec2::new(2.5, 0.25)));
+    }
+
+    #[test]
+    fn boxes_without_area_only_hold_their_own_points()
+    {
+        let point = Shape::aabb(Vec2::ZERO, Vec2::ZERO).unwrap();
+        assert!(point.contains(Vec2::ZERO));
+        assert!(!point.contains(Vec2::new(0.0, 7.0)));
+        assert!(point.contact(&Shape::aabb(Vec2::new(-1.0, 6.0), Vec2::new(1.0, 8.0)).unwrap()).is_none());
+
+        let flat = Shape::aabb(Vec2::ZERO, Vec2::X).unwrap();
+        assert!(flat.contains(Vec2::new(0.5, 0.0)));
+        assert!(!flat.contains(Vec2::new(5.0, 0.0)));
+        let turned = Shape::obb(Vec2::ZERO, Vec2::new(1.0, 0.0), std::f32::consts::FRAC_PI_2).unwrap();
+        assert!(turned.contact(&Shape::aabb(Vec2::new(-0.1, 0.4), Vec2::new(0.1, 0.6)).unwrap()).is_some());
+        assert!(!turned.contains(Vec2::new(0.0, 5.0)));
+
+        // a ray passing them by misses, and one through the flat box meets it where it crosses
+        let past = Ray::new(Vec2::new(-3.0, 2.0), Vec2::X, 10.0).unwrap();
+        assert!(point.raycast(&past).is_none());
+        assert!(flat.raycast(&past).is_none());
+        let down = Ray::new(Vec2::new(0.5, -3.0), Vec2::Y, 10.0).unwrap();
+        let (distance, normal) = flat.raycast(&down).unwrap();
+        assert!((distance - 3.0).abs() < 1e-4);
+        assert!(close(normal, -Vec2::Y));
+    }
+
+    #[test]
+    fn shaped_regions_answer_point_queries_by_their_shape()
+    {
+        let region = Region::<Collider>::shaped(Shape::circle(Vec2::ZERO, 1.0).unwrap());
+        assert_eq!((region.p0, region.p1), (Vec2::splat(-1.0), Vec2::splat(1.0)));
+        assert!(region.intersects(Vec2::new(0.5, 0.5)));
+        // inside the bounds, outside the circle
+        assert!(!region.intersects(Vec2::new(0.9, 0.9)));
+
+        let plain = Region::<Collider>::new(Vec2::splat(-1.0), Vec2::splat(1.0)).unwrap();
+        assert!(plain.intersects(Vec2::new(0.9, 0.9)));
+        assert_eq!(plain.shape(), Shape::aabb(Vec2::splat(-1.0), Vec2::splat(1.0)).unwrap());
+    }
+}
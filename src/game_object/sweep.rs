@@ -0,0 +1,167 @@
+use ggez::glam::{IVec2, Vec2};
+
+// local imports
+use super::{grid::{Chunk, ObjectType}, ColliderType, HasRegion};
+
+/// Where a point moving along a segment first touches something
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Hit
+{
+    /// How far along the segment, 0.0 at its start to 1.0 at its end
+    pub t: f32,
+    /// The world position of the impact
+    pub point: Vec2,
+}
+
+impl Hit
+{
+    fn along(from: Vec2, to: Vec2, t: f32) -> Self
+    {
+        Self { t, point: from.lerp(to, t) }
+    }
+}
+
+/// Sweep a point from `from` to `to` against the region of `target`.
+///
+/// Unlike checking where the point ends up, this can't miss a region thinner
+/// than the distance moved in a tick.
+pub fn sweep_region<T, P>(target: &P, from: Vec2, to: Vec2) -> Option<Hit>
+where
+    T: ColliderType,
+    P: HasRegion<T>,
+{
+    let region = target.region_get();
+    let min = target.position_get() + region.p0;
+    let max = target.position_get() + region.p1;
+    let d = to - from;
+
+    // slab test: intersect the parameter ranges over which each axis is inside the box
+    let mut t_enter: f32 = 0.0;
+    let mut t_exit: f32 = 1.0;
+    for (p, d, min, max) in [(from.x, d.x, min.x, max.x), (from.y, d.y, min.y, max.y)]
+    {
+        if d == 0.0
+        {
+            if p < min || p > max
+            {
+                return None;
+            }
+            continue;
+        }
+
+        let (t0, t1) = ((min - p) / d, (max - p) / d);
+        t_enter = t_enter.max(t0.min(t1));
+        t_exit = t_exit.min(t0.max(t1));
+        if t_enter > t_exit
+        {
+            return None;
+        }
+    }
+
+    Some(Hit::along(from, to, t_enter))
+}
+
+/// Sweep a point from `from` to `to` through the world cells, returning where it
+/// first enters a filled one. Starting inside a filled cell is a hit at the start.
+///
+/// Walks every cell the segment crosses, in order, so no cell can be skipped
+/// however far the point moves.
+pub fn sweep_cells(chunks: &[Chunk], from: Vec2, to: Vec2) -> Option<Hit>
+{
+    let filled = |cell: IVec2|
+        matches!(Chunk::object_at_world_cell(chunks, cell), Some(object) if matches!(object.foreground_object, ObjectType::Filled));
+
+    let d = to - from;
+    let mut cell = from.floor().as_ivec2();
+    let end = to.floor().as_ivec2();
+    let step = IVec2::new(d.x.signum() as i32, d.y.signum() as i32);
+
+    // the t at which the segment crosses the next cell boundary on each axis,
+    // and the t it takes to cross a whole cell
+    let boundary = |p: f32, d: f32| if d > 0.0 { (p.floor() + 1.0 - p) / d } else if d < 0.0 { (p - p.floor()) / -d } else { f32::INFINITY };
+    let mut t_max = Vec2::new(boundary(from.x, d.x), boundary(from.y, d.y));
+    let t_delta = Vec2::new(1.0 / d.x.abs(), 1.0 / d.y.abs());
+
+    let mut t = 0.0;
+    loop
+    {
+        if filled(cell)
+        {
+            return Some(Hit::along(from, to, t));
+        }
+
+        if cell == end
+        {
+            return None;
+        }
+
+        if t_max.x < t_max.y
+        {
+            t = t_max.x;
+            t_max.x += t_delta.x;
+            cell.x += step.x;
+        }
+        else
+        {
+            t = t_max.y;
+            t_max.y += t_delta.y;
+            cell.y += step.y;
+        }
+
+        if t > 1.0
+        {
+            return None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+    use crate::game_object::{collider_type::Collider, enemy::Enemy, HasPosition};
+
+    fn fill(chunks: &mut [Chunk], cell: (u8, u8))
+    {
+        chunks[0][cell.into()].foreground_object = ObjectType::Filled;
+    }
+
+    #[test]
+    fn sweep_finds_the_entry_point_of_a_cell()
+    {
+        let mut chunks = vec![Chunk::default()];
+        fill(&mut chunks, (5, 2));
+
+        let hit = sweep_cells(&chunks, Vec2::new(0.5, 2.5), Vec2::new(10.5, 2.5)).unwrap();
+        assert!((hit.t - 0.45).abs() < 1e-5);
+        assert!((hit.point - Vec2::new(5.0, 2.5)).length() < 1e-5);
+
+        // going the other way, it enters through the far side
+        let hit = sweep_cells(&chunks, Vec2::new(10.5, 2.5), Vec2::new(0.5, 2.5)).unwrap();
+        assert!((hit.point - Vec2::new(6.0, 2.5)).length() < 1e-5);
+    }
+
+    #[test]
+    fn sweep_misses_cells_off_the_segment()
+    {
+        let mut chunks = vec![Chunk::default()];
+        fill(&mut chunks, (5, 2));
+
+        assert!(sweep_cells(&chunks, Vec2::new(0.5, 3.5), Vec2::new(10.5, 3.5)).is_none());
+        assert!(sweep_cells(&chunks, Vec2::new(0.5, 2.5), Vec2::new(4.5, 2.5)).is_none());
+        // a diagonal passing just beside the cell
+        assert!(sweep_cells(&chunks, Vec2::new(4.2, 0.5), Vec2::new(4.9, 5.5)).is_none());
+    }
+
+    #[test]
+    fn sweep_hits_a_thin_region()
+    {
+        let target = HasRegion::<Collider>::region_set(Enemy::default().position_set((5.0, 0.0).into()), ((-0.05, -1.0), (0.05, 1.0)).into());
+
+        let hit = sweep_region::<Collider, _>(&target, Vec2::new(0.0, 0.0), Vec2::new(100.0, 0.0)).unwrap();
+        assert!((hit.point.x - 4.95).abs() < 1e-4);
+
+        assert!(sweep_region::<Collider, _>(&target, Vec2::new(0.0, 2.0), Vec2::new(100.0, 2.0)).is_none());
+        assert!(sweep_region::<Collider, _>(&target, Vec2::new(0.0, 0.0), Vec2::new(4.0, 0.0)).is_none());
+    }
+}
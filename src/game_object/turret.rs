@@ -0,0 +1,270 @@
+use ggez::glam::Vec2;
+use serde::{Deserialize, Serialize};
+
+// local imports
+use crate::{events::MissileFired, game_object::HasPosition, input::ActionCode, util::vec_extension::Flip};
+use super::{cannon::Cannon, enemy::Enemy, grid::{Chunk, ObjectType}, weapon::{Armory, WeaponDef}};
+
+/// How a turret picks which enemy to shoot at
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Targeting
+{
+    /// The closest enemy to the turret
+    #[default]
+    Nearest,
+    /// The enemy with the most health
+    Strongest,
+    /// The enemy closest to the player's cannon, which turrets defend
+    FirstInPath,
+}
+
+impl Targeting
+{
+    pub fn next(self) -> Self
+    {
+        match self
+        {
+            Targeting::Nearest => Targeting::Strongest,
+            Targeting::Strongest => Targeting::FirstInPath,
+            Targeting::FirstInPath => Targeting::Nearest,
+        }
+    }
+}
+
+/// A cannon placed on a grid cell that aims and fires on its own.
+///
+/// It turns with the same rotation physics as the player's cannon, but only
+/// considers enemies within `range` and within `fov_degrees` of the direction
+/// it was mounted facing.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Turret
+{
+    pub cannon: Cannon,
+    /// The direction the turret was placed facing, y-flipped like `Cannon::facing`
+    #[serde(with = "crate::util::vec_extension::_Vec2Ser")]
+    mount: Vec2,
+    /// Tiles
+    pub range: f32,
+    pub fov_degrees: f32,
+    pub targeting: Targeting,
+}
+
+impl Turret
+{
+    const DEFAULT_RANGE: f32 = 12.0;
+    const DEFAULT_FOV_DEGREES: f32 = 180.0;
+    /// Radians the barrel may be off its target and still fire
+    const AIM_TOLERANCE: f32 = 0.05;
+
+    /// A turret in the center of `cell`, mounted facing away from `defended`
+    pub fn new(cell: Vec2, defended: Vec2) -> Self
+    {
+        let position = cell.floor() + Vec2::splat(0.5);
+        let mount = (position - defended).flip_y().try_normalize().unwrap_or(Vec2::X);
+
+        let mut cannon = Cannon::new(mount, position);
+        cannon.armory = Armory::new(vec![WeaponDef::turret_default()]).expect("a turret has its weapon");
+
+        Self
+        {
+            cannon,
+            mount,
+            range: Turret::DEFAULT_RANGE,
+            fov_degrees: Turret::DEFAULT_FOV_DEGREES,
+            targeting: Targeting::default(),
+        }
+    }
+
+    /// Whether this turret sits on the same cell as world point `pos`
+    pub fn occupies(&self, pos: Vec2) -> bool
+    {
+        self.cannon.position.floor() == pos.floor()
+    }
+
+    /// Whether `enemy` is within range and the field of view
+    fn can_target(&self, enemy: &Enemy) -> bool
+    {
+        let to = enemy.position_get() - self.cannon.position;
+        to.length() <= self.range
+            && self.mount.angle_between(to.flip_y()).abs() <= self.fov_degrees.to_radians() / 2.0
+    }
+
+    /// The enemy this turret should aim at, by its targeting
+    fn select_target<'a>(&self, enemies: impl IntoIterator<Item = &'a Enemy>, defended: Vec2) -> Option<&'a Enemy>
+    {
+        let distance = |e: &Enemy, to: Vec2| e.position_get().distance(to);
+        let candidates = enemies.into_iter().filter(|e| self.can_target(e));
+
+        match self.targeting
+        {
+            Targeting::Nearest =>
+                candidates.min_by(|a, b| distance(a, self.cannon.position).total_cmp(&distance(b, self.cannon.position))),
+            Targeting::Strongest =>
+                candidates.max_by_key(|e| e.health()),
+            Targeting::FirstInPath =>
+                candidates.min_by(|a, b| distance(a, defended).total_cmp(&distance(b, defended))),
+        }
+    }
+}
+
+impl crate::FixedUpdate<Vec<Turret>> for crate::World
+{
+    fn fixed_update(&mut self, input: &crate::world::TickInput) -> ggez::GameResult
+    {
+        let defended = self.cannon.position;
+
+        // place or remove a turret, or change its targeting, at the clicked cell
+        if let Some(mouse) = input.mouse_world()
+        {
+            let existing = self.turrets.iter().position(|t| t.occupies(mouse));
+
+            if input.actions.just_pressed(ActionCode::PlaceTurret)
+            {
+                // a turret can't be built into a filled cell, nor onto the cannon
+                let blocked =
+                    mouse.floor() == defended.floor()
+                    || matches!(Chunk::object_at_world_cell(&self.chunks, mouse.floor().as_ivec2()), Some(object) if matches!(object.foreground_object, ObjectType::Filled));
+                match existing
+                {
+                    Some(i) => { self.turrets.remove(i); },
+                    None if !blocked => self.turrets.push(Turret::new(mouse, defended)),
+                    None => (),
+                }
+            }
+            else if let (true, Some(i)) = (input.actions.just_pressed(ActionCode::CycleTurretTargeting), existing)
+            {
+                self.turrets[i].targeting = self.turrets[i].targeting.next();
+            }
+        }
+
+        for turret in &mut self.turrets
+        {
+            let target = turret.select_target(self.enemies.by_id().map(|(_, e)| e), defended).map(|e| e.position_get());
+
+            let target_rot_vel = target.map_or(0.0, |target| turret.cannon.rot_vel_toward(target));
+            turret.cannon.turn(target_rot_vel);
+
+            let on_target = target.is_some_and(|target|
+                turret.cannon.facing.angle_between((target - turret.cannon.position).flip_y()).abs() < Turret::AIM_TOLERANCE);
+            for missile in turret.cannon.fire(on_target)
+            {
+                self.events.send(MissileFired { missile });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl crate::Draw<Vec<Turret>> for crate::MainState
+{
+    fn draw(&self, _context: &mut ggez::Context, canvas: &mut ggez::graphics::Canvas) -> ggez::GameResult
+    {
+        for turret in &self.world.turrets
+        {
+            self.draw_cannon(&turret.cannon, ggez::graphics::Color::CYAN, canvas);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+    use crate::{game_object::{collider_type::Collider, HasRegion}, input::action_state::ActionState, world::{TickInput, World}};
+
+    fn enemy_at(x: f32, y: f32, damage: i32) -> Enemy
+    {
+        let mut enemy = HasRegion::<Collider>::region_set(Enemy::default().position_set((x, y).into()), ((-0.75, -0.75), (0.75, 0.75)).into());
+        enemy.damage(damage);
+        enemy
+    }
+
+    fn tapped(action: ActionCode, mouse: Vec2) -> TickInput
+    {
+        let mut actions = ActionState::default();
+        actions.tap(&[action]);
+        actions.begin_tick();
+        TickInput::new(actions, Some(mouse))
+    }
+
+    #[test]
+    fn place_cycle_and_remove()
+    {
+        let mut world = World::new(0);
+        world.enemies.clear();
+
+        world.step(&tapped(ActionCode::PlaceTurret, Vec2::new(4.3, 2.8))).unwrap();
+        assert_eq!(world.turrets.len(), 1);
+        assert_eq!(world.turrets[0].cannon.position, Vec2::new(4.5, 2.5));
+
+        world.step(&tapped(ActionCode::CycleTurretTargeting, Vec2::new(4.9, 2.1))).unwrap();
+        assert_eq!(world.turrets[0].targeting, Targeting::Strongest);
+
+        world.step(&tapped(ActionCode::PlaceTurret, Vec2::new(4.5, 2.5))).unwrap();
+        assert!(world.turrets.is_empty());
+    }
+
+    #[test]
+    fn turrets_are_not_placed_on_cells_or_the_cannon()
+    {
+        let mut world = World::new(0);
+        world.enemies.clear();
+        Chunk::object_at_world_cell_mut(&mut world.chunks, (4, 2).into()).unwrap().foreground_object = ObjectType::Filled;
+
+        world.step(&tapped(ActionCode::PlaceTurret, Vec2::new(4.3, 2.8))).unwrap();
+        world.step(&tapped(ActionCode::PlaceTurret, world.cannon.position)).unwrap();
+        assert!(world.turrets.is_empty());
+    }
+
+    #[test]
+    fn targeting_picks_by_mode()
+    {
+        // mounted facing +x, away from a cannon to its left
+        let mut turret = Turret::new(Vec2::ZERO, Vec2::new(-10.0, 0.5));
+        let enemies = [enemy_at(3.0, 0.5, 50), enemy_at(6.0, 0.5, 0), enemy_at(4.0, -3.0, 20)];
+        let defended = Vec2::new(4.0, -10.0);
+
+        turret.targeting = Targeting::Nearest;
+        assert_eq!(turret.select_target(&enemies, defended).unwrap().position_get(), Vec2::new(3.0, 0.5));
+        turret.targeting = Targeting::Strongest;
+        assert_eq!(turret.select_target(&enemies, defended).unwrap().position_get(), Vec2::new(6.0, 0.5));
+        turret.targeting = Targeting::FirstInPath;
+        assert_eq!(turret.select_target(&enemies, defended).unwrap().position_get(), Vec2::new(4.0, -3.0));
+    }
+
+    #[test]
+    fn ignores_enemies_out_of_range_or_view()
+    {
+        let mut turret = Turret::new(Vec2::ZERO, Vec2::new(-10.0, 0.5));
+        turret.fov_degrees = 90.0;
+
+        // behind the mount, and too far ahead
+        let enemies = [enemy_at(-3.0, 0.5, 0), enemy_at(30.0, 0.5, 0)];
+        assert!(turret.select_target(&enemies, Vec2::ZERO).is_none());
+
+        // ahead but outside a 90 degree view
+        let enemies = [enemy_at(1.5, 5.0, 0)];
+        assert!(turret.select_target(&enemies, Vec2::ZERO).is_none());
+    }
+
+    #[test]
+    fn turns_toward_and_fires_at_target()
+    {
+        let mut world = World::new(0);
+        world.enemies = [enemy_at(6.5, -3.5, 0)].into_iter().collect();
+        world.turrets.push(Turret::new(Vec2::new(0.5, 0.5), Vec2::new(-10.0, 0.5)));
+
+        for _ in 0..120
+        {
+            world.step(&TickInput::default()).unwrap();
+        }
+
+        let turret = &world.turrets[0];
+        let to_target = (Vec2::new(6.5, -3.5) - turret.cannon.position).flip_y();
+        assert!(turret.cannon.facing.angle_between(to_target).abs() < Turret::AIM_TOLERANCE);
+        assert!(world.enemies.is_empty() || world.enemies[&0].health() < Enemy::MAX_HEALTH);
+    }
+}
@@ -0,0 +1,217 @@
+use std::collections::HashSet;
+
+use ggez::glam::{IVec2, Vec2};
+
+// local imports
+use crate::game_object::{grid::{Chunk, ObjectType}, HasPosition};
+
+/// The set of world cells visible from some origin, computed with
+/// recursive shadowcasting over the loaded chunks.
+///
+/// A cell whose foreground is `ObjectType::Filled` blocks sight but is
+/// itself visible (you can see the wall, not what is behind it).
+/// Cells not covered by any chunk are treated as opaque.
+#[derive(Debug, Clone, Default)]
+pub struct VisibleCells
+{
+    origin: IVec2,
+    radius: i32,
+    cells: HashSet<IVec2>,
+}
+
+impl VisibleCells
+{
+    /// How far, in cells, the cannon can see.
+    pub const VIEW_RADIUS: i32 = 24;
+
+    /// Transforms (xx, xy, yx, yy) mapping the first octant onto each of the eight.
+    const OCTANTS: [[i32; 4]; 8] = [
+        [ 1,  0,  0,  1],
+        [ 0,  1,  1,  0],
+        [ 0, -1,  1,  0],
+        [-1,  0,  0,  1],
+        [-1,  0,  0, -1],
+        [ 0, -1, -1,  0],
+        [ 0,  1, -1,  0],
+        [ 1,  0,  0, -1],
+    ];
+
+    /// Compute every cell visible from the world position `origin` within `radius` cells.
+    pub fn compute(chunks: &[Chunk], origin: Vec2, radius: i32) -> Self
+    {
+        let origin = origin.floor().as_ivec2();
+        let mut s = Self { origin, radius, cells: HashSet::new() };
+
+        s.cells.insert(origin);
+        for [xx, xy, yx, yy] in Self::OCTANTS
+        {
+            s.cast_light(chunks, 1, 1.0, 0.0, (xx, xy, yx, yy));
+        }
+
+        s
+    }
+
+    /// Scans one row at a time outward from the origin, narrowing the
+    /// (start, end) slope window whenever an opaque cell is met and recursing
+    /// for the still-visible portion beyond it.
+    fn cast_light(&mut self, chunks: &[Chunk], row: i32, mut start: f32, end: f32, mult: (i32, i32, i32, i32))
+    {
+        if start < end
+        {
+            return;
+        }
+
+        let (xx, xy, yx, yy) = mult;
+        let radius_sq = self.radius * self.radius;
+        let mut new_start = 0.0;
+
+        for j in row..=self.radius
+        {
+            let dy = -j;
+            let mut blocked = false;
+
+            for dx in -j..=0
+            {
+                let l_slope = (dx as f32 - 0.5) / (dy as f32 + 0.5);
+                let r_slope = (dx as f32 + 0.5) / (dy as f32 - 0.5);
+
+                if start < r_slope
+                {
+                    continue;
+                }
+                if end > l_slope
+                {
+                    break;
+                }
+
+                let cell = self.origin + IVec2::new(dx * xx + dy * xy, dx * yx + dy * yy);
+                if dx * dx + dy * dy <= radius_sq
+                {
+                    self.cells.insert(cell);
+                }
+
+                let opaque = Self::blocks_sight(chunks, cell);
+                if blocked
+                {
+                    if opaque
+                    {
+                        new_start = r_slope;
+                    }
+                    else
+                    {
+                        blocked = false;
+                        start = new_start;
+                    }
+                }
+                else if opaque && j < self.radius
+                {
+                    blocked = true;
+                    self.cast_light(chunks, j + 1, start, l_slope, mult);
+                    new_start = r_slope;
+                }
+            }
+
+            if blocked
+            {
+                break;
+            }
+        }
+    }
+
+    fn blocks_sight(chunks: &[Chunk], cell: IVec2) -> bool
+    {
+        match Chunk::object_at_world_cell(chunks, cell)
+        {
+            Some(object) => matches!(object.foreground_object, ObjectType::Filled),
+            None => true,
+        }
+    }
+
+    pub fn contains_cell(&self, cell: IVec2) -> bool
+    {
+        self.cells.contains(&cell)
+    }
+
+    /// Whether the cell containing the world position `pos` is visible.
+    pub fn is_visible(&self, pos: Vec2) -> bool
+    {
+        self.contains_cell(pos.floor().as_ivec2())
+    }
+}
+
+impl crate::FixedUpdate<VisibleCells> for crate::World
+{
+    fn fixed_update(&mut self, _input: &crate::world::TickInput) -> ggez::GameResult
+    {
+        self.visibility = VisibleCells::compute(&self.chunks, self.cannon.position_get(), VisibleCells::VIEW_RADIUS);
+
+        for enemy in self.enemies.values_mut()
+        {
+            enemy.spotted = self.visibility.is_visible(enemy.position_get());
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+
+    fn fill(chunks: &mut [Chunk], x: i32, y: i32)
+    {
+        let chunk = &mut chunks[0];
+        chunk[(x as u8, y as u8).into()].foreground_object = ObjectType::Filled;
+    }
+
+    #[test]
+    fn open_chunk_visible_within_radius()
+    {
+        let chunks = vec![Chunk::default()];
+        let vis = VisibleCells::compute(&chunks, (8.5, 8.5).into(), 4);
+
+        assert!(vis.contains_cell((8, 8).into()));
+        assert!(vis.contains_cell((12, 8).into()));
+        assert!(vis.contains_cell((8, 4).into()));
+        assert!(vis.contains_cell((10, 10).into()));
+        assert!(!vis.contains_cell((13, 8).into()));
+    }
+
+    #[test]
+    fn wall_is_visible_but_hides_cells_behind_it()
+    {
+        let mut chunks = vec![Chunk::default()];
+        fill(&mut chunks, 10, 8);
+
+        let vis = VisibleCells::compute(&chunks, (8.5, 8.5).into(), 8);
+
+        assert!(vis.contains_cell((10, 8).into()));
+        assert!(!vis.contains_cell((11, 8).into()));
+        assert!(!vis.contains_cell((14, 8).into()));
+        // around the side of the wall is still in view
+        assert!(vis.contains_cell((11, 6).into()));
+    }
+
+    #[test]
+    fn cells_outside_every_chunk_block_sight()
+    {
+        let chunks = vec![Chunk::default()];
+        let vis = VisibleCells::compute(&chunks, (1.5, 1.5).into(), 6);
+
+        assert!(vis.contains_cell((-1, 1).into()));
+        assert!(!vis.contains_cell((-2, 1).into()));
+    }
+
+    #[test]
+    fn sight_crosses_chunk_boundaries()
+    {
+        let right = Chunk { upper_left_position: [16.0, 0.0].into(), ..Default::default() };
+        let chunks = vec![Chunk::default(), right];
+
+        let vis = VisibleCells::compute(&chunks, (14.5, 4.5).into(), 6);
+
+        assert!(vis.contains_cell((19, 4).into()));
+        assert!(vis.is_visible((20.2, 4.9).into()));
+    }
+}
@@ -0,0 +1,465 @@
+use std::sync::LazyLock;
+
+use ggez::{GameError, GameResult};
+use serde::{Deserialize, Serialize};
+
+// local imports
+use super::missile::Behavior;
+
+/// The weapon definitions shipped with the game
+const DEFAULT_WEAPONS: &str = include_str!("../../resources/weapons.toml");
+
+/// How a weapon turns a pull of the trigger into shots
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum FirePattern
+{
+    /// One projectile straight ahead
+    Single,
+    /// `count` projectiles at once, fanned evenly across `arc_degrees`
+    Spread { count: u32, arc_degrees: f32 },
+    /// `shots` projectiles straight ahead, `interval_ticks` apart
+    Burst { shots: u32, interval_ticks: u32 },
+    /// A projectile straight ahead on every tick the trigger is held, cooldown or not
+    Beam,
+}
+
+/// What limits how long a weapon can keep firing
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum AmmoModel
+{
+    Unlimited,
+    /// `size` shots, then `reload_ticks` without firing to refill
+    Magazine { size: u32, reload_ticks: u32 },
+    /// Each shot adds `per_shot` heat. At 1.0 the weapon overheats,
+    /// and cannot fire again until it has cooled all the way down.
+    Heat { per_shot: f32, cooling_per_tick: f32 },
+}
+
+/// What each shot of a weapon fires
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProjectileDef
+{
+    /// Tiles per second
+    pub speed: f32,
+    #[serde(default)]
+    pub behaviors: Vec<Behavior>,
+}
+
+/// A kind of weapon, as defined in `resources/weapons.toml`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WeaponDef
+{
+    pub name: String,
+    /// Fixed ticks between trigger pulls
+    pub cooldown_ticks: u32,
+    pub pattern: FirePattern,
+    pub ammo: AmmoModel,
+    pub projectile: ProjectileDef,
+}
+
+#[derive(Debug, Deserialize)]
+struct WeaponsFile
+{
+    /// Name of the weapon turrets carry, the first one if not given
+    turret: Option<String>,
+    weapon: Vec<WeaponDef>,
+}
+
+impl WeaponsFile
+{
+    fn from_toml(s: &str) -> GameResult<Self>
+    {
+        let file: WeaponsFile =
+            toml::from_str(s)
+            .map_err(|e| GameError::ConfigError(format!("malformed weapon definitions: {e}")))?;
+
+        if file.weapon.is_empty()
+        {
+            return Err(GameError::ConfigError("no weapons defined".to_owned()));
+        }
+
+        Ok(file)
+    }
+}
+
+impl WeaponDef
+{
+    /// Parse a list of `[[weapon]]` definitions
+    pub fn list_from_toml(s: &str) -> GameResult<Vec<Self>>
+    {
+        Ok(WeaponsFile::from_toml(s)?.weapon)
+    }
+
+    /// Parse the weapon named as the `turret` weapon from a list of definitions
+    pub fn turret_from_toml(s: &str) -> GameResult<Self>
+    {
+        let file = WeaponsFile::from_toml(s)?;
+        match file.turret
+        {
+            Some(name) =>
+                file.weapon
+                .into_iter()
+                .find(|def| def.name == name)
+                .ok_or_else(|| GameError::ConfigError(format!("turret weapon {name} is not defined"))),
+            None => Ok(file.weapon.into_iter().next().expect("there is at least one weapon")),
+        }
+    }
+
+    /// The weapons shipped with the game
+    pub fn defaults() -> Vec<Self>
+    {
+        Self::list_from_toml(DEFAULT_WEAPONS).expect("the shipped weapon definitions are valid")
+    }
+
+    /// The weapon turrets are shipped with, parsed on first use
+    pub fn turret_default() -> Self
+    {
+        static TURRET: LazyLock<WeaponDef> =
+            LazyLock::new(|| WeaponDef::turret_from_toml(DEFAULT_WEAPONS).expect("the shipped weapon definitions are valid"));
+        TURRET.clone()
+    }
+
+    /// Angles, in radians from straight ahead, of the projectiles a single shot fires
+    fn shot_angles(&self) -> Vec<f32>
+    {
+        match self.pattern
+        {
+            FirePattern::Spread { count, arc_degrees } if count > 1 =>
+            {
+                let arc = arc_degrees.to_radians();
+                (0..count)
+                .map(|i| -arc / 2.0 + arc * i as f32 / (count - 1) as f32)
+                .collect()
+            },
+            FirePattern::Spread { count: 0, .. } => Vec::new(),
+            _ => vec![0.0],
+        }
+    }
+}
+
+/// A weapon's ammunition, heat and timers, counted in fixed ticks
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct WeaponState
+{
+    /// Ticks until the trigger may be pulled again
+    cooldown: u32,
+    /// Shots left in the magazine
+    rounds: u32,
+    /// Ticks left without firing before the magazine is refilled, while reloading
+    reload: u32,
+    heat: f32,
+    overheated: bool,
+    /// Shots of the current burst still to fire, and ticks until the next of them
+    burst_left: u32,
+    burst_timer: u32,
+}
+
+/// A weapon definition along with the state of the cannon's copy of it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Weapon
+{
+    pub def: WeaponDef,
+    state: WeaponState,
+}
+
+impl Weapon
+{
+    pub fn new(def: WeaponDef) -> Self
+    {
+        let rounds = match def.ammo { AmmoModel::Magazine { size, .. } => size, _ => 0 };
+        Self { def, state: WeaponState { rounds, ..Default::default() } }
+    }
+
+    /// Whether the ammo model allows a shot right now
+    fn has_ammo(&self) -> bool
+    {
+        match self.def.ammo
+        {
+            AmmoModel::Unlimited => true,
+            AmmoModel::Magazine { .. } => self.state.rounds > 0,
+            AmmoModel::Heat { .. } => !self.state.overheated,
+        }
+    }
+
+    fn use_ammo(&mut self)
+    {
+        let state = &mut self.state;
+        match self.def.ammo
+        {
+            AmmoModel::Unlimited => (),
+            AmmoModel::Magazine { reload_ticks, .. } =>
+            {
+                state.rounds = state.rounds.saturating_sub(1);
+                if state.rounds == 0
+                {
+                    state.reload = reload_ticks;
+                }
+            },
+            AmmoModel::Heat { per_shot, .. } =>
+            {
+                state.heat += per_shot;
+                if state.heat >= 1.0
+                {
+                    state.heat = 1.0;
+                    state.overheated = true;
+                }
+            },
+        }
+    }
+
+    /// Fire one shot if the ammo allows, returning the angles of its projectiles
+    fn shoot(&mut self) -> Vec<f32>
+    {
+        if !self.has_ammo()
+        {
+            return Vec::new();
+        }
+
+        self.use_ammo();
+        self.def.shot_angles()
+    }
+
+    /// Let go of any burst in progress, e.g. when switching away
+    pub fn cancel(&mut self)
+    {
+        self.state.burst_left = 0;
+    }
+
+    /// Advance one tick with the trigger held or not.
+    /// Returns the angles, in radians from straight ahead, of every projectile fired.
+    pub fn tick(&mut self, trigger: bool) -> Vec<f32>
+    {
+        self.reload();
+        let mut fired = Vec::new();
+
+        if self.state.burst_left > 0
+        {
+            if self.state.burst_timer == 0
+            {
+                fired = self.shoot();
+                self.state.burst_left -= 1;
+                self.state.burst_timer = match self.def.pattern { FirePattern::Burst { interval_ticks, .. } => interval_ticks, _ => 0 };
+            }
+        }
+        else if trigger && (self.state.cooldown == 0 || self.def.pattern == FirePattern::Beam) && self.has_ammo()
+        {
+            match self.def.pattern
+            {
+                FirePattern::Burst { shots, interval_ticks } =>
+                {
+                    fired = self.shoot();
+                    self.state.burst_left = shots.saturating_sub(1);
+                    self.state.burst_timer = interval_ticks;
+                },
+                _ => fired = self.shoot(),
+            }
+            self.state.cooldown = self.def.cooldown_ticks;
+        }
+
+        self.cool_down();
+        fired
+    }
+
+    /// Spend one tick of an empty magazine's reload, or refill it once `reload_ticks` are spent.
+    /// Done before firing, so the tick that empties it doesn't count.
+    fn reload(&mut self)
+    {
+        let state = &mut self.state;
+        if let AmmoModel::Magazine { size, .. } = self.def.ammo
+        {
+            if state.rounds == 0 && state.reload == 0
+            {
+                state.rounds = size;
+            }
+            else if state.rounds == 0
+            {
+                state.reload -= 1;
+            }
+        }
+    }
+
+    /// Run down timers and heat by one tick
+    fn cool_down(&mut self)
+    {
+        let state = &mut self.state;
+        state.cooldown = state.cooldown.saturating_sub(1);
+        state.burst_timer = state.burst_timer.saturating_sub(1);
+
+        match self.def.ammo
+        {
+            AmmoModel::Unlimited | AmmoModel::Magazine { .. } => (),
+            AmmoModel::Heat { cooling_per_tick, .. } =>
+            {
+                state.heat = (state.heat - cooling_per_tick).max(0.0);
+                if state.heat == 0.0
+                {
+                    state.overheated = false;
+                }
+            },
+        }
+    }
+
+    /// A short summary of the ammo or heat, for display
+    pub fn status(&self) -> String
+    {
+        match self.def.ammo
+        {
+            AmmoModel::Unlimited => "unlimited".to_owned(),
+            AmmoModel::Magazine { size, .. } if self.state.rounds == 0 => format!("reloading 0/{size}"),
+            AmmoModel::Magazine { size, .. } => format!("{}/{size}", self.state.rounds),
+            AmmoModel::Heat { .. } if self.state.overheated => "overheated".to_owned(),
+            AmmoModel::Heat { .. } => format!("heat {:.0}%", self.state.heat * 100.0),
+        }
+    }
+}
+
+/// The weapons a cannon carries, one of them selected
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Armory
+{
+    weapons: Vec<Weapon>,
+    selected: usize,
+}
+
+impl Default for Armory
+{
+    fn default() -> Self
+    {
+        Self::new(WeaponDef::defaults()).expect("there are shipped weapons")
+    }
+}
+
+impl Armory
+{
+    /// An armory of `defs`, with the first selected. There must be at least one.
+    pub fn new(defs: Vec<WeaponDef>) -> GameResult<Self>
+    {
+        if defs.is_empty()
+        {
+            return Err(GameError::ConfigError("an armory needs at least one weapon".to_owned()));
+        }
+        Ok(Self { weapons: defs.into_iter().map(Weapon::new).collect(), selected: 0 })
+    }
+
+    pub fn selected(&self) -> &Weapon
+    {
+        &self.weapons[self.selected]
+    }
+
+    /// Select the weapon `by` places on, wrapping around
+    pub fn cycle(&mut self, by: isize)
+    {
+        self.weapons[self.selected].cancel();
+        let len = self.weapons.len() as isize;
+        self.selected = (self.selected as isize + by).rem_euclid(len) as usize;
+    }
+
+    /// Advance every weapon one tick, with the trigger held or not on the selected one.
+    /// Returns the fired projectiles as angles from straight ahead, with their definitions.
+    pub fn tick(&mut self, trigger: bool) -> Vec<(f32, ProjectileDef)>
+    {
+        let mut fired = Vec::new();
+        for (i, weapon) in self.weapons.iter_mut().enumerate()
+        {
+            let angles = weapon.tick(trigger && i == self.selected);
+            fired.extend(angles.into_iter().map(|angle| (angle, weapon.def.projectile.clone())));
+        }
+        fired
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+
+    fn def(pattern: FirePattern, ammo: AmmoModel, cooldown_ticks: u32) -> WeaponDef
+    {
+        WeaponDef { name: "test".to_owned(), cooldown_ticks, pattern, ammo, projectile: ProjectileDef { speed: 1.0, behaviors: Vec::new() } }
+    }
+
+    /// Shots fired on each of `ticks` ticks with the trigger held
+    fn hold(weapon: &mut Weapon, ticks: usize) -> Vec<usize>
+    {
+        (0..ticks).map(|_| weapon.tick(true).len()).collect()
+    }
+
+    #[test]
+    fn shipped_weapons_parse()
+    {
+        let defs = WeaponDef::defaults();
+        assert!(defs.len() >= 4);
+        assert!(defs.contains(&WeaponDef::turret_default()));
+        assert!(WeaponDef::turret_from_toml("turret = \"Laser\"\n[[weapon]]\nname = \"x\"\ncooldown_ticks = 1\npattern = \"Single\"\nammo = \"Unlimited\"\nprojectile = { speed = 1.0 }").is_err());
+        assert!(WeaponDef::list_from_toml("").is_err());
+        assert!(WeaponDef::list_from_toml("[[weapon]]\nname = \"x\"").is_err());
+    }
+
+    #[test]
+    fn single_waits_for_cooldown()
+    {
+        let mut weapon = Weapon::new(def(FirePattern::Single, AmmoModel::Unlimited, 2));
+        assert_eq!(hold(&mut weapon, 5), vec![1, 0, 1, 0, 1]);
+    }
+
+    #[test]
+    fn spread_fans_across_its_arc()
+    {
+        let mut weapon = Weapon::new(def(FirePattern::Spread { count: 3, arc_degrees: 90.0 }, AmmoModel::Unlimited, 10));
+        let angles = weapon.tick(true);
+        assert_eq!(angles.len(), 3);
+        assert!((angles[0] + std::f32::consts::FRAC_PI_4).abs() < 1e-5);
+        assert!(angles[1].abs() < 1e-5);
+        assert!((angles[2] - std::f32::consts::FRAC_PI_4).abs() < 1e-5);
+    }
+
+    #[test]
+    fn burst_finishes_after_trigger_release()
+    {
+        let mut weapon = Weapon::new(def(FirePattern::Burst { shots: 3, interval_ticks: 2 }, AmmoModel::Unlimited, 10));
+        let fired: Vec<usize> = [true, false, false, false, false, false].into_iter().map(|t| weapon.tick(t).len()).collect();
+        assert_eq!(fired, vec![1, 0, 1, 0, 1, 0]);
+    }
+
+    #[test]
+    fn magazine_reloads_when_empty()
+    {
+        let mut weapon = Weapon::new(def(FirePattern::Single, AmmoModel::Magazine { size: 2, reload_ticks: 3 }, 0));
+        assert_eq!(hold(&mut weapon, 8), vec![1, 1, 0, 0, 0, 1, 1, 0]);
+
+        // without reload ticks, it refills right after the last shot
+        let mut weapon = Weapon::new(def(FirePattern::Single, AmmoModel::Magazine { size: 1, reload_ticks: 0 }, 0));
+        assert_eq!(hold(&mut weapon, 3), vec![1, 1, 1]);
+    }
+
+    #[test]
+    fn beam_overheats_until_cooled()
+    {
+        let mut weapon = Weapon::new(def(FirePattern::Beam, AmmoModel::Heat { per_shot: 0.5, cooling_per_tick: 0.25 }, 10));
+
+        // heat after each tick: 0.25, 0.5, then overheated at 1 and cooling to 0.75
+        assert_eq!(hold(&mut weapon, 3), vec![1, 1, 1]);
+        assert_eq!(weapon.status(), "overheated");
+        assert_eq!(hold(&mut weapon, 3), vec![0, 0, 0]);
+        assert_eq!(hold(&mut weapon, 1), vec![1]);
+    }
+
+    #[test]
+    fn only_the_selected_weapon_fires()
+    {
+        let mut armory = Armory::new(vec![
+            def(FirePattern::Single, AmmoModel::Unlimited, 0),
+            def(FirePattern::Spread { count: 4, arc_degrees: 30.0 }, AmmoModel::Unlimited, 0),
+        ]).unwrap();
+        assert_eq!(armory.tick(true).len(), 1);
+
+        armory.cycle(1);
+        assert_eq!(armory.tick(true).len(), 4);
+
+        armory.cycle(1);
+        armory.cycle(-3);
+        assert_eq!(armory.tick(true).len(), 4);
+
+        assert!(Armory::new(Vec::new()).is_err());
+    }
+}
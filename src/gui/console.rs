@@ -0,0 +1,53 @@
+use ggegui::egui;
+
+// local imports
+use crate::MainState;
+
+impl MainState
+{
+    /// The console's history, and a line to type commands into.
+    /// The line keeps the keyboard focus, so typing never reaches gameplay.
+    pub(super) fn console_window(&mut self, gui_context: &egui::Context, context: &ggez::Context)
+    {
+        let mut shown = self.gui_state.console_shown;
+        let mut submitted = None;
+
+        egui::Window::new("Console")
+        .open(&mut shown)
+        .default_width(480.0)
+        .show(gui_context,
+        |ui| {
+            egui::ScrollArea::vertical()
+            .max_height(240.0)
+            .stick_to_bottom(true)
+            .show(ui,
+            |ui| {
+                for line in self.console.history()
+                {
+                    ui.monospace(line);
+                }
+            });
+
+            // the key that toggles the console would otherwise be typed into it
+            self.gui_state.console_input.retain(|c| c != '`');
+
+            let line =
+                ui.add(
+                    egui::TextEdit::singleline(&mut self.gui_state.console_input)
+                    .hint_text("help")
+                    .desired_width(f32::INFINITY));
+            if line.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter))
+            {
+                submitted = Some(std::mem::take(&mut self.gui_state.console_input));
+            }
+            line.request_focus();
+        });
+
+        self.gui_state.console_shown = shown;
+
+        if let Some(line) = submitted
+        {
+            self.run_console(&line, context);
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use ggegui::egui;
+use ggez::glam::Vec2;
+
+// local imports
+use crate::{debug_draw::Category, game_object::grid::Chunk, DebugState, MainState};
+
+impl MainState
+{
+    /// Frame timing, what the world holds, where the camera and cursor are,
+    /// and the timing of every system, which can be switched off from here.
+    pub(super) fn debug_overlay(&mut self, gui_context: &egui::Context, context: &ggez::Context)
+    {
+        let mut shown = self.debug_state.overlay_shown;
+
+        let cursor = Vec2::from(context.mouse.position()) / 16.0 + self.world_pos;
+        let cell = cursor.floor().as_ivec2();
+        let under_cursor =
+            Chunk::object_at_world_cell(&self.world.chunks, cell)
+            .map_or("outside the chunks".to_owned(), |object| format!("{:?}", object.foreground_object));
+
+        egui::Window::new("Debug")
+        .open(&mut shown)
+        .anchor(egui::Align2::RIGHT_TOP, [-8.0, 8.0])
+        .resizable(false)
+        .show(gui_context,
+        |ui| {
+            ui.label(format!("{:.0} fps, {} fixed ticks last frame", context.time.fps(), self.debug_state.ticks_last_frame));
+            MainState::frame_time_graph(ui, &self.debug_state);
+
+            ui.separator();
+            ui.label(format!(
+                "{} missiles, {} enemies, {} turrets, {} chunks",
+                self.world.missiles.len(), self.world.enemies.len(), self.world.turrets.len(), self.world.chunks.len()));
+            ui.label(format!("tick {}", self.world.tick));
+            ui.label(format!("camera ({:.2}, {:.2})", self.world_pos.x, self.world_pos.y));
+            ui.label(format!("cursor cell ({}, {}): {under_cursor}", cell.x, cell.y));
+
+            ui.separator();
+            ui.horizontal(|ui|
+            {
+                ui.label("draw");
+                for category in Category::ALL
+                {
+                    ui.checkbox(self.debug_state.debug_shown.get_mut(category), category.name());
+                }
+            });
+
+            ui.separator();
+            egui::CollapsingHeader::new("systems")
+            .default_open(true)
+            .show(ui,
+            |ui| {
+                egui::Grid::new("systems_grid")
+                .striped(true)
+                .show(ui,
+                |ui| {
+                    // a replay steps with the fixed systems it was recorded with
+                    let locked = self.replay.locks_world();
+                    let fixed = self.world.systems.systems_mut().map(|system| (system, !locked));
+                    for (system, switchable) in fixed.chain(self.systems.systems_mut().map(|system| (system, true)))
+                    {
+                        let mut enabled = system.is_enabled();
+                        if ui.add_enabled(switchable, egui::Checkbox::new(&mut enabled, system.name())).changed()
+                        {
+                            system.set_enabled(enabled);
+                        }
+                        ui.label(format!("{:?}", system.stage()));
+                        ui.label(format!("{:.3} ms (avg {:.3} ms)",
+                            system.last_run().as_secs_f64() * 1000.0,
+                            system.average_run().as_secs_f64() * 1000.0));
+                        ui.end_row();
+                    }
+                });
+            });
+        });
+
+        self.debug_state.overlay_shown = shown;
+    }
+
+    /// A line of recent frame times, with a line across at the fixed tick's budget
+    fn frame_time_graph(ui: &mut egui::Ui, debug_state: &DebugState)
+    {
+        let budget = MainState::FIXED_PHYSICS_TIMESTEP * 1000.0;
+        // always show twice the budget, so a steady frame rate sits mid-graph
+        let max = debug_state.frame_times.iter().copied().fold(budget * 2.0, f32::max);
+
+        let (rect, _) = ui.allocate_exact_size(egui::vec2(240.0, 48.0), egui::Sense::hover());
+        let painter = ui.painter_at(rect);
+        let y = |ms: f32| rect.bottom() - rect.height() * ms / max;
+
+        painter.rect_filled(rect, 0.0, egui::Color32::from_black_alpha(96));
+        painter.hline(rect.x_range(), y(budget), egui::Stroke::new(1.0, egui::Color32::DARK_GREEN));
+
+        let step = rect.width() / DebugState::FRAME_HISTORY as f32;
+        let points =
+            debug_state.frame_times
+            .iter()
+            .enumerate()
+            .map(|(i, &ms)| egui::pos2(rect.left() + i as f32 * step, y(ms)))
+            .collect();
+        painter.add(egui::Shape::line(points, egui::Stroke::new(1.0, egui::Color32::YELLOW)));
+
+        let last = debug_state.frame_times.back().copied().unwrap_or_default();
+        ui.label(format!("frame {last:.2} ms, worst shown {max:.2} ms"));
+    }
+}
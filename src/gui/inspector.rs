@@ -0,0 +1,160 @@
+use ggegui::egui;
+use ggez::glam::Vec2;
+
+// local imports
+use crate::{game_object::{enemy::Behavior, ColliderType, Region}, replay::ReplayMode, MainState};
+
+/// Something whose fields can be shown and edited live in the inspector.
+/// Implemented beside each selectable type, like `Draw`. A field is only
+/// written when its widget reports a change.
+pub(crate) trait Inspect
+{
+    fn inspect(&mut self, ui: &mut egui::Ui);
+}
+
+/// A labelled pair of drag values editing `value`, returning whether it changed
+pub(crate) fn edit_vec2(ui: &mut egui::Ui, label: &str, value: &mut Vec2) -> bool
+{
+    let mut edited = *value;
+    let changed =
+        ui.horizontal(
+        |ui| {
+            ui.label(label);
+            let x = ui.add(egui::DragValue::new(&mut edited.x).speed(0.05).prefix("x "));
+            let y = ui.add(egui::DragValue::new(&mut edited.y).speed(0.05).prefix("y "));
+            x.changed() || y.changed()
+        })
+        .inner;
+
+    if changed
+    {
+        *value = edited;
+    }
+    changed
+}
+
+/// Edit both corners of `region`, keeping the second below and right of the first.
+/// A shaped region is only shown, since its box is fit to the shape.
+pub(crate) fn edit_region<T: ColliderType>(ui: &mut egui::Ui, label: &str, region: &mut Region<T>)
+{
+    if region.is_shaped()
+    {
+        ui.label(format!("{label}: {:?}", region.shape()));
+        return;
+    }
+
+    ui.label(label);
+    ui.indent(label,
+    |ui| {
+        let from = edit_vec2(ui, "from", &mut region.p0);
+        let to = edit_vec2(ui, "to", &mut region.p1);
+        if from || to
+        {
+            region.p1 = region.p1.max(region.p0);
+        }
+    });
+}
+
+impl MainState
+{
+    /// Commands for the selected group, and the fields of the selected
+    /// entity when there is just one, until everything is deselected or gone
+    pub(super) fn inspector_window(&mut self, gui_context: &egui::Context)
+    {
+        self.selection.retain_existing(&self.world, &self.enemy_walls);
+        let mut shown = !self.selection.is_empty();
+        let locked = self.replay.locks_world();
+
+        // a replay's world is inspected through a copy, which whatever is shown can't change
+        let mut copy;
+        let world =
+            if locked
+            {
+                copy = self.world.clone();
+                &mut copy
+            }
+            else { &mut self.world };
+
+        egui::Window::new("Inspector")
+        .open(&mut shown)
+        .show(gui_context,
+        |ui| {
+            if locked
+            {
+                ui.label(format!("read only: {}", ReplayMode::WORLD_LOCKED));
+            }
+            // a disabled ui still shows the values, but can't change them
+            ui.set_enabled(!locked);
+
+            if let [entity] = *self.selection.entities()
+            {
+                ui.heading(format!("{entity:?}"));
+                if let Some(inspected) = entity.inspectable(world, &mut self.enemy_walls)
+                {
+                    inspected.inspect(ui);
+                }
+            }
+            else
+            {
+                ui.heading(format!("{} selected", self.selection.entities().len()));
+            }
+
+            ui.separator();
+            if self.selection.enemy_count() > 0
+            {
+                ui.horizontal(
+                |ui| {
+                    ui.label("enemies");
+                    if ui.button("hold").clicked()
+                    {
+                        self.selection.assign(Behavior::Hold, world);
+                    }
+                    if ui.button("charge").clicked()
+                    {
+                        self.selection.assign(Behavior::Charge, world);
+                    }
+                });
+            }
+            if ui.button("delete").clicked()
+            {
+                self.selection.delete(world, &mut self.enemy_walls);
+            }
+        });
+
+        if !shown
+        {
+            self.selection.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+    use crate::game_object::{enemy::Enemy, enemy_wall::EnemyWall, missile::Missile, HasPosition};
+
+    /// Show `inspected` in the inspector for a few frames, without touching it
+    fn show(inspected: &mut dyn Inspect)
+    {
+        let context = egui::Context::default();
+        for _ in 0..3
+        {
+            let _ = context.run(egui::RawInput::default(), |context| { egui::CentralPanel::default().show(context, |ui| inspected.inspect(ui)); });
+        }
+    }
+
+    #[test]
+    fn showing_changes_nothing()
+    {
+        let mut wall = EnemyWall::default().position(Vec2::new(3.3, 1.7)).size(Vec2::new(4.0, 1.0)).rotation(0.407);
+        let mut enemy = Enemy::default().position_set(Vec2::new(12.1, 5.3));
+        let mut missile = Missile::new(Vec2::new(0.3, 0.7), Vec2::from_angle(0.37) * 9.0);
+        let before = format!("{wall:?} {enemy:?} {missile:?}");
+
+        show(&mut wall);
+        show(&mut enemy);
+        show(&mut missile);
+        assert_eq!(format!("{wall:?} {enemy:?} {missile:?}"), before);
+    }
+}
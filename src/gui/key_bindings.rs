@@ -0,0 +1,93 @@
+use ggegui::egui;
+
+// local imports
+use crate::{input::{layers::LayerId, ActionCode, ComboToAction}, MainState};
+
+impl MainState
+{
+    /// Lists every action with its bindings, and lets the user rebind
+    /// an action by pressing the new key or mouse button.
+    pub(super) fn key_bindings_window(&mut self, gui_context: &egui::Context, context: &ggez::Context)
+    {
+        let mut shown = self.gui_state.key_bindings_shown;
+        let mut save = false;
+        let mut reset = false;
+
+        egui::Window::new("Key Bindings")
+        .open(&mut shown)
+        .show(gui_context,
+        |ui| {
+            egui::Grid::new("key_bindings_grid")
+            .striped(true)
+            .show(ui,
+            |ui| {
+                for action in ActionCode::ALL
+                {
+                    ui.label(format!("{action:?}"));
+
+                    let bound = 
+                        if self.key_input_state.rebinding() == Some(action)
+                        {
+                            "press a key... (Esc cancels)".to_owned()
+                        }
+                        else
+                        {
+                            let key_combos = self.key_input_state.key_combos();
+                            let combos = key_combos.combos_for(action).into_iter().map(|kc| kc.to_string());
+                            let chords = key_combos.chords_for(action).into_iter().map(|chord| format!("[{chord}]"));
+
+                            // bindings of other layers can't be rebound here, but are listed
+                            let layered =
+                                self.key_input_state.layers()
+                                .iter()
+                                .filter(|layer| layer.id != LayerId::Gameplay)
+                                .flat_map(|layer| layer.key_combos.combos_for(action).into_iter().map(move |kc| format!("{kc} ({:?})", layer.id)));
+
+                            combos.chain(chords).chain(layered).collect::<Vec<_>>().join(", ")
+                        };
+                    ui.label(bound);
+
+                    if ui.button("Rebind").clicked()
+                    {
+                        self.key_input_state.begin_rebind(action);
+                    }
+                    ui.end_row();
+                }
+            });
+
+            let mut dead_zone = self.key_input_state.key_combos().dead_zone();
+            if ui.add(egui::Slider::new(&mut dead_zone, 0.0..=0.95).text("Gamepad dead zone")).changed()
+            {
+                self.key_input_state.set_dead_zone(dead_zone);
+            }
+
+            for (kc, actions) in self.key_input_state.key_combos().conflicts()
+            {
+                ui.colored_label(egui::Color32::RED, format!("{kc} is bound to multiple actions: {actions:?}"));
+            }
+
+            ui.horizontal(
+            |ui| {
+                save = ui.button("Save").clicked();
+                reset = ui.button("Reset to defaults").clicked();
+            });
+        });
+
+        self.gui_state.key_bindings_shown = shown;
+
+        if reset
+        {
+            self.key_input_state.set_key_combos(ComboToAction::default());
+        }
+
+        if save
+        {
+            let path = ComboToAction::config_path(context);
+            match self.key_input_state.key_combos().save(&path)
+            {
+                Ok(()) => println!("Saved key bindings to {}", path.display()),
+                Err(e) => println!("Could not save key bindings to {}: {e}", path.display()),
+            }
+        }
+    }
+}
@@ -1,28 +1,40 @@
+mod console;
+mod debug_overlay;
+pub mod inspector;
+mod key_bindings;
+
 use serde::{Deserialize, Serialize};
 
 use super::MainState;
 
-use crate::game_object::Update;
+use crate::{game_object::Update, input::{layers::{Capture, InputLayer, LayerId}, ComboToAction}, replay::ReplayMode};
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct GUIState
 {
     pub shown: bool,
+    pub key_bindings_shown: bool,
+    pub console_shown: bool,
+    /// The console line being typed
+    #[serde(skip)]
+    pub console_input: String,
 }
 
 impl Update<ggegui::Gui> for MainState
 {
     fn update(&mut self, context: &mut ggez::Context) -> ggez::GameResult 
     {
-        if !self.gui_state.shown
-        {
-            return Ok(())
-        }
-
+        // every window must be built within this one gui frame,
+        // and the frame must run even when all windows are closed so they stop being drawn
         let gui_context = self.gui.ctx();
 
+        // replay controls act on the whole MainState, so are applied once the window is built
+        let mut record = None;
+        let mut play = false;
+
         use ggegui::egui;
         egui::Window::new("Dragging Window")
+        .open(&mut self.gui_state.shown)
         .show(&gui_context, 
         |ui| {
             ui.label("label");
@@ -32,7 +44,107 @@ impl Update<ggegui::Gui> for MainState
             }
 
             ui.add_enabled(false, egui::Button::new("test"));
+
+            ui.separator();
+            let weapon = self.world.cannon.armory.selected();
+            ui.label(format!("weapon: {} ({})", weapon.def.name, weapon.status()));
+
+            ui.separator();
+            ui.label("replay");
+            ui.horizontal(
+            |ui| {
+                match self.replay
+                {
+                    ReplayMode::Live =>
+                    {
+                        if ui.button("Record").clicked()
+                        {
+                            record = Some(true);
+                        }
+                        if ui.button("Play last").clicked()
+                        {
+                            play = true;
+                        }
+                    },
+                    ReplayMode::Recording(ref recording) =>
+                    {
+                        ui.label(format!("recording, {} ticks", recording.len()));
+                        if ui.button("Stop").clicked()
+                        {
+                            record = Some(false);
+                        }
+                    },
+                    ReplayMode::Playing(_) => { ui.label("playing back"); },
+                }
+            });
+
+            ui.separator();
+            ui.label("post-processing");
+            for pass in self.post_process.passes_mut()
+            {
+                let mut enabled = pass.is_enabled();
+                if ui.checkbox(&mut enabled, pass.name().to_owned()).changed()
+                {
+                    pass.set_enabled(enabled);
+                }
+            }
         });
+
+        match record
+        {
+            Some(true) => self.start_recording(),
+            Some(false) => self.stop_recording(context),
+            None => (),
+        }
+        if play
+        {
+            self.play_last_replay(context);
+        }
+
+        if self.gui_state.key_bindings_shown
+        {
+            self.key_bindings_window(&gui_context, context);
+        }
+
+        if self.debug_state.overlay_shown
+        {
+            self.debug_overlay(&gui_context, context);
+        }
+
+        if !self.selection.is_empty()
+        {
+            self.inspector_window(&gui_context);
+        }
+
+        if self.gui_state.console_shown
+        {
+            self.console_window(&gui_context, context);
+        }
+
+        // the console's layer follows it however it was opened or closed
+        let layers = self.key_input_state.layers_mut();
+        if self.gui_state.console_shown != layers.contains(LayerId::Console)
+        {
+            if self.gui_state.console_shown
+            {
+                layers.push(InputLayer::new(LayerId::Console, ComboToAction::console()));
+            }
+            else
+            {
+                layers.pop(LayerId::Console);
+            }
+        }
+
+        // while egui is using the pointer or keyboard, nothing beneath the gui sees them
+        self.key_input_state.layers_mut().set_capture(
+            LayerId::Gui, 
+            Capture
+            {
+                pointer: gui_context.wants_pointer_input() || gui_context.is_pointer_over_area(),
+                keyboard: gui_context.wants_keyboard_input(),
+                gamepad: false,
+            });
+
         self.gui.update(context);
 
         // egui::SidePanel::new(egui::panel::Side::Left, "left_panel")
@@ -0,0 +1,306 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+// local imports
+use super::{ActionCode, Button};
+
+/// Per-action pressed / held / released state, as seen by fixed updates.
+///
+/// Input events arrive once per frame, but a frame may run zero or several
+/// fixed ticks. Edges (presses and releases) are latched as they arrive and
+/// only handed out by `begin_tick`, so every edge is seen by exactly one tick:
+/// a press and release that both land between two ticks still show up as
+/// `just_pressed` and `just_released` on the next one.
+///
+/// Only this tick's view is serialized, which is all a replay needs.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ActionState
+{
+    /// The actions each currently held button is driving
+    #[serde(skip)]
+    down: HashMap<Button, Vec<ActionCode>>,
+    /// How far each held analog button is pushed, 0.0 to 1.0.
+    /// Digital buttons are absent, and count as fully pushed.
+    #[serde(skip)]
+    strength: HashMap<Button, f32>,
+
+    // latched since the last tick
+    #[serde(skip)]
+    pending_pressed:    HashSet<ActionCode>,
+    #[serde(skip)]
+    pending_released:   HashSet<ActionCode>,
+
+    // this tick's view
+    just_pressed:       HashSet<ActionCode>,
+    just_released:      HashSet<ActionCode>,
+    held_ticks:         HashMap<ActionCode, u32>,
+    /// Values of held actions that are not fully pushed
+    values:             HashMap<ActionCode, f32>,
+}
+
+impl ActionState
+{
+    fn is_down(&self, action: ActionCode) -> bool
+    {
+        self.down.values().any(|actions| actions.contains(&action))
+    }
+
+    /// `button` went down and now drives `actions`.
+    /// Repeated presses of an already held button are ignored.
+    pub(super) fn press(&mut self, button: Button, actions: &[ActionCode])
+    {
+        if self.down.contains_key(&button)
+        {
+            return;
+        }
+
+        for &ac in actions
+        {
+            if !self.is_down(ac)
+            {
+                self.pending_pressed.insert(ac);
+            }
+        }
+
+        self.down.insert(button, actions.to_vec());
+    }
+
+    /// Set how far held analog `button` is pushed
+    pub(super) fn set_strength(&mut self, button: Button, strength: f32)
+    {
+        if self.down.contains_key(&button)
+        {
+            self.strength.insert(button, strength.clamp(0.0, 1.0));
+        }
+    }
+
+    /// `button` went up; any action no longer driven by another button is released.
+    pub(super) fn release(&mut self, button: Button)
+    {
+        self.strength.remove(&button);
+        let Some(actions) = self.down.remove(&button) else { return };
+
+        for ac in actions
+        {
+            if !self.is_down(ac)
+            {
+                self.pending_released.insert(ac);
+            }
+        }
+    }
+
+    /// Trigger `actions` for a single tick, as if pressed and released at once.
+    pub(crate) fn tap(&mut self, actions: &[ActionCode])
+    {
+        for &ac in actions
+        {
+            self.pending_pressed.insert(ac);
+            self.pending_released.insert(ac);
+        }
+    }
+
+    /// Release everything, e.g. when the window loses focus.
+    pub(super) fn release_all(&mut self)
+    {
+        let buttons: Vec<Button> = self.down.keys().copied().collect();
+        buttons.into_iter().for_each(|b| self.release(b));
+    }
+
+    /// Advance to the next fixed tick, handing out the edges latched since the last one.
+    pub(crate) fn begin_tick(&mut self)
+    {
+        self.just_pressed = std::mem::take(&mut self.pending_pressed);
+        self.just_released = std::mem::take(&mut self.pending_released);
+
+        let mut held_ticks = HashMap::new();
+        for ac in ActionCode::ALL
+        {
+            if self.is_down(ac) || self.just_pressed.contains(&ac)
+            {
+                let ticks = if self.just_pressed.contains(&ac) { 0 } else { self.held_ticks.get(&ac).copied().unwrap_or(0) };
+                held_ticks.insert(ac, ticks + 1);
+            }
+        }
+        self.held_ticks = held_ticks;
+
+        self.values =
+            self.held_ticks
+            .keys()
+            .map(|&ac| (ac, self.live_value(ac)))
+            // a tap was released before the tick, but counts as fully pushed
+            .filter(|&(_, value)| value > 0.0 && value < 1.0)
+            .collect();
+    }
+
+    /// The strongest analog button driving `action` right now, or 1.0 for a digital one.
+    /// 0.0 if no button is driving it.
+    fn live_value(&self, action: ActionCode) -> f32
+    {
+        self.down
+        .iter()
+        .filter(|(_, actions)| actions.contains(&action))
+        .map(|(button, _)| self.strength.get(button).copied().unwrap_or(1.0))
+        .fold(0.0, f32::max)
+    }
+
+    /// Went down since the previous tick
+    pub fn just_pressed(&self, action: ActionCode) -> bool
+    {
+        self.just_pressed.contains(&action)
+    }
+
+    /// Went up since the previous tick
+    pub fn just_released(&self, action: ActionCode) -> bool
+    {
+        self.just_released.contains(&action)
+    }
+
+    /// Down during this tick. A tap shorter than a tick still counts as held for one tick.
+    pub fn is_held(&self, action: ActionCode) -> bool
+    {
+        self.held_ticks.contains_key(&action)
+    }
+
+    /// How far the action is pushed, 0.0 to 1.0: the strongest analog button
+    /// driving it, or 1.0 if it is held by a digital one.
+    pub fn value(&self, action: ActionCode) -> f32
+    {
+        match self.values.get(&action)
+        {
+            Some(&value) => value,
+            None if self.is_held(action) => 1.0,
+            None => 0.0,
+        }
+    }
+
+    /// How many ticks, including this one, the action has been held. 0 if not held.
+    pub fn held_ticks(&self, action: ActionCode) -> u32
+    {
+        self.held_ticks.get(&action).copied().unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+    use super::super::AxisDirection;
+    use ggez::input::{gamepad::gilrs::Axis, keyboard::KeyCode};
+
+    const W: Button = Button::Keyboard(KeyCode::W);
+    const UP: Button = Button::Keyboard(KeyCode::Up);
+
+    #[test]
+    fn press_hold_release()
+    {
+        let mut state = ActionState::default();
+        state.press(W, &[ActionCode::CameraUp]);
+
+        state.begin_tick();
+        assert!(state.just_pressed(ActionCode::CameraUp));
+        assert!(state.is_held(ActionCode::CameraUp));
+        assert_eq!(state.held_ticks(ActionCode::CameraUp), 1);
+
+        state.begin_tick();
+        state.begin_tick();
+        assert!(!state.just_pressed(ActionCode::CameraUp));
+        assert_eq!(state.held_ticks(ActionCode::CameraUp), 3);
+
+        state.release(W);
+        state.begin_tick();
+        assert!(state.just_released(ActionCode::CameraUp));
+        assert!(!state.is_held(ActionCode::CameraUp));
+
+        state.begin_tick();
+        assert!(!state.just_released(ActionCode::CameraUp));
+    }
+
+    #[test]
+    fn edges_survive_frames_without_ticks()
+    {
+        let mut state = ActionState::default();
+
+        // a frame with no fixed updates: press and release arrive before any tick
+        state.press(W, &[ActionCode::CameraUp]);
+        state.release(W);
+
+        state.begin_tick();
+        assert!(state.just_pressed(ActionCode::CameraUp));
+        assert!(state.just_released(ActionCode::CameraUp));
+        assert_eq!(state.held_ticks(ActionCode::CameraUp), 1);
+
+        // the second tick of the same frame sees nothing
+        state.begin_tick();
+        assert!(!state.just_pressed(ActionCode::CameraUp));
+        assert!(!state.just_released(ActionCode::CameraUp));
+        assert!(!state.is_held(ActionCode::CameraUp));
+    }
+
+    #[test]
+    fn repeated_key_down_does_not_repress()
+    {
+        let mut state = ActionState::default();
+        state.press(W, &[ActionCode::CameraUp]);
+        state.begin_tick();
+
+        state.press(W, &[ActionCode::CameraUp]);
+        state.begin_tick();
+
+        assert!(!state.just_pressed(ActionCode::CameraUp));
+        assert_eq!(state.held_ticks(ActionCode::CameraUp), 2);
+    }
+
+    #[test]
+    fn action_held_by_two_buttons_releases_with_the_last()
+    {
+        let mut state = ActionState::default();
+        state.press(W, &[ActionCode::CameraUp]);
+        state.press(UP, &[ActionCode::CameraUp]);
+        state.begin_tick();
+
+        state.release(W);
+        state.begin_tick();
+        assert!(state.is_held(ActionCode::CameraUp));
+        assert!(!state.just_released(ActionCode::CameraUp));
+
+        state.release(UP);
+        state.begin_tick();
+        assert!(state.just_released(ActionCode::CameraUp));
+    }
+
+    #[test]
+    fn value_is_strongest_button()
+    {
+        let stick = Button::GamepadAxis(Axis::LeftStickX, AxisDirection::Negative);
+
+        let mut state = ActionState::default();
+        state.press(stick, &[ActionCode::TurnLeft]);
+        state.set_strength(stick, 0.25);
+        state.begin_tick();
+        assert_eq!(state.value(ActionCode::TurnLeft), 0.25);
+
+        state.press(W, &[ActionCode::TurnLeft]);
+        state.begin_tick();
+        assert_eq!(state.value(ActionCode::TurnLeft), 1.0);
+
+        state.release(W);
+        state.release(stick);
+        state.begin_tick();
+        assert_eq!(state.value(ActionCode::TurnLeft), 0.0);
+    }
+
+    #[test]
+    fn tap_lasts_one_tick()
+    {
+        let mut state = ActionState::default();
+        state.tap(&[ActionCode::FlipDebugHitboxes]);
+
+        state.begin_tick();
+        assert!(state.just_pressed(ActionCode::FlipDebugHitboxes));
+        assert!(state.is_held(ActionCode::FlipDebugHitboxes));
+
+        state.begin_tick();
+        assert!(!state.is_held(ActionCode::FlipDebugHitboxes));
+    }
+}
@@ -0,0 +1,444 @@
+use std::{collections::HashMap, path::{Path, PathBuf}};
+
+use ggez::{input::{gamepad::gilrs, keyboard::{KeyCode, KeyMods}, mouse::MouseButton}, GameError, GameResult};
+use serde::{Deserialize, Serialize};
+
+// local imports
+use super::{chord::Chord, ActionCode, AxisDirection, Button, ComboToAction, KeyCombo};
+
+/// File name of the key bindings, inside the user config directory
+pub const KEY_BINDINGS_FILE: &str = "key_bindings.toml";
+
+/// On-disk form of `ComboToAction`: a flat list of bindings,
+/// since toml tables can only be keyed by strings.
+/// Each binding, and each step of a sequence, has exactly one of `key`, `mouse`,
+/// `gamepad` or `axis`. `hold_ticks` makes a binding a hold chord; `then` makes it a sequence.
+///
+/// ```toml
+/// dead_zone = 0.2
+///
+/// [[binding]]
+/// action = "Shoot"
+/// mods = ["ALT"]
+/// key = "D"
+///
+/// [[binding]]
+/// action = "Click"
+/// mouse = "Left"
+///
+/// [[binding]]
+/// action = "CenterCamera"
+/// key = "C"
+/// then = [{ key = "C" }]
+///
+/// [[binding]]
+/// action = "TurnLeft"
+/// axis = ["LeftStickX", "Negative"]
+/// ```
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct BindingsFile
+{
+    #[serde(default = "default_dead_zone")]
+    dead_zone: f32,
+    #[serde(rename = "binding", default)]
+    bindings: Vec<Binding>,
+}
+
+fn default_dead_zone() -> f32
+{
+    ComboToAction::DEFAULT_DEAD_ZONE
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Binding
+{
+    action: ActionCode,
+    #[serde(flatten)]
+    press: Press,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    hold_ticks: Option<u32>,
+    /// Further steps of a sequence. Kept last, since toml writes tables after plain values.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    then: Vec<Press>,
+}
+
+/// A single `KeyCombo` as written in the file
+#[derive(Debug, Serialize, Deserialize)]
+struct Press
+{
+    #[serde(with = "key_mods_ser", default)]
+    mods: KeyMods,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    key: Option<KeyCode>,
+    #[serde(with = "mouse_ser", default, skip_serializing_if = "Option::is_none")]
+    mouse: Option<MouseButton>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    gamepad: Option<gilrs::Button>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    axis: Option<(gilrs::Axis, AxisDirection)>,
+}
+
+impl Press
+{
+    fn new(KeyCombo(mods, button): KeyCombo) -> Self
+    {
+        let mut press = Self { mods, key: None, mouse: None, gamepad: None, axis: None };
+        match button
+        {
+            Button::Keyboard(key) => press.key = Some(key),
+            Button::Mouse(mouse) => press.mouse = Some(mouse),
+            Button::Gamepad(button) => press.gamepad = Some(button),
+            Button::GamepadAxis(axis, direction) => press.axis = Some((axis, direction)),
+        };
+        press
+    }
+
+    fn combo(&self, action: ActionCode) -> GameResult<KeyCombo>
+    {
+        match (self.key, self.mouse, self.gamepad, self.axis)
+        {
+            (Some(key), None, None, None) => Ok((self.mods, key).into()),
+            (None, Some(mouse), None, None) => Ok((self.mods, mouse).into()),
+            (None, None, Some(button), None) => Ok((self.mods, button).into()),
+            (None, None, None, Some((axis, direction))) => Ok((self.mods, Button::GamepadAxis(axis, direction)).into()),
+            _ => Err(GameError::ConfigError(format!("malformed key bindings: binding for {action:?} needs exactly one of `key`, `mouse`, `gamepad` or `axis`"))),
+        }
+    }
+}
+
+impl Binding
+{
+    fn new(action: ActionCode, combo: KeyCombo) -> Self
+    {
+        Self { action, press: Press::new(combo), hold_ticks: None, then: Vec::new() }
+    }
+
+    /// `None` for an empty sequence, which cannot be written
+    fn from_chord(action: ActionCode, chord: &Chord) -> Option<Self>
+    {
+        match chord
+        {
+            Chord::Sequence(steps) =>
+            {
+                let (first, rest) = steps.split_first()?;
+                let then = rest.iter().map(|&kc| Press::new(kc)).collect();
+
+                Some(Self { then, ..Self::new(action, *first) })
+            },
+            &Chord::Hold(kc, ticks) => Some(Self { hold_ticks: Some(ticks), ..Self::new(action, kc) }),
+        }
+    }
+
+    fn combo(&self) -> GameResult<KeyCombo>
+    {
+        self.press.combo(self.action)
+    }
+
+    /// The chord this binding describes, if it is more than a plain combo
+    fn chord(&self) -> GameResult<Option<Chord>>
+    {
+        let first = self.combo()?;
+
+        match (self.hold_ticks, self.then.is_empty())
+        {
+            (None, true) => Ok(None),
+            (Some(0), _) => Err(GameError::ConfigError(format!("malformed key bindings: binding for {:?} has `hold_ticks` of 0", self.action))),
+            (Some(ticks), true) => Ok(Some(Chord::Hold(first, ticks))),
+            (None, false) =>
+            {
+                let mut steps = vec![first];
+                for press in &self.then
+                {
+                    steps.push(press.combo(self.action)?);
+                }
+                Ok(Some(Chord::Sequence(steps)))
+            },
+            (Some(_), false) => Err(GameError::ConfigError(format!("malformed key bindings: binding for {:?} cannot have both `hold_ticks` and `then`", self.action))),
+        }
+    }
+}
+
+/// (De)serializes `KeyMods` as a list of flag names such as `["CTRL", "SHIFT"]`,
+/// since ggez does not implement Serialize for it
+mod key_mods_ser
+{
+    use ggez::input::keyboard::KeyMods;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(mods: &KeyMods, serializer: S) -> Result<S::Ok, S::Error>
+    {
+        serializer.collect_seq(mods.iter_names().map(|(name, _)| name))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<KeyMods, D::Error>
+    {
+        let names = Vec::<String>::deserialize(deserializer)?;
+
+        names
+        .iter()
+        .try_fold(KeyMods::NONE, |mods, name|
+        {
+            KeyMods::from_name(&name.to_uppercase())
+            .map(|m| mods | m)
+            .ok_or_else(|| D::Error::custom(format!("unknown modifier `{name}`, expected one of SHIFT, CTRL, ALT, LOGO")))
+        })
+    }
+}
+
+/// (De)serializes a mouse button as its name, or its number for `Other` buttons.
+/// toml cannot represent winit's own `Other(u16)` form.
+mod mouse_ser
+{
+    use ggez::input::mouse::MouseButton;
+    use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(untagged)]
+    enum Repr
+    {
+        Named(String),
+        Other(u16),
+    }
+
+    pub fn serialize<S: Serializer>(button: &Option<MouseButton>, serializer: S) -> Result<S::Ok, S::Error>
+    {
+        let repr = match button
+        {
+            Some(MouseButton::Other(n)) => Repr::Other(*n),
+            Some(named) => Repr::Named(format!("{named:?}")),
+            None => return serializer.serialize_none(),
+        };
+        repr.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<MouseButton>, D::Error>
+    {
+        match Repr::deserialize(deserializer)?
+        {
+            Repr::Other(n) => Ok(Some(MouseButton::Other(n))),
+            Repr::Named(name) => match name.as_str()
+            {
+                "Left" => Ok(Some(MouseButton::Left)),
+                "Right" => Ok(Some(MouseButton::Right)),
+                "Middle" => Ok(Some(MouseButton::Middle)),
+                _ => Err(D::Error::custom(format!("unknown mouse button `{name}`, expected Left, Right, Middle or a button number"))),
+            },
+        }
+    }
+}
+
+impl ComboToAction
+{
+    /// Where the key bindings are stored for this user
+    pub fn config_path(context: &ggez::Context) -> PathBuf
+    {
+        context.fs.user_config_dir().join(KEY_BINDINGS_FILE)
+    }
+
+    pub fn to_toml(&self) -> GameResult<String>
+    {
+        let bindings =
+            ActionCode::ALL
+            .into_iter()
+            .flat_map(|action|
+            {
+                let combos = 
+                    self.combos_for(action)
+                    .into_iter()
+                    .map(move |kc| Binding::new(action, kc));
+                let chords =
+                    self.chords_for(action)
+                    .into_iter()
+                    .filter_map(move |chord| Binding::from_chord(action, &chord));
+
+                combos.chain(chords)
+            })
+            .collect();
+
+        toml::to_string(&BindingsFile { dead_zone: self.dead_zone, bindings })
+        .map_err(|e| GameError::ConfigError(format!("could not serialize key bindings: {e}")))
+    }
+
+    pub fn from_toml(s: &str) -> GameResult<Self>
+    {
+        let file: BindingsFile =
+            toml::from_str(s)
+            .map_err(|e| GameError::ConfigError(format!("malformed key bindings: {e}")))?;
+
+        let mut combos = Self { combos: HashMap::new(), chords: HashMap::new(), dead_zone: Self::DEFAULT_DEAD_ZONE };
+        combos.set_dead_zone(file.dead_zone);
+        for binding in file.bindings
+        {
+            match binding.chord()?
+            {
+                Some(chord) => combos.bind_chord(chord, binding.action),
+                None => combos.bind(binding.combo()?, binding.action),
+            }
+        }
+
+        Ok(combos)
+    }
+
+    pub fn load(path: &Path) -> GameResult<Self>
+    {
+        let s =
+            std::fs::read_to_string(path)
+            .map_err(|e| GameError::ConfigError(format!("could not read key bindings from {}: {e}", path.display())))?;
+
+        Self::from_toml(&s)
+    }
+
+    /// Load the bindings at `path`, falling back to the defaults
+    /// (and saying why) if the file is missing or malformed.
+    pub fn load_or_default(path: &Path) -> Self
+    {
+        if !path.exists()
+        {
+            println!("No key bindings at {}, using defaults", path.display());
+            return Self::default();
+        }
+
+        match Self::load(path)
+        {
+            Ok(combos) =>
+            {
+                combos.warn_conflicts();
+                combos
+            },
+            Err(e) =>
+            {
+                println!("{e}; using default key bindings");
+                Self::default()
+            },
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> GameResult
+    {
+        if let Some(dir) = path.parent()
+        {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        std::fs::write(path, self.to_toml()?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+
+    #[test]
+    fn default_bindings_round_trip()
+    {
+        let combos = ComboToAction::default();
+        let s = combos.to_toml().unwrap();
+
+        assert_eq!(ComboToAction::from_toml(&s).unwrap(), combos);
+    }
+
+    #[test]
+    fn reads_hand_written_bindings()
+    {
+        let s = r#"
+            [[binding]]
+            action = "Shoot"
+            mods = ["ctrl", "SHIFT"]
+            key = "F"
+
+            [[binding]]
+            action = "Click"
+            mouse = "Right"
+
+            [[binding]]
+            action = "TurnLeft"
+            mouse = 4
+
+            [[binding]]
+            action = "TurnRight"
+            axis = ["LeftStickX", "Positive"]
+
+            [[binding]]
+            action = "Shoot"
+            gamepad = "South"
+        "#;
+
+        let combos = ComboToAction::from_toml(s).unwrap();
+
+        assert_eq!(combos.combos_for(ActionCode::Shoot), vec![(KeyMods::CTRL | KeyMods::SHIFT, KeyCode::F).into(), (KeyMods::NONE, gilrs::Button::South).into()]);
+        assert_eq!(combos.combos_for(ActionCode::Click), vec![(KeyMods::NONE, MouseButton::Right).into()]);
+        assert_eq!(combos.combos_for(ActionCode::TurnLeft), vec![(KeyMods::NONE, MouseButton::Other(4)).into()]);
+        assert_eq!(combos.combos_for(ActionCode::TurnRight), vec![(KeyMods::NONE, Button::GamepadAxis(gilrs::Axis::LeftStickX, AxisDirection::Positive)).into()]);
+        assert_eq!(combos.dead_zone(), ComboToAction::DEFAULT_DEAD_ZONE);
+    }
+
+    #[test]
+    fn malformed_bindings_are_errors()
+    {
+        assert!(ComboToAction::from_toml("[[binding]]\naction = \"Fly\"\nkey = \"F\"").is_err());
+        assert!(ComboToAction::from_toml("[[binding]]\naction = \"Shoot\"\nmods = [\"HYPER\"]\nkey = \"F\"").is_err());
+        assert!(ComboToAction::from_toml("[[binding]]\naction = \"Shoot\"\nkey = \"F\"\nmouse = \"Left\"").is_err());
+        assert!(ComboToAction::from_toml("[[binding]]\naction = \"Shoot\"").is_err());
+        assert!(ComboToAction::from_toml("not toml at all [").is_err());
+    }
+
+    #[test]
+    fn reads_chords()
+    {
+        let s = r#"
+            [[binding]]
+            action = "Shoot"
+            key = "Space"
+            hold_ticks = 30
+
+            [[binding]]
+            action = "CenterCamera"
+            key = "G"
+            then = [{ key = "G" }, { mods = ["SHIFT"], mouse = "Left" }]
+        "#;
+
+        let combos = ComboToAction::from_toml(s).unwrap();
+        let g: KeyCombo = (KeyMods::NONE, KeyCode::G).into();
+
+        assert_eq!(combos.chords_for(ActionCode::Shoot), vec![Chord::Hold((KeyMods::NONE, KeyCode::Space).into(), 30)]);
+        assert_eq!(combos.chords_for(ActionCode::CenterCamera), vec![Chord::Sequence(vec![g, g, (KeyMods::SHIFT, MouseButton::Left).into()])]);
+        assert!(combos.combos_for(ActionCode::Shoot).is_empty());
+
+        assert_eq!(ComboToAction::from_toml(&combos.to_toml().unwrap()).unwrap(), combos);
+    }
+
+    #[test]
+    fn malformed_chords_are_errors()
+    {
+        assert!(ComboToAction::from_toml("[[binding]]\naction = \"Shoot\"\nkey = \"F\"\nhold_ticks = 0").is_err());
+        assert!(ComboToAction::from_toml("[[binding]]\naction = \"Shoot\"\nkey = \"F\"\nhold_ticks = 5\nthen = [{ key = \"F\" }]").is_err());
+        assert!(ComboToAction::from_toml("[[binding]]\naction = \"Shoot\"\nkey = \"F\"\nthen = [{ mods = [\"ALT\"] }]").is_err());
+    }
+
+    #[test]
+    fn missing_file_falls_back_to_defaults()
+    {
+        let combos = ComboToAction::load_or_default(Path::new("/nonexistent/key_bindings.toml"));
+        assert_eq!(combos, ComboToAction::default());
+    }
+
+    #[test]
+    fn defaults_have_no_conflicts()
+    {
+        assert!(ComboToAction::default().conflicts().is_empty());
+    }
+
+    #[test]
+    fn conflicting_combo_is_reported()
+    {
+        let mut combos = ComboToAction::default();
+        let d: KeyCombo = (KeyMods::NONE, KeyCode::D).into();
+        combos.rebind(ActionCode::Shoot, d);
+
+        assert_eq!(combos.conflicts(), vec![(d, vec![ActionCode::CameraRight, ActionCode::Shoot])]);
+        // rebinding removes the action's previous combos
+        assert_eq!(combos.combos_for(ActionCode::Shoot), vec![d]);
+    }
+}
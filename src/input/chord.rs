@@ -0,0 +1,231 @@
+use std::collections::HashMap;
+
+// local imports
+use super::{ActionCode, Button, KeyCombo};
+
+/// Ticks allowed between two steps of a sequence before the partially
+/// entered sequence is abandoned
+pub const SEQUENCE_TIMEOUT_TICKS: u64 = 20;
+
+/// A binding made of more than a single press of a `KeyCombo`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Chord
+{
+    /// Each combo pressed in turn, each within `SEQUENCE_TIMEOUT_TICKS` of the previous.
+    /// A double-tap is the same combo twice.
+    Sequence(Vec<KeyCombo>),
+    /// The combo held down for this many ticks
+    Hold(KeyCombo, u32),
+}
+
+impl std::fmt::Display for Chord
+{
+    /// Formats like `G, G` or `Hold Space (30 ticks)`
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        match self
+        {
+            Chord::Sequence(steps) =>
+            {
+                let steps: Vec<String> = steps.iter().map(ToString::to_string).collect();
+                write!(f, "{}", steps.join(", "))
+            },
+            Chord::Hold(kc, ticks) => write!(f, "Hold {kc} ({ticks} ticks)"),
+        }
+    }
+}
+
+/// Recognizes `Chord`s in the stream of presses and releases.
+///
+/// Completed chords trigger their actions as taps, lasting a single tick.
+/// The presses making up a chord still trigger any actions bound to them
+/// on their own, so binding `G` and `G, G` fires the first on both presses.
+#[derive(Debug, Clone, Default)]
+pub(super) struct ChordState
+{
+    tick: u64,
+
+    /// Steps of the sequence entered so far
+    sequence: Vec<KeyCombo>,
+    /// Tick of the latest step in `sequence`
+    last_step_tick: u64,
+
+    /// Every held button, with the combo it was pressed as and how many ticks it has been held
+    holds: HashMap<Button, (KeyCombo, u32)>,
+}
+
+impl ChordState
+{
+    /// `combo` was pressed. Returns the actions of any sequence this completes.
+    pub(super) fn press(&mut self, combo: KeyCombo, chords: &HashMap<Chord, Vec<ActionCode>>) -> Vec<ActionCode>
+    {
+        self.holds.insert(combo.1, (combo, 0));
+
+        self.sequence.push(combo);
+        self.last_step_tick = self.tick;
+
+        // only keep as many steps as the longest sequence could use
+        let longest =
+            chords
+            .keys()
+            .filter_map(|chord| match chord { Chord::Sequence(steps) => Some(steps.len()), _ => None })
+            .max()
+            .unwrap_or(0);
+        let excess = self.sequence.len().saturating_sub(longest);
+        self.sequence.drain(..excess);
+
+        let mut triggered = Vec::new();
+        for (chord, actions) in chords
+        {
+            if let Chord::Sequence(steps) = chord
+            {
+                if !steps.is_empty() && self.sequence.ends_with(steps)
+                {
+                    triggered.extend_from_slice(actions);
+                }
+            }
+        }
+
+        // a completed sequence does not count towards the next one
+        if !triggered.is_empty()
+        {
+            self.sequence.clear();
+        }
+
+        triggered
+    }
+
+    pub(super) fn release(&mut self, button: Button)
+    {
+        self.holds.remove(&button);
+    }
+
+    pub(super) fn release_all(&mut self)
+    {
+        self.holds.clear();
+        self.sequence.clear();
+    }
+
+    /// Advance one tick, timing out a stale sequence.
+    /// Returns the actions of any hold that completes on this tick.
+    pub(super) fn tick(&mut self, chords: &HashMap<Chord, Vec<ActionCode>>) -> Vec<ActionCode>
+    {
+        self.tick += 1;
+
+        if self.tick - self.last_step_tick > SEQUENCE_TIMEOUT_TICKS
+        {
+            self.sequence.clear();
+        }
+
+        let mut triggered = Vec::new();
+        for (combo, ticks) in self.holds.values_mut()
+        {
+            *ticks += 1;
+
+            for (chord, actions) in chords
+            {
+                if let Chord::Hold(kc, n) = chord
+                {
+                    if kc == combo && n == ticks
+                    {
+                        triggered.extend_from_slice(actions);
+                    }
+                }
+            }
+        }
+
+        triggered
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+    use ggez::input::keyboard::{KeyCode, KeyMods};
+
+    fn combo(key: KeyCode) -> KeyCombo
+    {
+        (KeyMods::NONE, key).into()
+    }
+
+    fn chords(chord: Chord) -> HashMap<Chord, Vec<ActionCode>>
+    {
+        HashMap::from([(chord, vec![ActionCode::Shoot])])
+    }
+
+    #[test]
+    fn double_tap_fires_once()
+    {
+        let chords = chords(Chord::Sequence(vec![combo(KeyCode::G), combo(KeyCode::G)]));
+        let mut state = ChordState::default();
+
+        assert!(state.press(combo(KeyCode::G), &chords).is_empty());
+        state.release(combo(KeyCode::G).1);
+        state.tick(&chords);
+        assert_eq!(state.press(combo(KeyCode::G), &chords), vec![ActionCode::Shoot]);
+
+        // a third tap starts over rather than completing another double-tap
+        state.tick(&chords);
+        assert!(state.press(combo(KeyCode::G), &chords).is_empty());
+    }
+
+    #[test]
+    fn sequence_times_out()
+    {
+        let chords = chords(Chord::Sequence(vec![combo(KeyCode::G), combo(KeyCode::H)]));
+        let mut state = ChordState::default();
+
+        state.press(combo(KeyCode::G), &chords);
+        for _ in 0..=SEQUENCE_TIMEOUT_TICKS
+        {
+            state.tick(&chords);
+        }
+        assert!(state.press(combo(KeyCode::H), &chords).is_empty());
+
+        // within the timeout it completes
+        state.press(combo(KeyCode::G), &chords);
+        for _ in 0..SEQUENCE_TIMEOUT_TICKS
+        {
+            state.tick(&chords);
+        }
+        assert_eq!(state.press(combo(KeyCode::H), &chords), vec![ActionCode::Shoot]);
+    }
+
+    #[test]
+    fn wrong_step_breaks_sequence()
+    {
+        let chords = chords(Chord::Sequence(vec![combo(KeyCode::G), combo(KeyCode::H)]));
+        let mut state = ChordState::default();
+
+        state.press(combo(KeyCode::G), &chords);
+        state.press(combo(KeyCode::J), &chords);
+        assert!(state.press(combo(KeyCode::H), &chords).is_empty());
+    }
+
+    #[test]
+    fn hold_fires_once_after_n_ticks()
+    {
+        let chords = chords(Chord::Hold(combo(KeyCode::Space), 3));
+        let mut state = ChordState::default();
+
+        state.press(combo(KeyCode::Space), &chords);
+        assert!(state.tick(&chords).is_empty());
+        assert!(state.tick(&chords).is_empty());
+        assert_eq!(state.tick(&chords), vec![ActionCode::Shoot]);
+        assert!(state.tick(&chords).is_empty());
+    }
+
+    #[test]
+    fn hold_released_early_does_not_fire()
+    {
+        let chords = chords(Chord::Hold(combo(KeyCode::Space), 3));
+        let mut state = ChordState::default();
+
+        state.press(combo(KeyCode::Space), &chords);
+        state.tick(&chords);
+        state.tick(&chords);
+        state.release(combo(KeyCode::Space).1);
+        assert!(state.tick(&chords).is_empty());
+    }
+}
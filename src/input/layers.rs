@@ -0,0 +1,274 @@
+use std::collections::HashMap;
+
+// local imports
+use super::{chord::Chord, ActionCode, Button, ComboToAction, KeyCombo};
+
+/// Identifies an input layer, and fixes its priority among the others.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LayerId
+{
+    /// The developer console, above the gui so its key still closes it while typing
+    Console,
+    /// egui windows, capturing the pointer or keyboard while egui wants them
+    Gui,
+    /// World editing tools
+    Editor,
+    /// Playing the game; always present
+    Gameplay,
+}
+
+impl LayerId
+{
+    /// Higher priority layers see input first
+    pub fn priority(self) -> u8
+    {
+        match self
+        {
+            LayerId::Console => 3,
+            LayerId::Gui => 2,
+            LayerId::Editor => 1,
+            LayerId::Gameplay => 0,
+        }
+    }
+}
+
+/// Which input devices a layer consumes entirely, bound or not,
+/// so nothing below it sees them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Capture
+{
+    pub pointer: bool,
+    pub keyboard: bool,
+    pub gamepad: bool,
+}
+
+impl Capture
+{
+    fn captures(&self, button: Button) -> bool
+    {
+        match button
+        {
+            Button::Mouse(_) => self.pointer,
+            Button::Keyboard(_) => self.keyboard,
+            Button::Gamepad(_) | Button::GamepadAxis(..) => self.gamepad,
+        }
+    }
+}
+
+/// A set of bindings active in some mode of play.
+#[derive(Debug, Clone)]
+pub struct InputLayer
+{
+    pub id: LayerId,
+    pub key_combos: ComboToAction,
+    pub capture: Capture,
+}
+
+impl InputLayer
+{
+    pub fn new(id: LayerId, key_combos: ComboToAction) -> Self
+    {
+        Self { id, key_combos, capture: Capture::default() }
+    }
+}
+
+/// The active input layers, highest priority first.
+///
+/// A press is offered to each layer in turn: the first layer binding its combo
+/// takes it, and a layer capturing its device consumes it whether bound or not.
+/// Releases are never routed, so a button held when a layer is pushed over it
+/// still lets go of its action.
+#[derive(Debug, Clone)]
+pub struct LayerStack
+{
+    layers: Vec<InputLayer>,
+}
+
+impl LayerStack
+{
+    /// The gui layer over a gameplay layer with `key_combos`
+    pub fn new(key_combos: ComboToAction) -> Self
+    {
+        let mut stack = Self { layers: vec![InputLayer::new(LayerId::Gameplay, key_combos)] };
+        stack.push(InputLayer::new(LayerId::Gui, ComboToAction::empty()));
+        stack
+    }
+
+    /// Add `layer` by its priority, replacing any layer with the same id
+    pub fn push(&mut self, layer: InputLayer)
+    {
+        self.pop(layer.id);
+
+        let at =
+            self.layers
+            .iter()
+            .position(|l| l.id.priority() < layer.id.priority())
+            .unwrap_or(self.layers.len());
+        self.layers.insert(at, layer);
+    }
+
+    /// Remove the layer `id`. The gameplay layer cannot be removed.
+    pub fn pop(&mut self, id: LayerId) -> Option<InputLayer>
+    {
+        if id == LayerId::Gameplay
+        {
+            return None;
+        }
+
+        let at = self.layers.iter().position(|l| l.id == id)?;
+        Some(self.layers.remove(at))
+    }
+
+    pub fn contains(&self, id: LayerId) -> bool
+    {
+        self.layers.iter().any(|l| l.id == id)
+    }
+
+    /// Set what the layer `id` captures, if it is in the stack
+    pub fn set_capture(&mut self, id: LayerId, capture: Capture)
+    {
+        if let Some(layer) = self.layers.iter_mut().find(|l| l.id == id)
+        {
+            layer.capture = capture;
+        }
+    }
+
+    /// Every layer, highest priority first
+    pub fn iter(&self) -> impl Iterator<Item = &InputLayer>
+    {
+        self.layers.iter()
+    }
+
+    pub fn gameplay(&self) -> &ComboToAction
+    {
+        &self.layers.last().expect("the gameplay layer is never removed").key_combos
+    }
+
+    pub fn gameplay_mut(&mut self) -> &mut ComboToAction
+    {
+        &mut self.layers.last_mut().expect("the gameplay layer is never removed").key_combos
+    }
+
+    /// The layers a press of `button` reaches, down to the first that captures it
+    fn reachable(&self, button: Button) -> impl Iterator<Item = &InputLayer> + Clone
+    {
+        let captured_at =
+            self.layers
+            .iter()
+            .position(|l| l.capture.captures(button))
+            .unwrap_or(self.layers.len());
+
+        self.layers[..captured_at].iter()
+    }
+
+    /// The actions a press of `combo` triggers, from the first layer it reaches that binds it
+    pub fn resolve(&self, combo: &KeyCombo) -> Option<&[ActionCode]>
+    {
+        self.reachable(combo.1)
+        .find_map(|l| l.key_combos.get(combo))
+        .map(Vec::as_slice)
+    }
+
+    /// Whether a layer captures `button` before any layer could see it
+    pub(super) fn is_captured(&self, button: Button) -> bool
+    {
+        self.reachable(button).next().is_none()
+    }
+
+    /// The chords of every layer a press of `button` reaches
+    pub(super) fn reachable_chords(&self, button: Button) -> HashMap<Chord, Vec<ActionCode>>
+    {
+        Self::merge_chords(self.reachable(button))
+    }
+
+    /// The chords of every layer
+    pub fn all_chords(&self) -> HashMap<Chord, Vec<ActionCode>>
+    {
+        Self::merge_chords(self.layers.iter())
+    }
+
+    fn merge_chords<'a>(layers: impl Iterator<Item = &'a InputLayer>) -> HashMap<Chord, Vec<ActionCode>>
+    {
+        let mut chords: HashMap<Chord, Vec<ActionCode>> = HashMap::new();
+        for layer in layers
+        {
+            for (chord, actions) in layer.key_combos.chords()
+            {
+                chords.entry(chord.clone()).or_default().extend_from_slice(actions);
+            }
+        }
+        chords
+    }
+}
+
+impl Default for LayerStack
+{
+    fn default() -> Self
+    {
+        Self::new(ComboToAction::default())
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+    use ggez::input::{keyboard::{KeyCode, KeyMods}, mouse::MouseButton};
+
+    fn left_click() -> KeyCombo
+    {
+        (KeyMods::NONE, MouseButton::Left).into()
+    }
+
+    #[test]
+    fn layers_are_ordered_by_priority()
+    {
+        let mut stack = LayerStack::default();
+        stack.push(InputLayer::new(LayerId::Editor, ComboToAction::editor()));
+
+        let ids: Vec<LayerId> = stack.iter().map(|l| l.id).collect();
+        assert_eq!(ids, vec![LayerId::Gui, LayerId::Editor, LayerId::Gameplay]);
+    }
+
+    #[test]
+    fn higher_layer_binding_wins()
+    {
+        let space = (KeyMods::NONE, KeyCode::Space).into();
+        let mut gameplay = ComboToAction::empty();
+        gameplay.bind(left_click(), ActionCode::Shoot);
+        gameplay.bind(space, ActionCode::Shoot);
+        let mut stack = LayerStack::new(gameplay);
+        assert_eq!(stack.resolve(&left_click()), Some(&[ActionCode::Shoot][..]));
+
+        stack.push(InputLayer::new(LayerId::Editor, ComboToAction::editor()));
+        assert_eq!(stack.resolve(&left_click()), Some(&[ActionCode::Select][..]));
+
+        // unbound input falls through the editor
+        assert_eq!(stack.resolve(&space), Some(&[ActionCode::Shoot][..]));
+
+        stack.pop(LayerId::Editor);
+        assert_eq!(stack.resolve(&left_click()), Some(&[ActionCode::Shoot][..]));
+    }
+
+    #[test]
+    fn capture_consumes_only_its_device()
+    {
+        let mut stack = LayerStack::new(ComboToAction::editor());
+        stack.set_capture(LayerId::Gui, Capture { pointer: true, ..Default::default() });
+
+        assert!(stack.is_captured(left_click().1));
+        assert_eq!(stack.resolve(&left_click()), None);
+        assert!(!stack.is_captured(Button::Keyboard(KeyCode::Space)));
+    }
+
+    #[test]
+    fn gameplay_layer_cannot_be_popped()
+    {
+        let mut stack = LayerStack::default();
+        assert!(stack.pop(LayerId::Gameplay).is_none());
+        assert!(stack.contains(LayerId::Gameplay));
+
+        assert!(stack.pop(LayerId::Gui).is_some());
+        assert!(!stack.contains(LayerId::Gui));
+    }
+}
@@ -1,22 +1,263 @@
+pub mod action_state;
+pub mod bindings;
+pub mod chord;
+pub mod layers;
+
 use std::collections::{HashMap, HashSet};
-use ggez::{glam::Vec2, GameResult};
+use ggez::{glam::Vec2, input::gamepad::gilrs, GameResult};
+use serde::{Deserialize, Serialize};
+
+use action_state::ActionState;
+use chord::{Chord, ChordState};
+use layers::LayerStack;
 
 #[derive(Debug, Clone, Default)]
 pub struct KeyInputState
 {
     pressed_keys:           HashSet<ggez::input::keyboard::KeyCode>,
     pressed_mouse:          HashSet<ggez::input::mouse::MouseButton>,
+    /// Gamepad buttons and axis directions, across every connected gamepad
+    pressed_gamepad:        HashSet<Button>,
     modifiers:              ggez::input::keyboard::KeyMods,
-    alt_only_pending:       bool,
-    shift_only_pending:     bool,
-    control_only_pending:   bool,
-    logo_only_pending:      bool,
+
+    /// The modifier-only tap that fires if the last pressed key, a modifier,
+    /// is released before anything else is pressed
+    modifier_tap_pending:   Option<KeyCombo>,
 
     // mouse_position_curr:    Option<Vec2>,
     // mouse_position_prev:    Option<Vec2>,
 
-    key_combos:             ComboToAction,
-    pub held_actions:       HashSet<ActionCode>
+    layers:                 LayerStack,
+    actions:                ActionState,
+    chords:                 ChordState,
+
+    /// When set, the next key or mouse press is bound to this action
+    /// instead of being handled as input.
+    rebinding:              Option<ActionCode>,
+}
+
+impl KeyInputState
+{
+    pub fn new(key_combos: ComboToAction) -> Self
+    {
+        Self { layers: LayerStack::new(key_combos), ..Default::default() }
+    }
+
+    /// Pressed / held / released state of every action for the current fixed tick
+    pub fn actions(&self) -> &ActionState
+    {
+        &self.actions
+    }
+
+    /// Call at the start of every fixed tick, before reading `actions`.
+    pub fn begin_tick(&mut self)
+    {
+        let held = self.chords.tick(&self.layers.all_chords());
+        self.actions.tap(&held);
+
+        self.actions.begin_tick();
+    }
+
+    /// `key_combo` went down. It goes to the highest layer binding it,
+    /// unless a layer above that captures its device.
+    fn button_down(&mut self, key_combo: KeyCombo)
+    {
+        if self.layers.is_captured(key_combo.1)
+        {
+            return;
+        }
+
+        if let Some(v) = self.layers.resolve(&key_combo)
+        {
+            self.actions.press(key_combo.1, v);
+        }
+
+        let completed = self.chords.press(key_combo, &self.layers.reachable_chords(key_combo.1));
+        self.actions.tap(&completed);
+    }
+
+    /// `button` went up
+    fn button_up(&mut self, button: Button)
+    {
+        self.actions.release(button);
+        self.chords.release(button);
+    }
+
+    fn on_key_down(&mut self, key: ggez::input::keyboard::KeyCode, mods: ggez::input::keyboard::KeyMods, repeated: bool)
+    {
+        self.pressed_keys.insert(key);
+        self.modifiers = mods;
+
+        // held keys are tracked by ActionState, so OS key repeat is ignored
+        if repeated
+        {
+            return;
+        }
+
+        // a modifier also fires its tap if released before anything else is pressed. Its own
+        // modifier is left out of its press, so `LShift` binds the key held alone, apart from
+        // its tap `Shift+LShift`. A rebind waits for the tap or for what the modifier is held with.
+        if let Some(tap) = modifier_tap(key)
+        {
+            self.modifier_tap_pending = Some(tap);
+            self.button_down((self.modifiers - tap.0, key).into());
+            return;
+        }
+
+        self.modifier_tap_pending = None;
+        let key_combo = (self.modifiers, key).into();
+        if self.capture_rebind(key_combo)
+        {
+            return;
+        }
+
+        self.button_down(key_combo);
+    }
+
+    fn on_key_up(&mut self, key: ggez::input::keyboard::KeyCode, mods: ggez::input::keyboard::KeyMods)
+    {
+        self.pressed_keys.remove(&key);
+        self.modifiers = mods;
+        self.button_up(key.into());
+
+        let Some(tap) = modifier_tap(key) else { return };
+        if self.modifier_tap_pending.take() != Some(tap) || self.capture_rebind(tap)
+        {
+            return;
+        }
+
+        if let Some(v) = self.layers.resolve(&tap)
+        {
+            self.actions.tap(v);
+        }
+    }
+
+    fn on_mouse_down(&mut self, button: ggez::input::mouse::MouseButton)
+    {
+        self.pressed_mouse.insert(button);
+        self.modifier_tap_pending = None;
+
+        let key_combo: KeyCombo = (self.modifiers, button).into();
+        if self.capture_rebind(key_combo)
+        {
+            return;
+        }
+
+        self.button_down(key_combo);
+    }
+
+    fn on_mouse_up(&mut self, button: ggez::input::mouse::MouseButton)
+    {
+        self.pressed_mouse.remove(&button);
+        self.button_up(button.into());
+    }
+
+    /// Gamepad buttons ignore keyboard modifiers
+    fn on_gamepad_down(&mut self, button: Button)
+    {
+        if !self.pressed_gamepad.insert(button)
+        {
+            return;
+        }
+
+        self.modifier_tap_pending = None;
+        let key_combo = (ggez::input::keyboard::KeyMods::NONE, button).into();
+        if self.capture_rebind(key_combo)
+        {
+            return;
+        }
+
+        self.button_down(key_combo);
+    }
+
+    fn on_gamepad_up(&mut self, button: Button)
+    {
+        if self.pressed_gamepad.remove(&button)
+        {
+            self.button_up(button);
+        }
+    }
+
+    /// Each direction of an axis acts as its own analog button,
+    /// pressed while the axis is pushed that way past the dead zone.
+    fn on_gamepad_axis(&mut self, axis: gilrs::Axis, value: f32)
+    {
+        let value = self.layers.gameplay().apply_dead_zone(value);
+
+        for direction in [AxisDirection::Negative, AxisDirection::Positive]
+        {
+            let button = Button::GamepadAxis(axis, direction);
+            let strength = match direction
+            {
+                AxisDirection::Negative => -value,
+                AxisDirection::Positive => value,
+            };
+
+            if strength > 0.0
+            {
+                self.on_gamepad_down(button);
+                self.actions.set_strength(button, strength);
+            }
+            else
+            {
+                self.on_gamepad_up(button);
+            }
+        }
+    }
+
+    /// The bindings of the gameplay layer, which are the ones the user edits
+    pub fn key_combos(&self) -> &ComboToAction
+    {
+        self.layers.gameplay()
+    }
+
+    pub fn set_key_combos(&mut self, key_combos: ComboToAction)
+    {
+        *self.layers.gameplay_mut() = key_combos;
+    }
+
+    pub fn set_dead_zone(&mut self, dead_zone: f32)
+    {
+        self.layers.gameplay_mut().set_dead_zone(dead_zone);
+    }
+
+    pub fn layers(&self) -> &LayerStack
+    {
+        &self.layers
+    }
+
+    /// Push or pop layers as modes change, or update what a layer captures
+    pub fn layers_mut(&mut self) -> &mut LayerStack
+    {
+        &mut self.layers
+    }
+
+    /// Capture the next key, mouse or gamepad press as the binding for `action`.
+    /// Escape cancels.
+    pub fn begin_rebind(&mut self, action: ActionCode)
+    {
+        self.rebinding = Some(action);
+    }
+
+    pub fn rebinding(&self) -> Option<ActionCode>
+    {
+        self.rebinding
+    }
+
+    /// If a rebind is in progress, consume `combo` as the new binding.
+    /// Returns whether the press was consumed.
+    fn capture_rebind(&mut self, combo: KeyCombo) -> bool
+    {
+        let Some(action) = self.rebinding.take() else { return false };
+
+        if combo.1 != Button::Keyboard(ggez::input::keyboard::KeyCode::Escape)
+        {
+            self.layers.gameplay_mut().rebind(action, combo);
+            self.layers.gameplay().warn_conflicts();
+        }
+
+        true
+    }
 }
 
 impl ggez::event::EventHandler for KeyInputState
@@ -35,34 +276,11 @@ impl ggez::event::EventHandler for KeyInputState
         &mut self,
         _context: &mut ggez::Context,
         input: ggez::input::keyboard::KeyInput,
-        _repeated: bool,
+        repeated: bool,
     ) -> GameResult
     {
         let key = input.keycode.expect("Keycode in key-down will always be something");
-
-        self.pressed_keys.insert(key);
-        self.modifiers = input.mods;
-
-        use ggez::input::keyboard::KeyCode::*;
-        match key
-        {
-            LAlt | RAlt =>          self.alt_only_pending       = true,
-            RShift | LShift =>      self.shift_only_pending     = true,
-            RControl | LControl =>  self.control_only_pending   = true,
-            _ => (),
-        };
-
-        let key_combo: KeyCombo = (self.modifiers, key).into();
-        if let Some(v) = self.key_combos.get(&key_combo)
-        {
-            for &ac in v
-            {
-                self.held_actions.insert(ac);
-                self.alt_only_pending = false;
-                self.shift_only_pending = false;
-                self.control_only_pending = false;
-            }
-        }
+        self.on_key_down(key, input.mods, repeated);
         
         Ok(())
     }
@@ -74,35 +292,7 @@ impl ggez::event::EventHandler for KeyInputState
     ) -> GameResult 
     {
         let key = input.keycode.expect("Keycode in key-up will always be something");
-
-        self.pressed_keys.remove(&key);
-        self.modifiers = input.mods;
-
-        use ggez::input::keyboard::KeyCode::*;
-        match key
-        {
-            LAlt | RAlt if self.alt_only_pending =>
-            {
-                let key_combo: KeyCombo = (self.modifiers, LAlt).into();
-                if let Some(v) = self.key_combos.get(&key_combo)
-                {
-                    for &ac in v
-                    {
-                        self.held_actions.insert(ac);   
-                        self.alt_only_pending = false;
-                    }
-                }
-            },
-            // LShift | RShift if self.shift_only_pending =>
-            // {
-
-            // },
-            // LControl | RControl if self.control_only_pending =>
-            // {
-
-            // },
-            _ => (),
-        }
+        self.on_key_up(key, input.mods);
         
         Ok(())    
     }
@@ -115,19 +305,7 @@ impl ggez::event::EventHandler for KeyInputState
         _y: f32,
     ) -> GameResult
     {
-        self.pressed_mouse.insert(button);
-
-        let key_combo: KeyCombo = (self.modifiers, button).into();
-        if let Some(v) = self.key_combos.get(&key_combo)
-        {
-            for &ac in v
-            {
-                self.held_actions.insert(ac);
-                self.alt_only_pending = false;
-                self.shift_only_pending = false;
-                self.control_only_pending = false;
-            }
-        }
+        self.on_mouse_down(button);
         
         Ok(())
     }
@@ -140,14 +318,67 @@ impl ggez::event::EventHandler for KeyInputState
             _y: f32,
     ) -> GameResult
     {
-        self.pressed_mouse.remove(&button);
+        self.on_mouse_up(button);
         
         Ok(())
     }
+
+    fn gamepad_button_down_event(
+        &mut self,
+        _context: &mut ggez::Context,
+        button: gilrs::Button,
+        _id: ggez::input::gamepad::GamepadId,
+    ) -> GameResult
+    {
+        self.on_gamepad_down(button.into());
+
+        Ok(())
+    }
+
+    fn gamepad_button_up_event(
+        &mut self,
+        _context: &mut ggez::Context,
+        button: gilrs::Button,
+        _id: ggez::input::gamepad::GamepadId,
+    ) -> GameResult
+    {
+        self.on_gamepad_up(button.into());
+
+        Ok(())
+    }
+
+    fn gamepad_axis_event(
+        &mut self,
+        _context: &mut ggez::Context,
+        axis: gilrs::Axis,
+        value: f32,
+        _id: ggez::input::gamepad::GamepadId,
+    ) -> GameResult
+    {
+        self.on_gamepad_axis(axis, value);
+
+        Ok(())
+    }
+
+    fn focus_event(&mut self, _context: &mut ggez::Context, gained: bool) -> GameResult
+    {
+        // key-up events are lost while unfocused, so let go of everything
+        if !gained
+        {
+            self.pressed_keys.clear();
+            self.pressed_mouse.clear();
+            self.pressed_gamepad.clear();
+            self.modifier_tap_pending = None;
+            self.actions.release_all();
+            self.chords.release_all();
+        }
+
+        Ok(())
+    }
 }
 
 #[non_exhaustive]
-#[derive(Debug, Hash, PartialEq, Eq, Copy, Clone)]
+#[derive(Debug, Hash, PartialEq, Eq, Copy, Clone, Serialize, Deserialize)]
 pub enum ActionCode
 {
     CameraUp,
@@ -158,7 +389,59 @@ pub enum ActionCode
     TurnRight,
     Shoot,
     FlipDebugHitboxes,
-    Click
+    Click,
+    Select,
+    SelectAdd,
+    SelectToggle,
+    MoveSelected,
+    DeleteSelected,
+    ToggleKeyBindings,
+    CenterCamera,
+    ToggleAimMode,
+    ToggleEditor,
+    ToggleDebugOverlay,
+    ToggleConsole,
+    NextWeapon,
+    PrevWeapon,
+    PlaceTurret,
+    CycleTurretTargeting,
+    MoveLeft,
+    MoveRight,
+    Jump,
+}
+
+impl ActionCode
+{
+    /// Every action, in the order they are listed to the user
+    pub const ALL: [ActionCode; 27] = [
+        ActionCode::CameraUp,
+        ActionCode::CameraDown,
+        ActionCode::CameraLeft,
+        ActionCode::CameraRight,
+        ActionCode::TurnLeft,
+        ActionCode::TurnRight,
+        ActionCode::Shoot,
+        ActionCode::FlipDebugHitboxes,
+        ActionCode::Click,
+        ActionCode::Select,
+        ActionCode::SelectAdd,
+        ActionCode::SelectToggle,
+        ActionCode::MoveSelected,
+        ActionCode::DeleteSelected,
+        ActionCode::ToggleKeyBindings,
+        ActionCode::CenterCamera,
+        ActionCode::ToggleAimMode,
+        ActionCode::ToggleEditor,
+        ActionCode::ToggleDebugOverlay,
+        ActionCode::ToggleConsole,
+        ActionCode::NextWeapon,
+        ActionCode::PrevWeapon,
+        ActionCode::PlaceTurret,
+        ActionCode::CycleTurretTargeting,
+        ActionCode::MoveLeft,
+        ActionCode::MoveRight,
+        ActionCode::Jump,
+    ];
 }
 
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
@@ -166,6 +449,16 @@ enum Button
 {
     Mouse(ggez::input::mouse::MouseButton),
     Keyboard(ggez::input::keyboard::KeyCode),
+    Gamepad(gilrs::Button),
+    GamepadAxis(gilrs::Axis, AxisDirection),
+}
+
+/// Which way a gamepad axis is pushed
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AxisDirection
+{
+    Negative,
+    Positive,
 }
 
 impl From<ggez::input::mouse::MouseButton> for Button
@@ -184,9 +477,67 @@ impl From<ggez::input::keyboard::KeyCode> for Button
     }
 }
 
-#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+impl From<gilrs::Button> for Button
+{
+    fn from(value: gilrs::Button) -> Self 
+    {
+        Self::Gamepad(value)
+    }
+}
+
+/// The combo a lone tap of modifier `key` is bound as, e.g. `Alt+LAlt` for either Alt key.
+/// `None` if `key` is not a modifier.
+fn modifier_tap(key: ggez::input::keyboard::KeyCode) -> Option<KeyCombo>
+{
+    use ggez::input::keyboard::{KeyCode::*, KeyMods};
+    match key
+    {
+        LShift | RShift =>      Some((KeyMods::SHIFT, LShift).into()),
+        LControl | RControl =>  Some((KeyMods::CTRL, LControl).into()),
+        LAlt | RAlt =>          Some((KeyMods::ALT, LAlt).into()),
+        LWin | RWin =>          Some((KeyMods::LOGO, LWin).into()),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct KeyCombo(ggez::input::keyboard::KeyMods, Button);
 
+// KeyMods does not implement Hash, so hash its bits instead
+impl std::hash::Hash for KeyCombo
+{
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H)
+    {
+        self.0.bits().hash(state);
+        self.1.hash(state);
+    }
+}
+
+impl std::fmt::Display for KeyCombo
+{
+    /// Formats like `Ctrl+Alt+D`, `Mouse Left`, `Pad South` or `Pad LeftStickX-`
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        use ggez::input::keyboard::KeyMods;
+        for (m, name) in [(KeyMods::CTRL, "Ctrl"), (KeyMods::SHIFT, "Shift"), (KeyMods::ALT, "Alt"), (KeyMods::LOGO, "Logo")]
+        {
+            if self.0.contains(m)
+            {
+                write!(f, "{name}+")?;
+            }
+        }
+
+        match self.1
+        {
+            Button::Keyboard(key) => write!(f, "{key:?}"),
+            Button::Mouse(button) => write!(f, "Mouse {button:?}"),
+            Button::Gamepad(button) => write!(f, "Pad {button:?}"),
+            Button::GamepadAxis(axis, AxisDirection::Negative) => write!(f, "Pad {axis:?}-"),
+            Button::GamepadAxis(axis, AxisDirection::Positive) => write!(f, "Pad {axis:?}+"),
+        }
+    }
+}
+
 impl<B> From<(ggez::input::keyboard::KeyMods, B)> for KeyCombo
 where
     B: Into<Button>
@@ -197,8 +548,21 @@ where
     }
 }
 
-#[derive(Clone, Debug)]
-pub struct ComboToAction(HashMap<KeyCombo, Vec<ActionCode>>);
+/// Maps input to actions.
+///
+/// A plain combo triggers its actions while held. A combo of a modifier key with its
+/// own modifier, such as `Alt+LAlt`, is instead a modifier-only tap: it triggers
+/// for one tick when the modifier is released without anything pressed meanwhile.
+/// `Chord`s trigger for one tick when completed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ComboToAction
+{
+    combos: HashMap<KeyCombo, Vec<ActionCode>>,
+    chords: HashMap<Chord, Vec<ActionCode>>,
+
+    /// Gamepad axis values closer to zero than this are ignored
+    dead_zone: f32,
+}
 
 impl std::ops::Deref for ComboToAction
 {
@@ -206,26 +570,155 @@ impl std::ops::Deref for ComboToAction
 
     fn deref(&self) -> &Self::Target 
     {
-        &self.0
+        &self.combos
     }
 }
 
 impl ComboToAction
 {
+    /// No bindings at all
+    pub fn empty() -> Self
+    {
+        Self { combos: HashMap::new(), chords: HashMap::new(), dead_zone: Self::DEFAULT_DEAD_ZONE }
+    }
+
+    /// Bindings of the editor layer, pushed over gameplay while editing the world. Its
+    /// left clicks select entities instead of filling tiles.
+    pub fn editor() -> Self
+    {
+        use ggez::input::{mouse::MouseButton, keyboard::KeyMods};
+        let mut combos = Self::empty();
+        combos.bind((KeyMods::NONE, MouseButton::Left).into(), ActionCode::Select);
+        combos.bind((KeyMods::SHIFT, MouseButton::Left).into(), ActionCode::SelectAdd);
+        combos.bind((KeyMods::CTRL, MouseButton::Left).into(), ActionCode::SelectToggle);
+        combos.bind((KeyMods::NONE, MouseButton::Right).into(), ActionCode::PlaceTurret);
+        combos.bind((KeyMods::NONE, MouseButton::Middle).into(), ActionCode::CycleTurretTargeting);
+        combos
+    }
+
+    /// Bindings of the console layer, pushed while the console is open
+    pub fn console() -> Self
+    {
+        use ggez::input::keyboard::{KeyCode, KeyMods};
+        let mut combos = Self::empty();
+        combos.bind((KeyMods::NONE, KeyCode::Grave).into(), ActionCode::ToggleConsole);
+        combos
+    }
+
     fn resolve_key_combo(&self, kc: &KeyCombo) -> Option<&Vec<ActionCode>>
     {
         self.get(kc)
     }
+
+    /// Every combo currently bound to `action`
+    pub fn combos_for(&self, action: ActionCode) -> Vec<KeyCombo>
+    {
+        let mut combos: Vec<KeyCombo> = 
+            self.iter()
+            .filter(|(_, actions)| actions.contains(&action))
+            .map(|(&kc, _)| kc)
+            .collect();
+        combos.sort_by_key(|kc| kc.to_string());
+        combos
+    }
+
+    pub const DEFAULT_DEAD_ZONE: f32 = 0.2;
+
+    pub fn dead_zone(&self) -> f32
+    {
+        self.dead_zone
+    }
+
+    pub fn set_dead_zone(&mut self, dead_zone: f32)
+    {
+        self.dead_zone = dead_zone.clamp(0.0, 0.95);
+    }
+
+    /// Zero inside the dead zone, rescaled so the edge of the dead zone is 0 and full tilt is ±1
+    fn apply_dead_zone(&self, value: f32) -> f32
+    {
+        let magnitude = (value.abs() - self.dead_zone) / (1.0 - self.dead_zone);
+        magnitude.clamp(0.0, 1.0).copysign(value)
+    }
+
+    pub fn chords(&self) -> &HashMap<Chord, Vec<ActionCode>>
+    {
+        &self.chords
+    }
+
+    /// Every chord currently bound to `action`
+    pub fn chords_for(&self, action: ActionCode) -> Vec<Chord>
+    {
+        let mut chords: Vec<Chord> = 
+            self.chords.iter()
+            .filter(|(_, actions)| actions.contains(&action))
+            .map(|(chord, _)| chord.clone())
+            .collect();
+        chords.sort_by_key(|chord| chord.to_string());
+        chords
+    }
+
+    /// Additionally bind `combo` to `action`
+    pub fn bind(&mut self, combo: KeyCombo, action: ActionCode)
+    {
+        let actions = self.combos.entry(combo).or_default();
+        if !actions.contains(&action)
+        {
+            actions.push(action);
+        }
+    }
+
+    /// Additionally bind `chord` to `action`
+    pub fn bind_chord(&mut self, chord: Chord, action: ActionCode)
+    {
+        let actions = self.chords.entry(chord).or_default();
+        if !actions.contains(&action)
+        {
+            actions.push(action);
+        }
+    }
+
+    /// Replace every binding of `action`, chords included, with `combo`
+    pub fn rebind(&mut self, action: ActionCode, combo: KeyCombo)
+    {
+        self.combos.values_mut().for_each(|actions| actions.retain(|&ac| ac != action));
+        self.combos.retain(|_, actions| !actions.is_empty());
+        self.chords.values_mut().for_each(|actions| actions.retain(|&ac| ac != action));
+        self.chords.retain(|_, actions| !actions.is_empty());
+
+        self.bind(combo, action);
+    }
+
+    /// Combos that trigger more than one action, sorted by their display form.
+    pub fn conflicts(&self) -> Vec<(KeyCombo, Vec<ActionCode>)>
+    {
+        let mut conflicts: Vec<(KeyCombo, Vec<ActionCode>)> = 
+            self.iter()
+            .filter(|(_, actions)| actions.len() > 1)
+            .map(|(&kc, actions)| (kc, actions.clone()))
+            .collect();
+        conflicts.sort_by_key(|(kc, _)| kc.to_string());
+        conflicts
+    }
+
+    pub fn warn_conflicts(&self)
+    {
+        for (kc, actions) in self.conflicts()
+        {
+            println!("warning: key binding {kc} is mapped to multiple actions: {actions:?}");
+        }
+    }
 }
 
 impl Default for ComboToAction
 {
     fn default() -> Self 
     {
-        use ggez::input::{mouse::MouseButton, keyboard::{KeyCode, KeyMods}};
-        let map = HashMap::from(
+        use ggez::input::{keyboard::{KeyCode, KeyMods}, mouse::MouseButton};
+        let combos = HashMap::from(
             [
                 ((KeyMods::ALT, KeyCode::D).into(), vec![ActionCode::Shoot]),
+                ((KeyMods::NONE, KeyCode::Space).into(), vec![ActionCode::Shoot]),
                 ((KeyMods::NONE, KeyCode::Left).into(), vec![ActionCode::TurnLeft]),
                 ((KeyMods::NONE, KeyCode::Right).into(), vec![ActionCode::TurnRight]),
 
@@ -234,14 +727,262 @@ impl Default for ComboToAction
                 ((KeyMods::NONE, KeyCode::W).into(), vec![ActionCode::CameraUp]),
                 ((KeyMods::NONE, KeyCode::S).into(), vec![ActionCode::CameraDown]),
                 ((KeyMods::NONE, KeyCode::A).into(), vec![ActionCode::CameraLeft]),
-                ((KeyMods::NONE, KeyCode::D).into(), vec![ActionCode::CameraRight, ActionCode::Shoot]),
+                ((KeyMods::NONE, KeyCode::D).into(), vec![ActionCode::CameraRight]),
 
+                ((KeyMods::ALT, KeyCode::LAlt).into(), vec![ActionCode::FlipDebugHitboxes]),
                 ((KeyMods::NONE, MouseButton::Left).into(), vec![ActionCode::Click]),
+                ((KeyMods::NONE, MouseButton::Right).into(), vec![ActionCode::MoveSelected]),
+                ((KeyMods::NONE, KeyCode::Delete).into(), vec![ActionCode::DeleteSelected]),
+
+                ((KeyMods::NONE, KeyCode::F1).into(), vec![ActionCode::ToggleKeyBindings]),
+                ((KeyMods::NONE, KeyCode::M).into(), vec![ActionCode::ToggleAimMode]),
+                ((KeyMods::NONE, KeyCode::F2).into(), vec![ActionCode::ToggleEditor]),
+                ((KeyMods::NONE, KeyCode::F3).into(), vec![ActionCode::ToggleDebugOverlay]),
+                ((KeyMods::NONE, KeyCode::Grave).into(), vec![ActionCode::ToggleConsole]),
+                ((KeyMods::NONE, KeyCode::E).into(), vec![ActionCode::NextWeapon]),
+                ((KeyMods::NONE, KeyCode::Q).into(), vec![ActionCode::PrevWeapon]),
+                ((KeyMods::NONE, KeyCode::J).into(), vec![ActionCode::MoveLeft]),
+                ((KeyMods::NONE, KeyCode::L).into(), vec![ActionCode::MoveRight]),
+                ((KeyMods::NONE, KeyCode::I).into(), vec![ActionCode::Jump]),
+
+                ((KeyMods::NONE, gilrs::Button::South).into(), vec![ActionCode::Shoot]),
+                ((KeyMods::NONE, gilrs::Button::RightTrigger2).into(), vec![ActionCode::Shoot]),
+                ((KeyMods::NONE, Button::GamepadAxis(gilrs::Axis::LeftStickX, AxisDirection::Negative)).into(), vec![ActionCode::TurnLeft]),
+                ((KeyMods::NONE, Button::GamepadAxis(gilrs::Axis::LeftStickX, AxisDirection::Positive)).into(), vec![ActionCode::TurnRight]),
+                ((KeyMods::NONE, Button::GamepadAxis(gilrs::Axis::RightStickY, AxisDirection::Positive)).into(), vec![ActionCode::CameraUp]),
+                ((KeyMods::NONE, Button::GamepadAxis(gilrs::Axis::RightStickY, AxisDirection::Negative)).into(), vec![ActionCode::CameraDown]),
+                ((KeyMods::NONE, Button::GamepadAxis(gilrs::Axis::RightStickX, AxisDirection::Negative)).into(), vec![ActionCode::CameraLeft]),
+                ((KeyMods::NONE, Button::GamepadAxis(gilrs::Axis::RightStickX, AxisDirection::Positive)).into(), vec![ActionCode::CameraRight]),
+                ((KeyMods::NONE, gilrs::Button::Select).into(), vec![ActionCode::ToggleKeyBindings]),
+                ((KeyMods::NONE, gilrs::Button::RightTrigger).into(), vec![ActionCode::NextWeapon]),
+                ((KeyMods::NONE, gilrs::Button::LeftTrigger).into(), vec![ActionCode::PrevWeapon]),
+                ((KeyMods::NONE, gilrs::Button::DPadLeft).into(), vec![ActionCode::MoveLeft]),
+                ((KeyMods::NONE, gilrs::Button::DPadRight).into(), vec![ActionCode::MoveRight]),
+                ((KeyMods::NONE, gilrs::Button::East).into(), vec![ActionCode::Jump]),
+            ]
+        );
 
-                ((KeyMods::ALT, KeyCode::LAlt).into(), vec![ActionCode::FlipDebugHitboxes]),
+        let chords = HashMap::from(
+            [
+                (Chord::Sequence(vec![(KeyMods::NONE, KeyCode::C).into(), (KeyMods::NONE, KeyCode::C).into()]), vec![ActionCode::CenterCamera]),
             ]
         );
 
-        Self(map)    
+        Self { combos, chords, dead_zone: Self::DEFAULT_DEAD_ZONE }
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+    use ggez::input::keyboard::{KeyCode, KeyMods};
+
+    fn tapped(state: &mut KeyInputState, action: ActionCode) -> bool
+    {
+        state.begin_tick();
+        state.actions().just_pressed(action)
+    }
+
+    #[test]
+    fn modifiers_bind_as_plain_keys_too()
+    {
+        let mut combos = ComboToAction::default();
+        combos.bind((KeyMods::NONE, KeyCode::LShift).into(), ActionCode::Jump);
+        combos.bind((KeyMods::SHIFT, KeyCode::LShift).into(), ActionCode::Shoot);
+        combos.bind((KeyMods::SHIFT, KeyCode::A).into(), ActionCode::Click);
+        let mut state = KeyInputState::new(combos);
+
+        // held alone, it's held as itself, and still taps when let go
+        state.on_key_down(KeyCode::LShift, KeyMods::SHIFT, false);
+        state.begin_tick();
+        assert!(state.actions().is_held(ActionCode::Jump));
+        state.on_key_up(KeyCode::LShift, KeyMods::NONE);
+        assert!(tapped(&mut state, ActionCode::Shoot));
+        assert!(!state.actions().is_held(ActionCode::Jump));
+
+        // and it still modifies what is pressed with it
+        state.on_key_down(KeyCode::LShift, KeyMods::SHIFT, false);
+        state.on_key_down(KeyCode::A, KeyMods::SHIFT, false);
+        assert!(tapped(&mut state, ActionCode::Click));
+        state.on_key_up(KeyCode::LShift, KeyMods::NONE);
+        assert!(!tapped(&mut state, ActionCode::Shoot));
+    }
+
+    #[test]
+    fn lone_modifier_taps()
+    {
+        let mut combos = ComboToAction::default();
+        combos.bind((KeyMods::SHIFT, KeyCode::LShift).into(), ActionCode::Shoot);
+        combos.bind((KeyMods::CTRL, KeyCode::LControl).into(), ActionCode::Click);
+        let mut state = KeyInputState::new(combos);
+
+        // either shift key, whatever the platform reports for modifiers on release
+        state.on_key_down(KeyCode::RShift, KeyMods::SHIFT, false);
+        state.on_key_up(KeyCode::RShift, KeyMods::NONE);
+        assert!(tapped(&mut state, ActionCode::Shoot));
+
+        state.on_key_down(KeyCode::LControl, KeyMods::CTRL, false);
+        state.on_key_up(KeyCode::LControl, KeyMods::CTRL);
+        assert!(tapped(&mut state, ActionCode::Click));
+        assert!(!state.actions().is_held(ActionCode::Shoot));
+    }
+
+    #[test]
+    fn modifier_used_in_combo_is_not_a_tap()
+    {
+        let mut state = KeyInputState::default();
+
+        state.on_key_down(KeyCode::LAlt, KeyMods::ALT, false);
+        state.on_key_down(KeyCode::D, KeyMods::ALT, false);
+        state.on_key_up(KeyCode::D, KeyMods::ALT);
+        state.on_key_up(KeyCode::LAlt, KeyMods::NONE);
+
+        assert!(tapped(&mut state, ActionCode::Shoot));
+        assert!(!state.actions().just_pressed(ActionCode::FlipDebugHitboxes));
+
+        state.on_key_down(KeyCode::LAlt, KeyMods::ALT, false);
+        state.on_key_up(KeyCode::LAlt, KeyMods::NONE);
+        assert!(tapped(&mut state, ActionCode::FlipDebugHitboxes));
+    }
+
+    #[test]
+    fn default_double_tap_centers_camera()
+    {
+        let mut state = KeyInputState::default();
+
+        state.on_key_down(KeyCode::C, KeyMods::NONE, false);
+        state.on_key_up(KeyCode::C, KeyMods::NONE);
+        assert!(!tapped(&mut state, ActionCode::CenterCamera));
+
+        state.on_key_down(KeyCode::C, KeyMods::NONE, false);
+        assert!(tapped(&mut state, ActionCode::CenterCamera));
+    }
+
+    #[test]
+    fn gamepad_buttons_map_to_actions()
+    {
+        let mut state = KeyInputState::default();
+
+        state.on_gamepad_down(gilrs::Button::South.into());
+        state.begin_tick();
+        assert!(state.actions().just_pressed(ActionCode::Shoot));
+
+        state.on_gamepad_up(gilrs::Button::South.into());
+        state.begin_tick();
+        assert!(state.actions().just_released(ActionCode::Shoot));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn stick_turns_proportionally_past_dead_zone()
+    {
+        let mut state = KeyInputState::default();
+        let dead_zone = ComboToAction::DEFAULT_DEAD_ZONE;
+
+        // inside the dead zone nothing happens
+        state.on_gamepad_axis(gilrs::Axis::LeftStickX, -dead_zone / 2.0);
+        state.begin_tick();
+        assert!(!state.actions().is_held(ActionCode::TurnLeft));
+
+        // halfway between the dead zone and full tilt
+        state.on_gamepad_axis(gilrs::Axis::LeftStickX, -(dead_zone + 1.0) / 2.0);
+        state.begin_tick();
+        assert!(state.actions().just_pressed(ActionCode::TurnLeft));
+        assert!((state.actions().value(ActionCode::TurnLeft) - 0.5).abs() < 1e-5);
+
+        state.on_gamepad_axis(gilrs::Axis::LeftStickX, -1.0);
+        state.begin_tick();
+        assert!(!state.actions().just_pressed(ActionCode::TurnLeft));
+        assert_eq!(state.actions().value(ActionCode::TurnLeft), 1.0);
+
+        // flicking across releases one direction and presses the other
+        state.on_gamepad_axis(gilrs::Axis::LeftStickX, 1.0);
+        state.begin_tick();
+        assert!(state.actions().just_released(ActionCode::TurnLeft));
+        assert!(state.actions().just_pressed(ActionCode::TurnRight));
+
+        state.on_gamepad_axis(gilrs::Axis::LeftStickX, 0.0);
+        state.begin_tick();
+        assert!(state.actions().just_released(ActionCode::TurnRight));
+        assert_eq!(state.actions().value(ActionCode::TurnRight), 0.0);
+    }
+
+    #[test]
+    fn gui_capture_blocks_click()
+    {
+        use ggez::input::mouse::MouseButton;
+        use layers::{Capture, LayerId};
+
+        let mut state = KeyInputState::default();
+        state.layers_mut().set_capture(LayerId::Gui, Capture { pointer: true, ..Default::default() });
+        state.on_mouse_down(MouseButton::Left);
+        assert!(!tapped(&mut state, ActionCode::Click));
+        state.on_mouse_up(MouseButton::Left);
+
+        // the keyboard still reaches gameplay
+        state.on_key_down(KeyCode::Space, KeyMods::NONE, false);
+        assert!(tapped(&mut state, ActionCode::Shoot));
+
+        state.layers_mut().set_capture(LayerId::Gui, Capture::default());
+        state.on_mouse_down(MouseButton::Left);
+        assert!(tapped(&mut state, ActionCode::Click));
+    }
+
+    #[test]
+    fn hold_chord_fires_after_its_ticks()
+    {
+        let mut combos = ComboToAction::default();
+        combos.bind_chord(Chord::Hold((KeyMods::NONE, KeyCode::H).into(), 2), ActionCode::Shoot);
+        let mut state = KeyInputState::new(combos);
+
+        state.on_key_down(KeyCode::H, KeyMods::NONE, false);
+        assert!(!tapped(&mut state, ActionCode::Shoot));
+        assert!(tapped(&mut state, ActionCode::Shoot));
+        assert!(!tapped(&mut state, ActionCode::Shoot));
+    }
+
+    /// Where `action` is in `ActionCode::ALL`. Matching every action here means a new one
+    /// won't compile until it's given a place, which `all_lists_every_action` then checks.
+    fn listed_at(action: ActionCode) -> usize
+    {
+        match action
+        {
+            ActionCode::CameraUp => 0,
+            ActionCode::CameraDown => 1,
+            ActionCode::CameraLeft => 2,
+            ActionCode::CameraRight => 3,
+            ActionCode::TurnLeft => 4,
+            ActionCode::TurnRight => 5,
+            ActionCode::Shoot => 6,
+            ActionCode::FlipDebugHitboxes => 7,
+            ActionCode::Click => 8,
+            ActionCode::Select => 9,
+            ActionCode::SelectAdd => 10,
+            ActionCode::SelectToggle => 11,
+            ActionCode::MoveSelected => 12,
+            ActionCode::DeleteSelected => 13,
+            ActionCode::ToggleKeyBindings => 14,
+            ActionCode::CenterCamera => 15,
+            ActionCode::ToggleAimMode => 16,
+            ActionCode::ToggleEditor => 17,
+            ActionCode::ToggleDebugOverlay => 18,
+            ActionCode::ToggleConsole => 19,
+            ActionCode::NextWeapon => 20,
+            ActionCode::PrevWeapon => 21,
+            ActionCode::PlaceTurret => 22,
+            ActionCode::CycleTurretTargeting => 23,
+            ActionCode::MoveLeft => 24,
+            ActionCode::MoveRight => 25,
+            ActionCode::Jump => 26,
+        }
+    }
+
+    #[test]
+    fn all_lists_every_action()
+    {
+        for (i, action) in ActionCode::ALL.into_iter().enumerate()
+        {
+            assert_eq!(listed_at(action), i, "{action:?} is out of place in ActionCode::ALL");
+        }
+    }
+}
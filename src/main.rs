@@ -1,23 +1,39 @@
 // local imports
+mod chess;
+mod console;
+mod debug_draw;
 mod game_object;
 mod util;
 mod gui;
+mod events;
 mod input;
-
-use game_object::{enemy::Enemy, enemy_wall::EnemyWall, grid::{Chunk, Object, PackedU8}, Region, HasPosition};
-use ggez::{glam::{Vec2, Vec3, Vec4}, mint::{Vector2, Vector4}};
+mod post_process;
+mod replay;
+mod schedule;
+mod settings;
+mod world;
+
+use game_object::{enemy::Enemy, enemy_wall::EnemyWall, grid::{Chunk, Object, PackedU8}, raycast::Ray, selection::{Entity, Group, SelectMode}, Region};
+use ggez::glam::{Vec2, Vec3, Vec4};
+use console::{Command, Console, Target};
+use debug_draw::{Category, DebugDraw};
 use gui::GUIState;
-use input::{ComboToAction, KeyInputState};
+use input::{layers::{InputLayer, LayerId}, ComboToAction, KeyInputState};
+use post_process::PostProcessChain;
+use replay::{Playback, Recording, ReplayMode};
+use schedule::{draw_system, update_system, Run, Schedule, System};
+use settings::Settings;
 use serde::{Deserialize, Serialize};
 use util::hash_map_tracker::HashMapTracker;
+use world::{TickInput, World};
 // use std::collections::HashMap;
-use crate::game_object::HasRegion;
 
 
 use crate::game_object::{
-    cannon::{Cannon, RotateDir}, 
+    cannon::{AimMode, Cannon, RotateDir}, 
     missile::Missile, 
-    // player::Player, 
+    turret::Turret, 
+    player::Player, 
     Draw, 
     FixedUpdate, 
     Update
@@ -25,34 +41,73 @@ use crate::game_object::{
 
 struct MainState
 {
+    /// The world is rendered here, then run through `post_process` before presenting.
     screen: ggez::graphics::ScreenImage,
     assets: Assets,
     
-    periscope: PeriscopeUniform,
-    periscope_shader: ggez::graphics::Shader,
+    post_process: PostProcessChain,
 
     /// The position of the camera, per se. 
     world_pos: Vec2,
 
-    cannon: Cannon,
-    missiles: HashMapTracker<Missile>,
+    /// Everything the fixed-tick simulation works on
+    world: World,
     enemy_walls: HashMapTracker<EnemyWall>,
-    chunks: Vec<Chunk>,
+    /// Outlined, and shown in the inspector
+    selection: Group,
 
-    enemies: Vec<Enemy>,
+    /// Live play, or recording or replaying the world's input
+    replay: ReplayMode,
 
     gui_state: GUIState,
     gui: ggegui::Gui,
 
     key_input_state: KeyInputState,
 
+    /// Preferences saved between sessions
+    settings: Settings,
+
     debug_state: DebugState,
+    /// Debug drawings pushed while drawing this frame
+    debug_draw: DebugDraw,
+
+    /// Every input, update and draw system, in the order they run
+    systems: Schedule,
+
+    console: Console,
+    /// How fast fixed ticks run compared to real time, set from the console
+    time_scale: f32,
+    /// A game of chess, played from the console
+    chess: chess::Board,
 }
 
 #[derive(Default)]
 struct DebugState
 {
-    draw_hitboxes: bool
+    /// Which categories of debug drawings are drawn
+    debug_shown: debug_draw::Shown,
+    overlay_shown: bool,
+
+    /// Milliseconds each of the most recent frames took, oldest first
+    frame_times: std::collections::VecDeque<f32>,
+    /// Fixed ticks run during the last frame
+    ticks_last_frame: u32,
+}
+
+impl DebugState
+{
+    /// Frames of timing kept for the overlay's graph
+    const FRAME_HISTORY: usize = 120;
+
+    fn record_frame(&mut self, frame_time: std::time::Duration, ticks: u32)
+    {
+        if self.frame_times.len() == DebugState::FRAME_HISTORY
+        {
+            self.frame_times.pop_front();
+        }
+        self.frame_times.push_back(frame_time.as_secs_f32() * 1000.0);
+        self.ticks_last_frame = ticks;
+    }
 }
 
 impl MainState
@@ -61,6 +116,17 @@ impl MainState
     const WINDOW_Y: f32 = 800.0;
     const FIXED_PHYSICS_FRAMERATE: u32 = 60;
     const FIXED_PHYSICS_TIMESTEP: f32 = 1.0 / MainState::FIXED_PHYSICS_FRAMERATE as f32;
+    /// Tiles per second the camera pans while a camera action is held
+    const CAMERA_PAN_SPEED: f32 = 10.0;
+    /// Ticks a camera action must be held before panning doubles in speed
+    const CAMERA_PAN_RAMP_TICKS: u32 = MainState::FIXED_PHYSICS_FRAMERATE / 2;
+    const MAX_TIME_SCALE: f32 = 4.0;
+    /// The actions that click or drag out a selection, and how each changes it
+    const SELECT_ACTIONS: [(input::ActionCode, SelectMode); 3] = [
+        (input::ActionCode::Select, SelectMode::Replace),
+        (input::ActionCode::SelectAdd, SelectMode::Add),
+        (input::ActionCode::SelectToggle, SelectMode::Toggle),
+    ];
 
     fn new(context: &mut ggez::Context) -> ggez::GameResult<MainState>
     {
@@ -71,47 +137,36 @@ impl MainState
                 1.0, 1.0, 1
             );
         let assets = Assets::new(context)?;
-        // let player = Player::default().feet_offset([0.0, 20.0].into()).grounded(false);
-        let periscope = PeriscopeUniform::new([0.0, 0.0], 0.5);
-        let periscope_shader = 
-            ggez::graphics::ShaderBuilder::new().fragment_path("/periscope.wgsl").build(context)?;
-        let cannon = Cannon::default();
-        let missiles = HashMapTracker::new();
+        let post_process = PostProcessChain::with_default_passes(context)?;
         let mut enemy_walls = HashMapTracker::new();
 
         let example_wall = 
             EnemyWall::default()
-            .rect(ggez::graphics::Rect::new(0.0, 0.0, 50.0, 10.0))
-            .transform(ggez::graphics::Transform::Values { dest: [600.0, 300.0].into(), rotation: -0.6, scale: [50.0, 10.0].into(), offset: [0.0, 0.0].into() });
+            .position(Vec2::new(37.5, 18.75))
+            .size(Vec2::new(3.125, 0.625))
+            .rotation(-0.6);
 
         enemy_walls.push(example_wall);
 
-        let chunk: Chunk = Chunk::default();
-        let mut chunk2 = chunk.clone();
-        chunk2.upper_left_position = [-16.0, 0.0].into();
-        let mut chunk3 = chunk.clone();
-        chunk3.upper_left_position = [-16.0, -16.0].into();
-        let mut chunk4 = chunk.clone();
-        chunk4.upper_left_position = [0.0, -16.0].into();
+        // nothing in the simulation is random yet, but a fresh seed per session
+        // means recordings already carry the one they need
+        let seed = 
+            std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos() as u64);
+        let settings = Settings::load_or_default(&Settings::config_path(context));
 
-        let chunks = vec![chunk, chunk2, chunk3, chunk4];
+        let mut world = World::new(seed);
+        world.cannon.aim = settings.aim_mode;
+        let replay = ReplayMode::default();
 
         let world_pos = [0.5, 0.5].into();
 
-        let enemies = vec![
-            // <Enemy::default()
-            // .position_set((12.0, 5.0).into()) as HasRegion<game_object::collider_type::Collider>>::region_set()
-            // .region_set(((-0.75, -0.75), (0.75, 0.75)).into())
-
-            HasRegion::<game_object::collider_type::Collider>::region_set(
-                Enemy::default().position_set((12.0, 5.0).into()), 
-                ((-0.75, -0.75), (0.75, 0.75)).into())
-        ];
-
         let gui_state = GUIState::default();
         let gui = ggegui::Gui::new(&context);
 
-        let key_input_state = KeyInputState::default();
+        let key_combos = ComboToAction::load_or_default(&ComboToAction::config_path(context));
+        let key_input_state = KeyInputState::new(key_combos);
 
         let debug_state = DebugState::default();
 
@@ -119,60 +174,442 @@ impl MainState
         {
             screen,
             assets,
-            periscope,
-            periscope_shader,
-            cannon,
-            missiles,
+            post_process,
+            world,
             enemy_walls,
-            chunks,
+            selection: Group::default(),
+            replay,
             world_pos,
-            enemies,
             gui_state,
             gui,
 
             key_input_state,
+            settings,
 
             debug_state,
+            debug_draw: DebugDraw::default(),
+
+            systems: MainState::systems(),
+
+            console: MainState::console(),
+            time_scale: 1.0,
+            chess: chess::Board::default(),
         };
 
         Ok(s)
     }
-}
 
-impl Update<PeriscopeUniform> for MainState
-{
-    fn update(&mut self, context: &mut ggez::Context) -> ggez::GameResult 
+    /// Every system run outside the world's fixed step. 
+    /// An `Update` or `Draw` not listed here never runs.
+    fn systems() -> Schedule
     {
-        let ps = &mut self.periscope;
+        Schedule::new(vec![
+            System::new("actions", Run::Input(MainState::apply_actions)),
+
+            update_system!(ggegui::Gui),
+            update_system!(PostProcessChain),
+
+            // later draws go on top
+            draw_system!(Draw, Cannon),
+            draw_system!(Draw, Vec<Turret>),
+            draw_system!(Draw, HashMapTracker<Missile>),
+            draw_system!(Draw, HashMapTracker<EnemyWall>),
+            draw_system!(Draw, Vec<Chunk>),
+            draw_system!(Draw, HashMapTracker<Enemy>),
+            draw_system!(Draw, Player),
+            draw_system!(Draw, Entity),
+            draw_system!(Draw, DebugDraw),
+
+            draw_system!(Post, ggegui::Gui),
+        ])
+        .expect("the systems are ordered consistently")
+    }
 
-        ps.position = context.mouse.position().into();
+    /// The console, with the commands of every part of the game
+    fn console() -> Console
+    {
+        Console::default()
+        .register(Enemy::commands())
+        .and_then(|console| console.register(Chunk::commands()))
+        .and_then(|console| console.register(World::commands()))
+        .and_then(|console| console.register(Ray::commands()))
+        .and_then(|console| console.register(MainState::commands()))
+        .and_then(|console| console.register(MainState::chess_commands()))
+        .expect("console command names are unique")
+    }
 
-        Ok(())    
+    /// Console commands acting on the camera and debugging
+    fn commands() -> Vec<Command>
+    {
+        vec![
+            Command
+            {
+                name: "camera goto",
+                args: "<x> <y>",
+                help: "move the camera's corner to world position x, y",
+                run: |args, target|
+                {
+                    let position = Vec2::new(args.next("x")?, args.next("y")?);
+                    args.finish()?;
+                    *target.camera = position;
+                    Ok(String::new())
+                },
+            },
+            Command
+            {
+                name: "hitboxes",
+                args: "<on|off>",
+                help: "draw collision regions",
+                run: |args, target|
+                {
+                    let shown = args.switch()?;
+                    args.finish()?;
+                    *target.debug_shown.get_mut(Category::Hitboxes) = shown;
+                    Ok(String::new())
+                },
+            },
+            Command
+            {
+                name: "debug draw",
+                args: "<hitboxes|chunks|paths|rays> <on|off>",
+                help: "show or hide a category of debug drawings",
+                run: |args, target|
+                {
+                    let category: Category = args.next("category")?;
+                    let shown = args.switch()?;
+                    args.finish()?;
+                    *target.debug_shown.get_mut(category) = shown;
+                    Ok(String::new())
+                },
+            },
+            Command
+            {
+                name: "timescale",
+                args: "<scale>",
+                help: "run fixed ticks at a multiple of real time, up to 4",
+                run: |args, target|
+                {
+                    let scale: f32 = args.next("scale")?;
+                    args.finish()?;
+                    if !(scale > 0.0 && scale <= MainState::MAX_TIME_SCALE)
+                    {
+                        return Err(ggez::GameError::CustomError(format!("{scale} is not in (0, {}]", MainState::MAX_TIME_SCALE)));
+                    }
+                    *target.time_scale = scale;
+                    Ok(String::new())
+                },
+            },
+        ]
     }
-}
 
-impl Draw<PeriscopeUniform> for MainState
-{
-    fn draw(&self, context: &mut ggez::Context, canvas: &mut ggez::graphics::Canvas) -> ggez::GameResult 
+    /// Console commands playing chess on the side
+    fn chess_commands() -> Vec<Command>
     {
-        let ps = &self.periscope;
-        
-        use ggez::graphics;
-        // canvas.set_blend_mode(graphics::BlendMode::DARKEN);
-        canvas.set_blend_mode(graphics::BlendMode::MULTIPLY);
-        // let shader = graphics::ShaderBuilder::new().fragment_path("/periscope.wgsl").build(context)?;
-        
-        canvas.set_shader(&self.periscope_shader);
-        let params = graphics::ShaderParamsBuilder::new(ps).build(context);
-        canvas.set_shader_params(&params);
+        // the rules know nothing of ggez, so their errors are wrapped here
+        fn wrap(e: chess::ChessError) -> ggez::GameError
+        {
+            ggez::GameError::CustomError(e.to_string())
+        }
+
+        vec![
+            Command
+            {
+                name: "chess new",
+                args: "",
+                help: "set up a new game of chess",
+                run: |args, target|
+                {
+                    args.finish()?;
+                    *target.chess = chess::Board::default();
+                    Ok(target.chess.to_string())
+                },
+            },
+            Command
+            {
+                name: "chess show",
+                args: "",
+                help: "show the chess board and how the game stands",
+                run: |args, target|
+                {
+                    args.finish()?;
+                    Ok(format!("{}\n{:?}", target.chess, target.chess.status()))
+                },
+            },
+            Command
+            {
+                name: "chess moves",
+                args: "",
+                help: "list the legal moves",
+                run: |args, target|
+                {
+                    args.finish()?;
+                    let moves: Vec<String> = target.chess.legal_moves().iter().map(chess::Move::to_string).collect();
+                    Ok(if moves.is_empty() { format!("no legal moves, {:?}", target.chess.status()) } else { moves.join(" ") })
+                },
+            },
+            Command
+            {
+                name: "chess move",
+                args: "<move>",
+                help: "play a move like e2e4, or e7e8q to promote",
+                run: |args, target|
+                {
+                    let mv = target.chess.parse_move(&args.next::<String>("move")?).map_err(wrap)?;
+                    args.finish()?;
+                    target.chess.make_move(mv);
+                    Ok(format!("{}\n{:?}", target.chess, target.chess.status()))
+                },
+            },
+            Command
+            {
+                name: "chess fen",
+                args: "[<fen>]",
+                help: "print the position in FEN, or set it up from one",
+                run: |args, target|
+                {
+                    let fen = args.rest();
+                    if fen.is_empty()
+                    {
+                        return Ok(target.chess.to_fen());
+                    }
+                    *target.chess = chess::Board::from_fen(&fen).map_err(wrap)?;
+                    Ok(target.chess.to_string())
+                },
+            },
+            Command
+            {
+                name: "chess undo",
+                args: "",
+                help: "take back the last move",
+                run: |args, target|
+                {
+                    args.finish()?;
+                    let mv = target.chess.unmake_move().ok_or_else(|| ggez::GameError::CustomError("no move to take back".to_string()))?;
+                    Ok(format!("took back {mv}"))
+                },
+            },
+        ]
+    }
+
+    /// Run a console command line on the game
+    fn run_console(&mut self, line: &str, context: &ggez::Context)
+    {
+        let save_dir = context.fs.user_data_dir().join("saves");
+        let mut target =
+            Target::new(
+                &mut self.world,
+                self.replay.locks_world(),
+                &mut self.world_pos,
+                &mut self.debug_state.debug_shown,
+                &mut self.time_scale,
+                &mut self.chess,
+                &save_dir,
+            );
+
+        // the result is kept in the console's history, which the console window shows
+        let _ = self.console.run(line, &mut target);
+    }
+
+    /// The input for the next fixed tick: the player's, or the replay's while one is playing.
+    /// While recording, the player's input is also recorded.
+    fn next_tick_input(&mut self, context: &ggez::Context) -> TickInput
+    {
+        // live input keeps ticking during a replay, so nothing pressed meanwhile is left over
+        self.key_input_state.begin_tick();
+
+        if let ReplayMode::Playing(playback) = &mut self.replay
+        {
+            if let Some(input) = playback.next_input()
+            {
+                return input;
+            }
+
+            match playback.recording().check_end(&self.world)
+            {
+                Ok(()) => println!("Replay finished after {} ticks", playback.recording().len()),
+                Err(e) => println!("{e}"),
+            }
+            self.replay = ReplayMode::Live;
+        }
+
+        let actions = self.key_input_state.actions().clone();
+        // the mouse only matters to the world while clicking, selecting, placing turrets or aiming with it
+        let wants_mouse =
+            actions.is_held(input::ActionCode::Click)
+            || MainState::SELECT_ACTIONS.iter().any(|&(ac, _)| actions.just_pressed(ac) || actions.just_released(ac))
+            || actions.just_pressed(input::ActionCode::MoveSelected)
+            || actions.just_pressed(input::ActionCode::PlaceTurret)
+            || actions.just_pressed(input::ActionCode::CycleTurretTargeting)
+            || self.world.cannon.aim == AimMode::Mouse;
+        let mouse_world = 
+            wants_mouse
+            .then(|| Vec2::from(context.mouse.position()) / 16.0 + self.world_pos);
+        let input = TickInput::new(actions, mouse_world);
+
+        if let ReplayMode::Recording(recording) = &mut self.replay
+        {
+            recording.push(input.clone());
+        }
+
+        input
+    }
+
+    /// React to the actions of a fixed tick that concern the camera, gui and debugging, not the world
+    fn apply_actions(&mut self, input: &TickInput) -> ggez::GameResult
+    {
+        let actions = &input.actions;
+
+        // check debug state
+        if actions.just_pressed(input::ActionCode::FlipDebugHitboxes)
+        {
+            let hitboxes = self.debug_state.debug_shown.get_mut(Category::Hitboxes);
+            *hitboxes = !*hitboxes;
+        }
+
+        if actions.just_pressed(input::ActionCode::ToggleDebugOverlay)
+        {
+            self.debug_state.overlay_shown = !self.debug_state.overlay_shown;
+        }
+
+        if actions.just_pressed(input::ActionCode::ToggleConsole)
+        {
+            self.gui_state.console_shown = !self.gui_state.console_shown;
+        }
+
+        for (ac, mode) in MainState::SELECT_ACTIONS
+        {
+            if let (true, Some(pos)) = (actions.just_pressed(ac), input.mouse_world())
+            {
+                self.selection.begin_drag(pos, mode);
+            }
+            if let (true, Some(pos)) = (actions.just_released(ac), input.mouse_world())
+            {
+                self.selection.end_drag(pos, &self.world, &self.enemy_walls);
+            }
+        }
+
+        if let (true, Some(pos)) = (actions.just_pressed(input::ActionCode::MoveSelected), input.mouse_world())
+        {
+            if self.replay.locks_world()
+            {
+                println!("can't move the selection: {}", ReplayMode::WORLD_LOCKED);
+            }
+            else
+            {
+                self.selection.move_to(pos, &mut self.world, &mut self.enemy_walls);
+            }
+        }
+
+        if actions.just_pressed(input::ActionCode::DeleteSelected)
+        {
+            if self.replay.locks_world()
+            {
+                println!("can't delete the selection: {}", ReplayMode::WORLD_LOCKED);
+            }
+            else
+            {
+                let deleted = self.selection.delete(&mut self.world, &mut self.enemy_walls);
+                println!("deleted {deleted} entities");
+            }
+        }
+
+        if actions.just_pressed(input::ActionCode::ToggleKeyBindings)
+        {
+            self.gui_state.key_bindings_shown = !self.gui_state.key_bindings_shown;
+        }
+
+        if actions.just_pressed(input::ActionCode::ToggleEditor)
+        {
+            let layers = self.key_input_state.layers_mut();
+            if layers.pop(LayerId::Editor).is_none()
+            {
+                layers.push(InputLayer::new(LayerId::Editor, ComboToAction::editor()));
+            }
+            println!("editor {}", if layers.contains(LayerId::Editor) { "on" } else { "off" });
+        }
+
+        if actions.just_pressed(input::ActionCode::CenterCamera)
+        {
+            self.world_pos = self.world.cannon.position - Vec2::new(MainState::WINDOW_X, MainState::WINDOW_Y) / 32.0;
+        }
+
+        // update world pos
+        let mut apply_movements = Vec2::ZERO;
+        {
+            use input::ActionCode::*;
+            [CameraUp, CameraDown, CameraLeft, CameraRight]
+            .into_iter()
+            .for_each(|ac|
+            {
+                if actions.is_held(ac)
+                {
+                    // pan slowly for fine adjustment, then speed up once held for a while.
+                    // a partly pushed stick pans proportionally slower
+                    let speed = 
+                        if actions.held_ticks(ac) > MainState::CAMERA_PAN_RAMP_TICKS { 2.0 } 
+                        else { 1.0 };
+                    let speed = speed * actions.value(ac);
+
+                    match ac
+                    {
+                        CameraUp => apply_movements.y -= speed,
+                        CameraDown => apply_movements.y += speed,
+                        CameraLeft => apply_movements.x -= speed,
+                        CameraRight => apply_movements.x += speed,
+                        _ => (),
+                    };
+                }
+            });
+        }
 
-        let q = graphics::Quad;
+        self.world_pos += apply_movements * MainState::CAMERA_PAN_SPEED * MainState::FIXED_PHYSICS_TIMESTEP;
 
-        canvas.draw(&q, graphics::DrawParam::new().scale([Self::WINDOW_X, Self::WINDOW_Y]));
         Ok(())
     }
-}
 
+    fn start_recording(&mut self)
+    {
+        self.world.events.start_log();
+        self.replay = ReplayMode::Recording(Recording::new(&self.world));
+        println!("Recording");
+    }
+
+    /// Stop recording and save the recording as the last replay
+    fn stop_recording(&mut self, context: &ggez::Context)
+    {
+        let ReplayMode::Recording(mut recording) = std::mem::take(&mut self.replay) else { return };
+        recording.finish(&mut self.world);
+
+        let path = Recording::last_path(context);
+        match recording.save(&path)
+        {
+            Ok(()) => println!("Saved {} ticks and {} events of replay to {}", recording.len(), recording.events().len(), path.display()),
+            Err(e) => println!("Could not save replay to {}: {e}", path.display()),
+        }
+    }
+
+    /// Rewind the world to the start of the last saved replay and play it back
+    fn play_last_replay(&mut self, context: &ggez::Context)
+    {
+        let path = Recording::last_path(context);
+        match Recording::load(&path)
+        {
+            Ok(recording) =>
+            {
+                // a recording made before the simulation changed no longer plays out the same
+                if let Err(e) = recording.verify()
+                {
+                    println!("warning: {e}");
+                }
+
+                self.world = recording.start().clone();
+                self.world.events.start_log();
+                self.replay = ReplayMode::Playing(Playback::new(recording));
+            },
+            Err(e) => println!("Could not load replay from {}: {e}", path.display()),
+        }
+    }
+}
 
 impl Draw<ggegui::Gui> for MainState
 {
@@ -220,79 +657,58 @@ impl ggez::event::EventHandler for MainState
 {
     fn update(&mut self, context: &mut ggez::Context) -> ggez::GameResult {
         // fixed-update
-        while context.time.check_update_time(MainState::FIXED_PHYSICS_FRAMERATE)
+        let mut ticks = 0;
+        // ticks always simulate the same time step, so slowing time runs fewer of them
+        let tick_rate = (MainState::FIXED_PHYSICS_FRAMERATE as f32 * self.time_scale).round().max(1.0) as u32;
+        while context.time.check_update_time(tick_rate)
         {
-            // if context.time.ticks() % 200 == 0
-            {
-                println!("{:?}", self.key_input_state.held_actions);
-            }
-
-            // check debug state
-            if self.key_input_state.held_actions.contains(&input::ActionCode::FlipDebugHitboxes)
+            ticks += 1;
+            let input = self.next_tick_input(context);
+            Schedule::run_input(self, &input)?;
+            self.world.step(&input)?;
+            self.post_process.after_tick(&self.world.events);
+
+            // a replay's choices are not the player's preferences
+            if self.world.cannon.aim != self.settings.aim_mode && !matches!(self.replay, ReplayMode::Playing(_))
             {
-                self.debug_state.draw_hitboxes = !self.debug_state.draw_hitboxes;
-            }
+                self.settings.aim_mode = self.world.cannon.aim;
+                println!("Aiming with {:?}", self.settings.aim_mode);
 
-            // update world pos
-            let mut apply_movements = Vec2::ZERO;
-            {
-                use input::ActionCode::*;
-                [CameraUp, CameraDown, CameraLeft, CameraRight]
-                .into_iter()
-                .for_each(|ac|
+                let path = Settings::config_path(context);
+                if let Err(e) = self.settings.save(&path)
                 {
-                    if self.key_input_state.held_actions.contains(&ac)
-                    {
-                        match ac
-                        {
-                            CameraUp => apply_movements.y -= 1.0,
-                            CameraDown => apply_movements.y += 1.0,
-                            CameraLeft => apply_movements.x -= 1.0,
-                            CameraRight => apply_movements.x += 1.0,
-                            _ => (),
-                        };
-                    }
-                });
+                    println!("Could not save settings to {}: {e}", path.display());
+                }
             }
-
-            self.world_pos += apply_movements;
-
-            FixedUpdate::<Cannon>::fixed_update(self, context)?;
-            FixedUpdate::<Vec<Chunk>>::fixed_update(self, context)?;
-            FixedUpdate::<HashMapTracker<Missile>>::fixed_update(self, context)?;
-
-            self.key_input_state.held_actions.clear();
         }
         
-        Update::<ggegui::Gui>::update(self, context)?;
-        Update::<PeriscopeUniform>::update(self, context)?;
+        self.debug_state.record_frame(context.time.delta(), ticks);
+        Schedule::run_update(self, context)?;
 
         Ok(())
     }
 
     fn draw(&mut self, context: &mut ggez::Context) -> ggez::GameResult {
-        // Our drawing is quite simple.
-        // Just clear the screen...
+        // Draw the world into the offscreen screen image...
         use ggez::graphics::{self, Color};
         let mut canvas = 
-            graphics::Canvas::from_frame(context, Color::WHITE);
+            graphics::Canvas::from_screen_image(context, &mut self.screen, Color::WHITE);
 
         // pixel scaling, nearest-neighbor
         canvas.set_sampler(graphics::Sampler::nearest_clamp());
 
-        Draw::<Cannon>::draw(self, context, &mut canvas)?;
-        Draw::<HashMapTracker<Missile>>::draw(self, context, &mut canvas)?;
+        Schedule::run_draw(self, context, &mut canvas)?;
 
-        Draw::<HashMapTracker<EnemyWall>>::draw(self, context, &mut canvas)?;
-
-        Draw::<Vec<Chunk>>::draw(self, context, &mut canvas)?;
+        canvas.finish(context)?;
 
-        Draw::<Vec<Enemy>>::draw(self, context, &mut canvas)?;
+        // ...run the post effects over it, then present with the gui on top
+        let processed = self.post_process.run(context, &mut self.screen)?;
 
-        Draw::<ggegui::Gui>::draw(self, context, &mut canvas)?;
+        let mut canvas = 
+            graphics::Canvas::from_frame(context, Color::BLACK);
+        canvas.draw(&processed, graphics::DrawParam::new());
 
-        // post effects
-        // Draw::<PeriscopeUniform>::draw(self, context, &mut canvas)?;
+        Schedule::run_post(self, context, &mut canvas)?;
         
         canvas.finish(context)?;
         
@@ -325,6 +741,11 @@ impl ggez::event::EventHandler for MainState
         self.key_input_state.key_up_event(context, input)
     }
 
+    fn focus_event(&mut self, context: &mut ggez::Context, gained: bool) -> ggez::GameResult
+    {
+        self.key_input_state.focus_event(context, gained)
+    }
+
     fn mouse_button_down_event(
             &mut self,
             context: &mut ggez::Context,
@@ -346,21 +767,36 @@ impl ggez::event::EventHandler for MainState
     {
         self.key_input_state.mouse_button_up_event(context, button, x, y)
     }
-}
 
-#[derive(Clone, crevice::std140::AsStd140)]
-struct PeriscopeUniform
-{
-    position: Vector2<f32>,
-    width: f32
-}
+    fn gamepad_button_down_event(
+            &mut self,
+            context: &mut ggez::Context,
+            button: ggez::input::gamepad::gilrs::Button,
+            id: ggez::input::gamepad::GamepadId,
+        ) -> ggez::GameResult
+    {
+        self.key_input_state.gamepad_button_down_event(context, button, id)
+    }
 
-impl PeriscopeUniform
-{
-    fn new(position: impl Into<Vector2<f32>>, width: f32) -> Self
+    fn gamepad_button_up_event(
+            &mut self,
+            context: &mut ggez::Context,
+            button: ggez::input::gamepad::gilrs::Button,
+            id: ggez::input::gamepad::GamepadId,
+        ) -> ggez::GameResult
+    {
+        self.key_input_state.gamepad_button_up_event(context, button, id)
+    }
+
+    fn gamepad_axis_event(
+            &mut self,
+            context: &mut ggez::Context,
+            axis: ggez::input::gamepad::gilrs::Axis,
+            value: f32,
+            id: ggez::input::gamepad::GamepadId,
+        ) -> ggez::GameResult
     {
-        let position = position.into();
-        PeriscopeUniform { position, width }
+        self.key_input_state.gamepad_axis_event(context, axis, value, id)
     }
 }
 
@@ -0,0 +1,349 @@
+use std::any::Any;
+
+use ggez::{graphics, mint::{Vector2, Vector4}};
+
+// local imports
+use crate::{events::{EnemyDamaged, Events}, game_object::Update};
+
+/// A single full-screen fragment-shader pass in a `PostProcessChain`.
+///
+/// The previous pass's output (or the rendered world, for the first pass) is
+/// drawn through this pass's shader, so the shader can sample it as the usual
+/// `@group(1)` texture `t` with sampler `s`. Its uniforms live at `@group(3) @binding(0)`.
+pub trait PostPass
+{
+    fn name(&self) -> &str;
+    fn is_enabled(&self) -> bool;
+    fn set_enabled(&mut self, enabled: bool);
+
+    /// Bind this pass's shader and current uniforms to `canvas`.
+    fn bind(&mut self, context: &mut ggez::Context, canvas: &mut graphics::Canvas);
+
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+/// A post-processing pass driven by a wgsl fragment shader and a uniform struct `U`.
+pub struct ShaderPass<U: crevice::std140::AsStd140>
+{
+    name: String,
+    enabled: bool,
+    shader: graphics::Shader,
+    pub uniforms: U,
+    params: Option<graphics::ShaderParams<U>>,
+}
+
+impl<U: crevice::std140::AsStd140> ShaderPass<U>
+{
+    /// Build a pass from the fragment shader found at resource path `fragment_path`.
+    pub fn new(context: &mut ggez::Context, name: &str, fragment_path: &str, uniforms: U) -> ggez::GameResult<Self>
+    {
+        let shader = graphics::ShaderBuilder::new().fragment_path(fragment_path).build(context)?;
+
+        Ok(
+            Self
+            {
+                name: name.to_owned(),
+                enabled: true,
+                shader,
+                uniforms,
+                params: None,
+            }
+        )
+    }
+
+    pub fn enabled(mut self, enabled: bool) -> Self
+    {
+        self.enabled = enabled;
+        self
+    }
+}
+
+impl<U: crevice::std140::AsStd140 + 'static> PostPass for ShaderPass<U>
+{
+    fn name(&self) -> &str
+    {
+        &self.name
+    }
+
+    fn is_enabled(&self) -> bool
+    {
+        self.enabled
+    }
+
+    fn set_enabled(&mut self, enabled: bool)
+    {
+        self.enabled = enabled;
+    }
+
+    fn bind(&mut self, context: &mut ggez::Context, canvas: &mut graphics::Canvas)
+    {
+        // build the uniform buffer once, then only re-upload values
+        let params = match self.params
+        {
+            Some(ref mut params) =>
+            {
+                params.set_uniforms(context, &self.uniforms);
+                params
+            },
+            None => self.params.insert(graphics::ShaderParamsBuilder::new(&self.uniforms).build(context)),
+        };
+
+        canvas.set_shader(&self.shader);
+        canvas.set_shader_params(params);
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any
+    {
+        self
+    }
+}
+
+/// An ordered list of post-processing passes run over the rendered world
+/// before it is presented.
+///
+/// Passes ping-pong between the world's `ScreenImage` and a second
+/// swap image owned by the chain, so no pass reads the image it writes.
+pub struct PostProcessChain
+{
+    passes: Vec<Box<dyn PostPass>>,
+    swap: graphics::ScreenImage,
+}
+
+impl PostProcessChain
+{
+    pub fn new(context: &mut ggez::Context) -> Self
+    {
+        let swap =
+            graphics::ScreenImage::new(
+                context,
+                graphics::ImageFormat::Rgba8UnormSrgb,
+                1.0, 1.0, 1
+            );
+
+        Self { passes: Vec::new(), swap }
+    }
+
+    /// The default chain: periscope darkening (off), vignette, color grading, damage flash.
+    pub fn with_default_passes(context: &mut ggez::Context) -> ggez::GameResult<Self>
+    {
+        let mut chain = Self::new(context);
+
+        chain.push(ShaderPass::new(context, PeriscopeUniform::PASS, "/periscope.wgsl", PeriscopeUniform::new([0.0, 0.0], 0.5))?.enabled(false));
+        chain.push(ShaderPass::new(context, VignetteUniform::PASS, "/vignette.wgsl", VignetteUniform::default())?);
+        chain.push(ShaderPass::new(context, ColorGradeUniform::PASS, "/color_grade.wgsl", ColorGradeUniform::default())?);
+        chain.push(ShaderPass::new(context, DamageFlashUniform::PASS, "/shader_a.wgsl", DamageFlashUniform::default())?);
+
+        Ok(chain)
+    }
+
+    /// Append a pass to the end of the chain.
+    pub fn push(&mut self, pass: impl PostPass + 'static)
+    {
+        self.passes.push(Box::new(pass));
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut (dyn PostPass + 'static)>
+    {
+        self.passes.iter_mut().find(|p| p.name() == name).map(|p| p.as_mut())
+    }
+
+    /// The uniforms of the pass called `name`, if it is a `ShaderPass<U>`.
+    pub fn uniforms_mut<U>(&mut self, name: &str) -> Option<&mut U>
+    where
+        U: crevice::std140::AsStd140 + 'static
+    {
+        self.get_mut(name)?
+        .as_any_mut()
+        .downcast_mut::<ShaderPass<U>>()
+        .map(|p| &mut p.uniforms)
+    }
+
+    /// Every pass, in the order they run.
+    pub fn passes_mut(&mut self) -> impl Iterator<Item = &mut (dyn PostPass + 'static)>
+    {
+        self.passes.iter_mut().map(|p| p.as_mut())
+    }
+
+    /// React to what happened during the fixed tick that sent `events`
+    pub fn after_tick(&mut self, events: &Events)
+    {
+        if let Some(flash) = self.uniforms_mut::<DamageFlashUniform>(DamageFlashUniform::PASS)
+        {
+            flash.hit(events.current::<EnemyDamaged>());
+        }
+    }
+
+    /// Run every enabled pass over the image in `screen`, returning the final image.
+    pub fn run(&mut self, context: &mut ggez::Context, screen: &mut graphics::ScreenImage) -> ggez::GameResult<graphics::Image>
+    {
+        let mut source = screen.image(context);
+        let mut target = self.swap.image(context);
+
+        for pass in self.passes.iter_mut().filter(|p| p.is_enabled())
+        {
+            let mut canvas = graphics::Canvas::from_image(context, target.clone(), graphics::Color::BLACK);
+            pass.bind(context, &mut canvas);
+            canvas.draw(&source, graphics::DrawParam::new());
+            canvas.finish(context)?;
+
+            std::mem::swap(&mut source, &mut target);
+        }
+
+        Ok(source)
+    }
+}
+
+/// Darkens everything outside a circle around `position` (in screen pixels).
+#[derive(Clone, crevice::std140::AsStd140)]
+pub struct PeriscopeUniform
+{
+    pub position: Vector2<f32>,
+    pub width: f32
+}
+
+impl PeriscopeUniform
+{
+    pub const PASS: &'static str = "periscope";
+
+    pub fn new(position: impl Into<Vector2<f32>>, width: f32) -> Self
+    {
+        let position = position.into();
+        PeriscopeUniform { position, width }
+    }
+}
+
+/// Darkens the edges of the screen. `radius` is where darkening begins,
+/// as a fraction of the distance from center to corner.
+#[derive(Clone, crevice::std140::AsStd140)]
+pub struct VignetteUniform
+{
+    pub strength: f32,
+    pub radius: f32,
+}
+
+impl VignetteUniform
+{
+    pub const PASS: &'static str = "vignette";
+}
+
+impl Default for VignetteUniform
+{
+    fn default() -> Self
+    {
+        Self { strength: 0.35, radius: 0.75 }
+    }
+}
+
+/// Multiplies by `tint`, then applies saturation, contrast and brightness.
+/// The default is the identity grade.
+#[derive(Clone, crevice::std140::AsStd140)]
+pub struct ColorGradeUniform
+{
+    pub tint: Vector4<f32>,
+    pub saturation: f32,
+    pub contrast: f32,
+    pub brightness: f32,
+}
+
+impl ColorGradeUniform
+{
+    pub const PASS: &'static str = "color_grade";
+}
+
+impl Default for ColorGradeUniform
+{
+    fn default() -> Self
+    {
+        Self { tint: [1.0, 1.0, 1.0, 1.0].into(), saturation: 1.0, contrast: 1.0, brightness: 0.0 }
+    }
+}
+
+/// Blends the screen toward `color.rgb` by `color.a`.
+///
+/// Each hit on an enemy raises the alpha, which fades back to zero on its own.
+#[derive(Clone, crevice::std140::AsStd140)]
+pub struct DamageFlashUniform
+{
+    pub color: Vector4<f32>,
+}
+
+impl DamageFlashUniform
+{
+    pub const PASS: &'static str = "damage_flash";
+
+    /// Flash alpha lost per second
+    const FADE_PER_SEC: f32 = 2.0;
+    /// Flash alpha gained per point of damage, up to `MAX_ALPHA`
+    const ALPHA_PER_DAMAGE: f32 = 0.05;
+    const MAX_ALPHA: f32 = 0.5;
+
+    /// Flash for the damage dealt in a fixed tick
+    pub fn hit(&mut self, damaged: &[EnemyDamaged])
+    {
+        let damage: i32 = damaged.iter().map(|d| d.amount.max(0)).sum();
+        self.color.w = (self.color.w + Self::ALPHA_PER_DAMAGE * damage as f32).min(Self::MAX_ALPHA);
+    }
+
+    fn fade(&mut self, dt: f32)
+    {
+        self.color.w = (self.color.w - Self::FADE_PER_SEC * dt).max(0.0);
+    }
+}
+
+impl Default for DamageFlashUniform
+{
+    fn default() -> Self
+    {
+        Self { color: [1.0, 0.0, 0.0, 0.0].into() }
+    }
+}
+
+impl Update<PostProcessChain> for crate::MainState
+{
+    fn update(&mut self, context: &mut ggez::Context) -> ggez::GameResult
+    {
+        if let Some(ps) = self.post_process.uniforms_mut::<PeriscopeUniform>(PeriscopeUniform::PASS)
+        {
+            ps.position = context.mouse.position().into();
+        }
+
+        if let Some(flash) = self.post_process.uniforms_mut::<DamageFlashUniform>(DamageFlashUniform::PASS)
+        {
+            flash.fade(context.time.delta().as_secs_f32());
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+    use crate::{game_object::{collider_type::Collider, enemy::Enemy, missile::Missile, HasPosition, HasRegion}, world::{TickInput, World}};
+    use ggez::glam::Vec2;
+
+    #[test]
+    fn hitting_an_enemy_flashes_then_fades()
+    {
+        let mut world = World::new(0);
+        world.enemies = [HasRegion::<Collider>::region_set(Enemy::default().position_set((4.0, 0.5).into()), ((-0.5, -0.5), (0.5, 0.5)).into())].into_iter().collect();
+        world.missiles.push(Missile::new(Vec2::new(0.5, 0.5), Vec2::X * 30.0));
+
+        let mut flash = DamageFlashUniform::default();
+        for _ in 0..20
+        {
+            world.step(&TickInput::default()).unwrap();
+            flash.hit(world.events.current::<EnemyDamaged>());
+        }
+        assert!(flash.color.w > 0.0);
+        assert!(flash.color.w <= DamageFlashUniform::MAX_ALPHA);
+
+        flash.fade(1.0);
+        assert_eq!(flash.color.w, 0.0);
+
+        // a quiet tick doesn't flash
+        flash.hit(&[]);
+        assert_eq!(flash.color.w, 0.0);
+    }
+}
@@ -0,0 +1,326 @@
+use std::path::{Path, PathBuf};
+
+use ggez::{GameError, GameResult};
+use serde::{Deserialize, Serialize};
+
+// local imports
+use crate::{events::LoggedEvent, world::{TickInput, World}};
+
+/// File name of the most recent recording, inside the user data directory
+pub const LAST_REPLAY_FILE: &str = "last.replay";
+
+/// A recorded play session: the world it started from, seed included,
+/// and the input of every fixed tick since.
+///
+/// The simulation is deterministic given those, so playing the inputs back
+/// over the starting world reproduces the session exactly. A finished
+/// recording also keeps a checksum of the world it ended on, which makes it
+/// a regression test: `verify` fails if the simulation has since changed.
+/// The events sent while recording are kept too, to tell when it changed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Recording
+{
+    start: World,
+    ticks: Vec<TickInput>,
+    /// `World::checksum` of the world after the last tick, once finished
+    end_checksum: Option<u64>,
+    /// The events sent while recording, if the world was logging them
+    events: Vec<LoggedEvent>,
+}
+
+impl Recording
+{
+    pub fn new(start: &World) -> Self
+    {
+        Self { start: start.clone(), ticks: Vec::new(), end_checksum: None, events: Vec::new() }
+    }
+
+    pub fn push(&mut self, input: TickInput)
+    {
+        self.ticks.push(input);
+    }
+
+    /// Stop recording, remembering the world it ended on and the events it logged on the way
+    pub fn finish(&mut self, end: &mut World)
+    {
+        self.end_checksum = Some(end.checksum());
+        self.events = end.events.take_log();
+    }
+
+    pub fn start(&self) -> &World
+    {
+        &self.start
+    }
+
+    pub fn len(&self) -> usize
+    {
+        self.ticks.len()
+    }
+
+    pub fn events(&self) -> &[LoggedEvent]
+    {
+        &self.events
+    }
+
+    /// Where the most recent recording is stored for this user
+    pub fn last_path(context: &ggez::Context) -> PathBuf
+    {
+        context.fs.user_data_dir().join(LAST_REPLAY_FILE)
+    }
+
+    pub fn to_bytes(&self) -> GameResult<Vec<u8>>
+    {
+        postcard::to_allocvec(self)
+        .map_err(|e| GameError::CustomError(format!("could not serialize replay: {e}")))
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> GameResult<Self>
+    {
+        postcard::from_bytes(bytes)
+        .map_err(|e| GameError::CustomError(format!("malformed replay: {e}")))
+    }
+
+    pub fn save(&self, path: &Path) -> GameResult
+    {
+        if let Some(dir) = path.parent()
+        {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        std::fs::write(path, self.to_bytes()?)?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> GameResult<Self>
+    {
+        Self::from_bytes(&std::fs::read(path)?)
+    }
+
+    /// Step a copy of the starting world through every recorded tick, logging its events
+    pub fn play_out(&self) -> GameResult<World>
+    {
+        let mut world = self.start.clone();
+        world.events.start_log();
+        for input in &self.ticks
+        {
+            world.step(input)?;
+        }
+
+        Ok(world)
+    }
+
+    /// Check `end` is the world this recording finished on.
+    /// Unfinished recordings match anything.
+    pub fn check_end(&self, end: &World) -> GameResult
+    {
+        match self.end_checksum
+        {
+            Some(expected) if expected != end.checksum() =>
+            {
+                let mut message = format!("replay diverged: world checksum is {:#x}, recorded {expected:#x}", end.checksum());
+                if let Some(tick) = self.first_divergent_event(end.events.log())
+                {
+                    message += &format!(", events first differ at tick {tick}");
+                }
+                Err(GameError::CustomError(message))
+            },
+            _ => Ok(()),
+        }
+    }
+
+    /// The tick of the first event that differs between the recorded events and `played`
+    fn first_divergent_event(&self, played: &[LoggedEvent]) -> Option<u64>
+    {
+        // events don't compare, but the same events serialize the same
+        let bytes = |logged: &LoggedEvent| postcard::to_allocvec(logged).ok();
+
+        match self.events.iter().zip(played).find(|(recorded, played)| bytes(recorded) != bytes(played))
+        {
+            Some((recorded, played)) => Some(recorded.tick.min(played.tick)),
+            // one is longer, so diverges where the other ran out of events
+            None => self.events.get(played.len()).or(played.get(self.events.len())).map(|logged| logged.tick),
+        }
+    }
+
+    /// Play the recording out and check it ends on the recorded world
+    pub fn verify(&self) -> GameResult<World>
+    {
+        let end = self.play_out()?;
+        self.check_end(&end)?;
+        Ok(end)
+    }
+}
+
+/// Feeds a recording back into the game one fixed tick at a time.
+#[derive(Debug)]
+pub struct Playback
+{
+    recording: Recording,
+    next: usize,
+}
+
+impl Playback
+{
+    pub fn new(recording: Recording) -> Self
+    {
+        Self { recording, next: 0 }
+    }
+
+    pub fn recording(&self) -> &Recording
+    {
+        &self.recording
+    }
+
+    /// The input for the next tick, or `None` once every tick has been played
+    pub fn next_input(&mut self) -> Option<TickInput>
+    {
+        let input = self.recording.ticks.get(self.next)?.clone();
+        self.next += 1;
+        Some(input)
+    }
+}
+
+/// Whether the game is playing live, recording, or playing back a recording
+#[derive(Debug, Default)]
+pub enum ReplayMode
+{
+    #[default]
+    Live,
+    Recording(Recording),
+    Playing(Playback),
+}
+
+impl ReplayMode
+{
+    /// Why the world can't be changed outside its fixed ticks now
+    pub const WORLD_LOCKED: &'static str = "the world only changes through its ticks while a replay is recording or playing";
+
+    /// Whether the world may only change through its fixed ticks, since a replay
+    /// only repeats those and would drift from what was played otherwise
+    pub fn locks_world(&self) -> bool
+    {
+        !matches!(self, ReplayMode::Live)
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use ggez::glam::Vec2;
+
+    use super::*;
+    use crate::{game_object::grid::{ObjectType, Chunk}, input::{action_state::ActionState, ActionCode}};
+
+    /// The actions of a tick in which `held` are all held
+    fn holding(held: &[ActionCode]) -> ActionState
+    {
+        let mut actions = ActionState::default();
+        actions.tap(held);
+        actions.begin_tick();
+        actions
+    }
+
+    /// A short scripted session: turn left while firing, then click a tile
+    fn scripted_session() -> Recording
+    {
+        let mut world = World::new(7);
+        world.events.start_log();
+        let mut recording = Recording::new(&world);
+
+        for tick in 0..90
+        {
+            let input = match tick
+            {
+                0..=59 => TickInput::new(holding(&[ActionCode::TurnLeft, ActionCode::Shoot]), None),
+                80.. => TickInput::new(holding(&[ActionCode::Click]), Some(Vec2::new(3.5, 2.5))),
+                _ => TickInput::default(),
+            };
+            world.step(&input).unwrap();
+            recording.push(input);
+        }
+
+        recording.finish(&mut world);
+        recording
+    }
+
+    #[test]
+    fn replay_reproduces_the_session()
+    {
+        let recording = scripted_session();
+        let end = recording.verify().unwrap();
+
+        assert_eq!(end.tick, 90);
+        assert!(!end.missiles.is_empty());
+        // turning left rotates the cannon off of its starting facing
+        assert!(end.cannon.facing.angle_between(Vec2::X).abs() > 0.1);
+        let cell = Chunk::object_at_world_cell(&end.chunks, (3, 2).into()).unwrap();
+        assert!(matches!(cell.foreground_object, ObjectType::Filled));
+    }
+
+    #[test]
+    fn replay_survives_the_file_format()
+    {
+        let recording = scripted_session();
+        let bytes = recording.to_bytes().unwrap();
+
+        let loaded = Recording::from_bytes(&bytes).unwrap();
+        assert_eq!(loaded.len(), recording.len());
+        assert_eq!(loaded.start().checksum(), recording.start().checksum());
+        assert_eq!(loaded.verify().unwrap().checksum(), recording.verify().unwrap().checksum());
+    }
+
+    #[test]
+    fn replay_keeps_the_disabled_systems()
+    {
+        let mut world = World::new(7);
+        world.systems.systems_mut().find(|s| s.name() == "Cannon").unwrap().set_enabled(false);
+        let mut recording = Recording::new(&world);
+        for _ in 0..30
+        {
+            let input = TickInput::new(holding(&[ActionCode::TurnLeft]), None);
+            world.step(&input).unwrap();
+            recording.push(input);
+        }
+        recording.finish(&mut world);
+
+        // the cannon stays put, whether played from memory or from a file
+        let loaded = Recording::from_bytes(&recording.to_bytes().unwrap()).unwrap();
+        for played in [recording.verify().unwrap(), loaded.verify().unwrap()]
+        {
+            assert_eq!(played.cannon.facing, World::new(7).cannon.facing);
+        }
+    }
+
+    #[test]
+    fn diverging_replay_fails_verification()
+    {
+        let mut recording = scripted_session();
+        recording.ticks.truncate(30);
+
+        assert!(recording.verify().is_err());
+    }
+
+    #[test]
+    fn diverging_replay_reports_where_events_differ()
+    {
+        let mut recording = scripted_session();
+        assert!(recording.events().iter().any(|logged| matches!(logged.event, crate::events::GameEvent::MissileFired(_))));
+
+        // the cannon stops firing and turning partway through
+        for input in &mut recording.ticks[40..60]
+        {
+            *input = TickInput::default();
+        }
+
+        let Err(GameError::CustomError(message)) = recording.verify() else { panic!("the replay should diverge") };
+        let tick = recording.first_divergent_event(recording.play_out().unwrap().events.log()).unwrap();
+        assert!((40..60).contains(&tick));
+        assert!(message.contains(&format!("events first differ at tick {tick}")));
+    }
+
+    #[test]
+    fn malformed_replay_is_an_error()
+    {
+        assert!(Recording::from_bytes(&[1, 2, 3]).is_err());
+    }
+}
@@ -0,0 +1,441 @@
+use std::time::{Duration, Instant};
+
+use ggez::{graphics::Canvas, Context, GameError, GameResult};
+
+// local imports
+use crate::{world::{TickInput, World}, MainState};
+
+/// When in a frame a system runs. Stages run in this order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Stage
+{
+    /// Every fixed tick, before the world steps, reacting to the tick's input
+    Input,
+    /// Every fixed tick, stepping the `World`
+    Fixed,
+    /// Every frame
+    Update,
+    /// Every frame, drawing the world before it is post-processed
+    Draw,
+    /// Every frame, drawing over the post-processed frame
+    Post,
+}
+
+/// What a system runs, which also decides its stage
+#[derive(Debug, Clone, Copy)]
+pub enum Run
+{
+    Input(fn(&mut MainState, &TickInput) -> GameResult),
+    Fixed(fn(&mut World, &TickInput) -> GameResult),
+    Update(fn(&mut MainState, &mut Context) -> GameResult),
+    Draw(fn(&MainState, &mut Context, &mut Canvas) -> GameResult),
+    Post(fn(&MainState, &mut Context, &mut Canvas) -> GameResult),
+}
+
+impl Run
+{
+    pub fn stage(&self) -> Stage
+    {
+        match self
+        {
+            Run::Input(_) => Stage::Input,
+            Run::Fixed(_) => Stage::Fixed,
+            Run::Update(_) => Stage::Update,
+            Run::Draw(_) => Stage::Draw,
+            Run::Post(_) => Stage::Post,
+        }
+    }
+}
+
+/// A named step of the game loop, with the systems of its stage it must run after or before
+#[derive(Debug, Clone)]
+pub struct System
+{
+    name: &'static str,
+    run: Run,
+    after: Vec<&'static str>,
+    before: Vec<&'static str>,
+    enabled: bool,
+
+    last_run: Duration,
+    total_run: Duration,
+    runs: u64,
+}
+
+impl System
+{
+    pub fn new(name: &'static str, run: Run) -> Self
+    {
+        Self
+        {
+            name,
+            run,
+            after: Vec::new(),
+            before: Vec::new(),
+            enabled: true,
+            last_run: Duration::ZERO,
+            total_run: Duration::ZERO,
+            runs: 0,
+        }
+    }
+
+    /// Run after the system called `name`
+    pub fn after(mut self, name: &'static str) -> Self
+    {
+        self.after.push(name);
+        self
+    }
+
+    /// Run before the system called `name`
+    pub fn before(mut self, name: &'static str) -> Self
+    {
+        self.before.push(name);
+        self
+    }
+
+    pub fn name(&self) -> &'static str
+    {
+        self.name
+    }
+
+    pub fn stage(&self) -> Stage
+    {
+        self.run.stage()
+    }
+
+    pub fn is_enabled(&self) -> bool
+    {
+        self.enabled
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool)
+    {
+        self.enabled = enabled;
+    }
+
+    /// How long the most recent run took
+    pub fn last_run(&self) -> Duration
+    {
+        self.last_run
+    }
+
+    /// How long a run takes on average, over every run so far
+    pub fn average_run(&self) -> Duration
+    {
+        if self.runs == 0 { Duration::ZERO } else { self.total_run / self.runs as u32 }
+    }
+}
+
+/// A system running `FixedUpdate<$t>` on the `World`, named after `$t`
+macro_rules! fixed_system
+{
+    ($t:ty) =>
+    {
+        $crate::schedule::System::new(stringify!($t), $crate::schedule::Run::Fixed(<$crate::World as $crate::FixedUpdate<$t>>::fixed_update))
+    };
+}
+pub(crate) use fixed_system;
+
+/// A system running `Update<$t>` on the `MainState`, named after `$t`
+macro_rules! update_system
+{
+    ($t:ty) =>
+    {
+        $crate::schedule::System::new(stringify!($t), $crate::schedule::Run::Update(<$crate::MainState as $crate::Update<$t>>::update))
+    };
+}
+pub(crate) use update_system;
+
+/// A system running `Draw<$t>` on the `MainState` in the `Draw` or `Post` stage, named after `$t`
+macro_rules! draw_system
+{
+    ($stage:ident, $t:ty) =>
+    {
+        $crate::schedule::System::new(stringify!($t), $crate::schedule::Run::$stage(<$crate::MainState as $crate::Draw<$t>>::draw))
+    };
+}
+pub(crate) use draw_system;
+
+/// The systems of a game loop, kept in the order they run.
+///
+/// Systems run by stage, then in the order they were added, except where
+/// moved to satisfy their `after` and `before` constraints.
+#[derive(Debug, Clone, Default)]
+pub struct Schedule
+{
+    systems: Vec<System>,
+}
+
+impl Schedule
+{
+    /// Order `systems`, failing if names repeat within a stage, or the constraints name a system
+    /// not in the same stage, or can't all be satisfied
+    pub fn new(systems: Vec<System>) -> GameResult<Self>
+    {
+        let error = |e: String| GameError::CustomError(format!("bad system schedule: {e}"));
+
+        for (i, system) in systems.iter().enumerate()
+        {
+            if systems[..i].iter().any(|s| s.name == system.name && s.stage() == system.stage())
+            {
+                return Err(error(format!("{} is added twice to {:?}", system.name, system.stage())));
+            }
+            for other in system.after.iter().chain(&system.before)
+            {
+                if !systems.iter().any(|s| s.name == *other && s.stage() == system.stage())
+                {
+                    return Err(error(format!("{} is ordered against {other}, which is not in its stage", system.name)));
+                }
+            }
+        }
+
+        // whether system `a` has to run before system `b`
+        let precedes = |a: &System, b: &System| b.after.contains(&a.name) || a.before.contains(&b.name);
+
+        // repeatedly take the first system in stage and insertion order that nothing left must precede
+        let mut left: Vec<System> = systems;
+        left.sort_by_key(System::stage);
+        let mut ordered = Vec::with_capacity(left.len());
+        while !left.is_empty()
+        {
+            let Some(next) = (0..left.len()).find(|&i| !left.iter().any(|other| precedes(other, &left[i])))
+            else
+            {
+                let names: Vec<_> = left.iter().map(System::name).collect();
+                return Err(error(format!("the order of {names:?} is circular")));
+            };
+            ordered.push(left.remove(next));
+        }
+
+        Ok(Self { systems: ordered })
+    }
+
+    /// Every system, in the order they run
+    pub fn systems(&self) -> impl Iterator<Item = &System>
+    {
+        self.systems.iter()
+    }
+
+    /// Every system, in the order they run
+    pub fn systems_mut(&mut self) -> impl Iterator<Item = &mut System>
+    {
+        self.systems.iter_mut()
+    }
+
+    /// Run the enabled systems of `stage` in the schedule `schedule` finds in `state`, in order,
+    /// timing each one. The schedule is only borrowed between systems, so a running system
+    /// can list and switch it, like the debug overlay does.
+    fn run<S>(state: &mut S, schedule: fn(&mut S) -> &mut Schedule, stage: Stage, mut call: impl FnMut(&mut S, Run) -> GameResult) -> GameResult
+    {
+        let mut i = 0;
+        while let Some(system) = schedule(state).systems.get(i)
+        {
+            i += 1;
+            if !system.enabled || system.stage() != stage
+            {
+                continue;
+            }
+
+            let run = system.run;
+            let start = Instant::now();
+            call(state, run)?;
+            let system = &mut schedule(state).systems[i - 1];
+            system.last_run = start.elapsed();
+            system.total_run += system.last_run;
+            system.runs += 1;
+        }
+
+        Ok(())
+    }
+
+    pub fn run_input(state: &mut MainState, input: &TickInput) -> GameResult
+    {
+        Schedule::run(state, |state| &mut state.systems, Stage::Input, |state, run| match run { Run::Input(f) => f(state, input), _ => Ok(()) })
+    }
+
+    pub fn run_fixed(&mut self, world: &mut World, input: &TickInput) -> GameResult
+    {
+        Schedule::run(self, |schedule| schedule, Stage::Fixed, |_, run| match run { Run::Fixed(f) => f(world, input), _ => Ok(()) })
+    }
+
+    pub fn run_update(state: &mut MainState, context: &mut Context) -> GameResult
+    {
+        Schedule::run(state, |state| &mut state.systems, Stage::Update, |state, run| match run { Run::Update(f) => f(state, context), _ => Ok(()) })
+    }
+
+    pub fn run_draw(state: &mut MainState, context: &mut Context, canvas: &mut Canvas) -> GameResult
+    {
+        Schedule::run(state, |state| &mut state.systems, Stage::Draw, |state, run| match run { Run::Draw(f) => f(state, context, canvas), _ => Ok(()) })
+    }
+
+    pub fn run_post(state: &mut MainState, context: &mut Context, canvas: &mut Canvas) -> GameResult
+    {
+        Schedule::run(state, |state| &mut state.systems, Stage::Post, |state, run| match run { Run::Post(f) => f(state, context, canvas), _ => Ok(()) })
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+
+    fn noop(_: &mut World, _: &TickInput) -> GameResult { Ok(()) }
+
+    fn names(mut schedule: Schedule) -> Vec<&'static str>
+    {
+        schedule.systems_mut().map(|s| s.name()).collect()
+    }
+
+    #[test]
+    fn constraints_reorder_systems()
+    {
+        let schedule = Schedule::new(vec![
+            System::new("a", Run::Fixed(noop)).after("c"),
+            System::new("b", Run::Fixed(noop)),
+            System::new("c", Run::Fixed(noop)),
+            System::new("d", Run::Fixed(noop)).before("b"),
+        ]).unwrap();
+
+        assert_eq!(names(schedule), ["c", "a", "d", "b"]);
+    }
+
+    #[test]
+    fn bad_constraints_are_errors()
+    {
+        let circular = Schedule::new(vec![
+            System::new("a", Run::Fixed(noop)).after("b"),
+            System::new("b", Run::Fixed(noop)).after("a"),
+        ]);
+        assert!(circular.is_err());
+
+        let missing = Schedule::new(vec![System::new("a", Run::Fixed(noop)).after("b")]);
+        assert!(missing.is_err());
+
+        let repeated = Schedule::new(vec![System::new("a", Run::Fixed(noop)), System::new("a", Run::Fixed(noop))]);
+        assert!(repeated.is_err());
+
+        let ordered_against_another_stage = Schedule::new(vec![
+            System::new("a", Run::Fixed(noop)).after("b"),
+            System::new("b", Run::Input(|_, _| Ok(()))),
+        ]);
+        assert!(ordered_against_another_stage.is_err());
+    }
+
+    #[test]
+    fn disabled_systems_do_not_run()
+    {
+        fn count(world: &mut World, _: &TickInput) -> GameResult
+        {
+            world.tick += 1;
+            Ok(())
+        }
+
+        let mut schedule = Schedule::new(vec![System::new("count", Run::Fixed(count))]).unwrap();
+        let mut world = World::new(0);
+        schedule.run_fixed(&mut world, &TickInput::default()).unwrap();
+        schedule.systems_mut().for_each(|s| s.set_enabled(false));
+        schedule.run_fixed(&mut world, &TickInput::default()).unwrap();
+
+        assert_eq!(world.tick, 1);
+    }
+
+    #[test]
+    fn running_systems_can_switch_their_schedule()
+    {
+        struct State
+        {
+            schedule: Schedule,
+            ran: Vec<&'static str>,
+        }
+
+        let mut state = State
+        {
+            schedule: Schedule::new(vec![System::new("a", Run::Fixed(noop)), System::new("b", Run::Fixed(noop))]).unwrap(),
+            ran: Vec::new(),
+        };
+        let run = |state: &mut State| Schedule::run(state, |state| &mut state.schedule, Stage::Fixed, |state, _|
+        {
+            // the first system to run switches the second off
+            state.ran.push(if state.ran.is_empty() { "a" } else { "b" });
+            state.schedule.systems_mut().nth(1).unwrap().set_enabled(false);
+            Ok(())
+        });
+
+        run(&mut state).unwrap();
+        assert_eq!(state.ran, ["a"]);
+        assert_eq!(state.schedule.systems().map(|s| s.runs).collect::<Vec<_>>(), [1, 0]);
+    }
+
+    /// The `FixedUpdate`, `Update` and `Draw` implementations in `src`, as
+    /// (trait, type) pairs with whitespace removed from the type
+    fn implemented_systems() -> Vec<(String, String)>
+    {
+        fn visit(dir: &std::path::Path, found: &mut Vec<(String, String)>)
+        {
+            for entry in std::fs::read_dir(dir).unwrap().map(Result::unwrap)
+            {
+                let path = entry.path();
+                if path.is_dir()
+                {
+                    visit(&path, found);
+                    continue;
+                }
+
+                let source = std::fs::read_to_string(&path).unwrap();
+                for line in source.lines()
+                {
+                    let Some(rest) = line.trim_start().strip_prefix("impl ") else { continue };
+                    let rest = rest.strip_prefix("crate::").unwrap_or(rest);
+                    let Some(open) = rest.find('<') else { continue };
+                    let trait_name = &rest[..open];
+                    if !["FixedUpdate", "Update", "Draw"].contains(&trait_name)
+                    {
+                        continue;
+                    }
+
+                    // the type runs to the `>` closing the trait's generics
+                    let mut depth = 0;
+                    let close = rest.char_indices().skip(open).find(|&(_, c)|
+                    {
+                        match c { '<' => depth += 1, '>' => depth -= 1, _ => () }
+                        depth == 0
+                    }).unwrap().0;
+                    let ty: String = rest[open + 1..close].chars().filter(|c| !c.is_whitespace()).collect();
+                    found.push((trait_name.to_owned(), ty));
+                }
+            }
+        }
+
+        let mut found = Vec::new();
+        visit(&std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("src"), &mut found);
+        found
+    }
+
+    /// A system that is implemented but not registered silently never runs
+    #[test]
+    fn every_system_is_registered()
+    {
+        let mut world = World::systems();
+        let mut main = MainState::systems();
+        let registered: Vec<(Stage, String)> =
+            world.systems_mut().chain(main.systems_mut())
+            .map(|s| (s.stage(), s.name().chars().filter(|c| !c.is_whitespace()).collect()))
+            .collect();
+
+        let implemented = implemented_systems();
+        assert!(implemented.iter().any(|(t, ty)| t == "FixedUpdate" && ty == "Cannon"), "found no systems to check");
+
+        for (trait_name, ty) in implemented
+        {
+            let stages: &[Stage] = match trait_name.as_str()
+            {
+                "FixedUpdate" => &[Stage::Fixed],
+                "Update" => &[Stage::Update],
+                _ => &[Stage::Draw, Stage::Post],
+            };
+            assert!(
+                registered.iter().any(|(stage, name)| stages.contains(stage) && *name == ty),
+                "{trait_name}<{ty}> is implemented but not registered as a system");
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use std::path::{Path, PathBuf};
+
+use ggez::{GameError, GameResult};
+use serde::{Deserialize, Serialize};
+
+// local imports
+use crate::game_object::cannon::AimMode;
+
+/// File name of the settings, inside the user config directory
+pub const SETTINGS_FILE: &str = "settings.toml";
+
+/// Player preferences that outlive a session.
+/// Missing entries take their defaults, so older files keep loading.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings
+{
+    pub aim_mode: AimMode,
+}
+
+impl Settings
+{
+    /// Where the settings are stored for this user
+    pub fn config_path(context: &ggez::Context) -> PathBuf
+    {
+        context.fs.user_config_dir().join(SETTINGS_FILE)
+    }
+
+    pub fn to_toml(&self) -> GameResult<String>
+    {
+        toml::to_string(self)
+        .map_err(|e| GameError::ConfigError(format!("could not serialize settings: {e}")))
+    }
+
+    pub fn from_toml(s: &str) -> GameResult<Self>
+    {
+        toml::from_str(s)
+        .map_err(|e| GameError::ConfigError(format!("malformed settings: {e}")))
+    }
+
+    /// Load the settings at `path`, falling back to the defaults
+    /// (and saying why) if the file is malformed.
+    pub fn load_or_default(path: &Path) -> Self
+    {
+        if !path.exists()
+        {
+            return Self::default();
+        }
+
+        let loaded =
+            std::fs::read_to_string(path)
+            .map_err(|e| GameError::ConfigError(format!("could not read settings from {}: {e}", path.display())))
+            .and_then(|s| Self::from_toml(&s));
+
+        loaded.unwrap_or_else(|e|
+        {
+            println!("{e}; using default settings");
+            Self::default()
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> GameResult
+    {
+        if let Some(dir) = path.parent()
+        {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        std::fs::write(path, self.to_toml()?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test
+{
+    use super::*;
+
+    #[test]
+    fn settings_round_trip()
+    {
+        let settings = Settings { aim_mode: AimMode::Mouse };
+        assert_eq!(Settings::from_toml(&settings.to_toml().unwrap()).unwrap(), settings);
+    }
+
+    #[test]
+    fn missing_entries_are_defaults()
+    {
+        assert_eq!(Settings::from_toml("").unwrap(), Settings::default());
+        assert!(Settings::from_toml("aim_mode = \"Sideways\"").is_err());
+    }
+}
@@ -1,5 +1,7 @@
 use std::{collections::HashMap, ops::{Deref, DerefMut}};
 
+use serde::{Deserialize, Serialize};
+
 // local imports
 
 /// Acts as a tracker for a variety of objects with ID: u16, with a maximum number of elements.
@@ -9,7 +11,7 @@ use std::{collections::HashMap, ops::{Deref, DerefMut}};
 /// 
 /// User can push items that the tracker places in itself as an owner, 
 /// giving them a circular unique ID of the element.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HashMapTracker<I, const MAX: u16 = 1024>
 {
     cur_index: u16,
@@ -81,6 +83,14 @@ where
         }
     }
 
+    /// The items with their ids, in id order, which unlike the hash map's order is the same every run
+    pub fn by_id(&self) -> impl Iterator<Item = (u16, &I)>
+    {
+        let mut ids: Vec<u16> = self.tracker.keys().copied().collect();
+        ids.sort();
+        ids.into_iter().map(|id| (id, &self.tracker[&id]))
+    }
+
     pub fn get_tracker(&self) -> &<Self as Deref>::Target
     {
         & *self
@@ -100,4 +110,20 @@ where
             None    => Status::Failure,
         }
     }
-}
\ No newline at end of file
+}
+
+impl<I, const MAX: u16> FromIterator<I> for HashMapTracker<I, MAX>
+where
+    I: ForTracker
+{
+    /// Push every item in turn, dropping any past `MAX`
+    fn from_iter<T: IntoIterator<Item = I>>(iter: T) -> Self
+    {
+        let mut tracker = Self::new();
+        for i in iter
+        {
+            tracker.push(i);
+        }
+        tracker
+    }
+}
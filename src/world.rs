@@ -0,0 +1,274 @@
+use std::path::{Path, PathBuf};
+
+use ggez::{glam::Vec2, GameError, GameResult};
+use serde::{Deserialize, Serialize};
+
+// local imports
+use crate::{
+    console::Command,
+    debug_draw::DebugDraw,
+    events::Events,
+    game_object::{
+        cannon::Cannon,
+        collider_type::Collider,
+        enemy::Enemy,
+        grid::{Chunk, ObjectType},
+        missile::Missile,
+        player::Player,
+        turret::Turret,
+        visibility::VisibleCells,
+        HasPosition,
+        HasRegion,
+    },
+    input::action_state::ActionState,
+    schedule::{fixed_system, Schedule},
+    util::hash_map_tracker::HashMapTracker,
+};
+
+/// Everything the player fed into the simulation during one fixed tick.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TickInput
+{
+    pub actions: ActionState,
+    /// Where the mouse points, in world tiles.
+    /// Only kept while it matters (e.g. `Click` is held), to keep recordings small.
+    mouse_world: Option<[f32; 2]>,
+}
+
+impl TickInput
+{
+    pub fn new(actions: ActionState, mouse_world: Option<Vec2>) -> Self
+    {
+        Self { actions, mouse_world: mouse_world.map(|pos| pos.to_array()) }
+    }
+
+    pub fn mouse_world(&self) -> Option<Vec2>
+    {
+        self.mouse_world.map(Vec2::from_array)
+    }
+}
+
+/// Everything the fixed-tick simulation reads and writes.
+///
+/// Kept apart from rendering, windowing and gui state so that it can be
+/// stepped without a ggez context, which is what lets a recorded session
+/// be replayed, and replays be run as tests.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct World
+{
+    /// Seeds anything random in the simulation, so a replay from the same seed plays out the same
+    pub seed: u64,
+    /// Fixed ticks simulated so far
+    pub tick: u64,
+
+    pub cannon: Cannon,
+    pub missiles: HashMapTracker<Missile>,
+    pub chunks: Vec<Chunk>,
+    pub enemies: HashMapTracker<Enemy>,
+    pub turrets: Vec<Turret>,
+    pub player: Player,
+
+    /// Cells the cannon can currently see, recomputed every fixed tick.
+    #[serde(skip)]
+    pub visibility: VisibleCells,
+
+    /// What the fixed systems told each other this tick and the last
+    pub events: Events,
+
+    /// What the fixed systems drew to debug the last tick, so headless ticks can draw too
+    #[serde(skip)]
+    pub debug_draw: DebugDraw,
+
+    /// The fixed systems `step` runs, which can be disabled while debugging.
+    /// Saved as which are disabled, so saves and replays step the same way.
+    #[serde(with = "disabled_systems")]
+    pub systems: Schedule,
+}
+
+impl World
+{
+    /// The starting level
+    pub fn new(seed: u64) -> Self
+    {
+        let mut chunk: Chunk = Chunk::default();
+        let mut chunk2 = chunk.clone();
+        chunk2.upper_left_position = [-16.0, 0.0].into();
+        let mut chunk3 = chunk.clone();
+        chunk3.upper_left_position = [-16.0, -16.0].into();
+        let mut chunk4 = chunk.clone();
+        chunk4.upper_left_position = [0.0, -16.0].into();
+
+        // a floor for the player to stand on
+        for x in 0..16u8
+        {
+            chunk[(x, 15u8).into()].foreground_object = ObjectType::Filled;
+        }
+
+        let chunks = vec![chunk, chunk2, chunk3, chunk4];
+
+        let enemies = [
+            // <Enemy::default()
+            // .position_set((12.0, 5.0).into()) as HasRegion<game_object::collider_type::Collider>>::region_set()
+            // .region_set(((-0.75, -0.75), (0.75, 0.75)).into())
+
+            HasRegion::<Collider>::region_set(
+                Enemy::default().position_set((12.0, 5.0).into()),
+                ((-0.75, -0.75), (0.75, 0.75)).into())
+        ].into_iter().collect();
+
+        Self
+        {
+            seed,
+            tick: 0,
+            cannon: Cannon::default(),
+            missiles: HashMapTracker::new(),
+            chunks,
+            enemies,
+            turrets: Vec::new(),
+            player: Player::new(Vec2::new(8.5, 12.0)),
+            visibility: VisibleCells::default(),
+            events: Events::default(),
+            debug_draw: DebugDraw::default(),
+            systems: World::systems(),
+        }
+    }
+
+    /// Every fixed system, in the order they step the world.
+    /// A `FixedUpdate` not listed here never runs.
+    pub fn systems() -> Schedule
+    {
+        Schedule::new(vec![
+            fixed_system!(Cannon),
+            fixed_system!(Vec<Turret>).after("Cannon"),
+            fixed_system!(Player),
+            fixed_system!(Vec<Chunk>),
+            // spawns the missiles fired this tick, and damages the enemies they hit
+            fixed_system!(HashMapTracker<Missile>).after("Cannon").after("Vec<Turret>").before("HashMapTracker<Enemy>"),
+            fixed_system!(HashMapTracker<Enemy>),
+            fixed_system!(VisibleCells).after("Vec<Chunk>"),
+        ])
+        .expect("the fixed systems are ordered consistently")
+    }
+
+    /// Advance the simulation by one fixed tick.
+    pub fn step(&mut self, input: &TickInput) -> ggez::GameResult
+    {
+        self.events.begin_tick(self.tick);
+        self.debug_draw.clear();
+
+        let mut systems = std::mem::take(&mut self.systems);
+        let result = systems.run_fixed(self, input);
+        self.systems = systems;
+        result?;
+
+        self.tick += 1;
+        Ok(())
+    }
+
+    /// Where the save slot `slot` is kept in `dir`. Slot names are kept to
+    /// letters, digits, `-` and `_`, so they can't point outside of it.
+    pub fn slot_path(dir: &Path, slot: &str) -> GameResult<PathBuf>
+    {
+        if slot.is_empty() || !slot.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(GameError::CustomError(format!("`{slot}` is not a valid slot name")));
+        }
+        Ok(dir.join(format!("{slot}.world")))
+    }
+
+    pub fn to_bytes(&self) -> GameResult<Vec<u8>>
+    {
+        postcard::to_allocvec(self)
+        .map_err(|e| GameError::CustomError(format!("could not serialize world: {e}")))
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> GameResult<Self>
+    {
+        postcard::from_bytes(bytes)
+        .map_err(|e| GameError::CustomError(format!("malformed world: {e}")))
+    }
+
+    /// Console commands saving and loading the world
+    pub fn commands() -> Vec<Command>
+    {
+        vec![
+            Command
+            {
+                name: "save",
+                args: "<slot>",
+                help: "save the world to a slot",
+                run: |args, target|
+                {
+                    let path = World::slot_path(target.save_dir, &args.next::<String>("slot")?)?;
+                    args.finish()?;
+                    std::fs::create_dir_all(target.save_dir)?;
+                    std::fs::write(&path, target.world().to_bytes()?)?;
+                    Ok(format!("saved to {}", path.display()))
+                },
+            },
+            Command
+            {
+                name: "load",
+                args: "<slot>",
+                help: "replace the world with one saved to a slot",
+                run: |args, target|
+                {
+                    let path = World::slot_path(target.save_dir, &args.next::<String>("slot")?)?;
+                    args.finish()?;
+                    *target.world_mut()? = World::from_bytes(&std::fs::read(&path)?)?;
+                    Ok(format!("loaded {}", path.display()))
+                },
+            },
+        ]
+    }
+
+    /// A digest of the simulated state. Equal worlds have equal checksums,
+    /// whatever order their hash maps happen to iterate in.
+    pub fn checksum(&self) -> u64
+    {
+        let missiles: Vec<_> = self.missiles.by_id().collect();
+        let enemies: Vec<_> = self.enemies.by_id().collect();
+
+        let bytes =
+            postcard::to_allocvec(&(self.seed, self.tick, &self.cannon, missiles, &self.chunks, enemies, &self.turrets, &self.player, &self.events))
+            .expect("the world is always serializable");
+
+        // FNV-1a, which unlike std's hasher is guaranteed stable across builds
+        bytes
+        .iter()
+        .fold(0xcbf29ce484222325, |hash, &b| (hash ^ b as u64).wrapping_mul(0x100000001b3))
+    }
+}
+
+/// (De)serializes the fixed systems as the names of those disabled, loading them onto `World::systems`
+mod disabled_systems
+{
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    // local imports
+    use crate::schedule::Schedule;
+    use super::World;
+
+    pub fn serialize<S: Serializer>(systems: &Schedule, serializer: S) -> Result<S::Ok, S::Error>
+    {
+        // postcard needs the length up front, which a filtered iterator doesn't know
+        let disabled: Vec<&str> = systems.systems().filter(|s| !s.is_enabled()).map(|s| s.name()).collect();
+        serializer.collect_seq(disabled)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Schedule, D::Error>
+    {
+        let disabled = Vec::<String>::deserialize(deserializer)?;
+
+        let mut systems = World::systems();
+        if let Some(name) = disabled.iter().find(|name| !systems.systems().any(|s| s.name() == name.as_str()))
+        {
+            return Err(D::Error::custom(format!("there is no fixed system `{name}` to disable")));
+        }
+        for system in systems.systems_mut()
+        {
+            system.set_enabled(!disabled.iter().any(|name| name == system.name()));
+        }
+        Ok(systems)
+    }
+}